@@ -0,0 +1,541 @@
+//! ADS-B Simulator - see README.md
+//
+// © NewForester, 2018.  Available under MIT licence terms.
+//
+//! The build script generates MAVLink message implementations from the MAVLink
+//! XML message definitions (dialect files) in `src/mavlink/definitions`.
+//!
+//! For each message enabled below, it writes `msgNNN.rs` to `OUT_DIR` with:
+//!
+//!   * the message structure, its fields in MAVLink (wire) order,
+//!   * the CRC_EXTRA byte, calculated from the definition as MAVLink does,
+//!   * the implementation of the _mavlink message trait_ (pack, unpack and
+//!     the JSON rendering of fields in engineering units).
+//!
+//! The generated code is included by the hand-written message module of the
+//! same name, which need provide only `new()` and any getters and setters.
+//!
+//! Messages are MAVLink 1 unless their ids do not fit in a byte, in which
+//! case they are MAVLink 2.  Either way extension fields are ignored.
+//!
+//! The XML is parsed by a small purpose built scanner:  the dialect files are
+//! machine generated and very regular so 'necessary and sufficient' applies.
+//!
+use std::cmp::Reverse;
+use std::env;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+// ---------------------------------------------------------------------------
+
+/// The dialect files to be read, along with those they include (all messages found are candidates)
+const DIALECTS: &[&str] = &[
+    "src/mavlink/definitions/uAvionix.xml",
+];
+
+/// The names of the messages for which code is generated:  enable messages here
+const ENABLED: &[&str] = &[
+    "HEARTBEAT",
+    "PARAM_REQUEST_READ",
+    "PARAM_REQUEST_LIST",
+    "PARAM_VALUE",
+    "PARAM_SET",
+    "GPS_RAW_INT",
+    "GLOBAL_POSITION_INT",
+    "MISSION_CURRENT",
+    "MISSION_COUNT",
+    "MISSION_ITEM_REACHED",
+    "MISSION_ACK",
+    "MISSION_REQUEST_INT",
+    "REQUEST_DATA_STREAM",
+    "MISSION_ITEM_INT",
+    "COMMAND_LONG",
+    "COMMAND_ACK",
+    "SET_POSITION_TARGET_LOCAL_NED",
+    "ADSB_VEHICLE",
+    "UAVIONIX_ADSB_OUT_CFG",
+    "UAVIONIX_ADSB_OUT_DYNAMIC",
+    "UAVIONIX_ADSB_TRANSCEIVER_HEALTH_REPORT",
+];
+
+// ---------------------------------------------------------------------------
+
+/// The Field structure holds the definition of one message field
+struct Field {
+    name:           String,
+    mavtype:        String,
+    arraylen:       usize,
+    units:          String,
+    description:    String,
+}
+
+/// The MessageDef structure holds the definition of one message
+struct MessageDef {
+    id:             u32,
+    name:           String,
+    dialect:        String,
+    fields:         Vec<Field>,
+}
+
+// ---------------------------------------------------------------------------
+
+/// The main() routine reads the dialect files and generates the enabled messages
+fn main() {
+    let outdir = env::var("OUT_DIR").unwrap();
+
+    let mut messages: Vec<MessageDef> = Vec::new();
+
+    let mut pending: Vec<PathBuf> = DIALECTS.iter().map(PathBuf::from).collect();
+    let mut read: Vec<PathBuf> = Vec::new();
+
+    while let Some(dialect) = pending.pop() {
+        if read.contains(&dialect) {
+            continue;
+        }
+        println!("cargo:rerun-if-changed={}", dialect.display());
+
+        let xml = fs::read_to_string(&dialect).unwrap_or_else(|e| panic!("{}: {}", dialect.display(), e));
+
+        let filename = dialect.file_name().unwrap().to_string_lossy().into_owned();
+
+        let (defined, includes) = parse_dialect(&xml, &filename);
+
+        messages.extend(defined);
+
+        let directory = dialect.parent().unwrap().to_path_buf();
+
+        pending.extend(includes.iter().map(|include| directory.join(include)));
+
+        read.push(dialect);
+    }
+
+    for name in ENABLED {
+        let message = match messages.iter().find(|m| m.name == *name) {
+            Some(message) => message,
+            None          => panic!("message {} is not defined in any dialect", name),
+        };
+
+        let path = Path::new(&outdir).join(format!("msg{}.rs", message.id));
+
+        let mut file = fs::File::create(&path).unwrap();
+
+        file.write_all(generate(message).as_bytes()).unwrap();
+    }
+}
+
+// ---------------------------------------------------------------------------
+
+// parse_dialect() returns the definitions of all messages in a dialect file and the files it includes
+fn parse_dialect(xml: &str, dialect: &str) -> (Vec<MessageDef>, Vec<String>) {
+    let mut messages: Vec<MessageDef> = Vec::new();
+    let mut includes: Vec<String> = Vec::new();
+
+    let mut message: Option<MessageDef> = None;
+    let mut extensions = false;
+
+    let mut rest = xml;
+
+    while let Some(start) = rest.find('<') {
+        rest = &rest[start ..];
+
+        if rest.starts_with("<!--") {
+            let end = rest.find("-->").expect("unterminated comment");
+            rest = &rest[end + 3 ..];
+            continue;
+        }
+
+        let end = rest.find('>').expect("unterminated tag");
+        let tag = &rest[1 .. end];
+        rest = &rest[end + 1 ..];
+
+        let (tagname, attributes) = split_tag(tag);
+
+        match tagname {
+            "include" => {
+                let end = rest.find("</include>").expect("unterminated include");
+                includes.push(rest[.. end].trim().to_string());
+                rest = &rest[end + 10 ..];
+            }
+            "message" => {
+                message = Some(MessageDef {
+                    id:         attribute(attributes, "id").parse().unwrap(),
+                    name:       attribute(attributes, "name").to_string(),
+                    dialect:    dialect.to_string(),
+                    fields:     Vec::new(),
+                });
+                extensions = false;
+            }
+            "/message" => {
+                if let Some(message) = message.take() {
+                    messages.push(message);
+                }
+            }
+            "extensions/" => {
+                extensions = true;
+            }
+            "field" => {
+                if let Some(ref mut message) = message {
+                    let end = rest.find("</field>").expect("unterminated field");
+                    let description = unescape(rest[.. end].trim());
+                    rest = &rest[end + 8 ..];
+
+                    if extensions {
+                        continue;
+                    }
+
+                    let mavtype = attribute(attributes, "type");
+
+                    let (mavtype, arraylen) = match mavtype.find('[') {
+                        Some(bracket) => (&mavtype[.. bracket], mavtype[bracket + 1 .. mavtype.len() - 1].parse().unwrap()),
+                        None          => (mavtype, 0),
+                    };
+
+                    message.fields.push(Field {
+                        name:           attribute(attributes, "name").to_string(),
+                        mavtype:        mavtype.to_string(),
+                        arraylen,
+                        units:          optional_attribute(attributes, "units").unwrap_or("").to_string(),
+                        description,
+                    });
+                }
+            }
+            _ => (),
+        }
+    }
+
+    (messages, includes)
+}
+
+// split_tag() splits the contents of a tag into its name and its attributes
+fn split_tag(tag: &str) -> (&str, &str) {
+    let tag = tag.trim();
+
+    match tag.find(char::is_whitespace) {
+        Some(space) => {
+            let attributes = tag[space ..].trim();
+
+            if attributes.ends_with('/') && &tag[.. space] == "extensions" {
+                ("extensions/", "")
+            } else {
+                (&tag[.. space], attributes)
+            }
+        }
+        None => (tag, ""),
+    }
+}
+
+// attribute() returns the value of the named attribute (or panics)
+fn attribute<'a>(attributes: &'a str, name: &str) -> &'a str {
+    optional_attribute(attributes, name).unwrap_or_else(|| panic!("attribute {} missing from <{}>", name, attributes))
+}
+
+// optional_attribute() returns the value of the named attribute, if there is one
+fn optional_attribute<'a>(attributes: &'a str, name: &str) -> Option<&'a str> {
+    let pattern = format!("{}=\"", name);
+
+    let mut rest = attributes;
+
+    while let Some(start) = rest.find(&pattern) {
+        if start == 0 || rest[.. start].ends_with(char::is_whitespace) {
+            let value = &rest[start + pattern.len() ..];
+            let end = value.find('"').expect("unterminated attribute");
+
+            return Some(&value[.. end]);
+        }
+        rest = &rest[start + pattern.len() ..];
+    }
+
+    None
+}
+
+// unescape() replaces XML entities and collapses white space
+fn unescape(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<&str>>().join(" ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+// ---------------------------------------------------------------------------
+
+// type_size() returns the size in bytes of a MAVLink (element) type
+fn type_size(mavtype: &str) -> usize {
+    match mavtype {
+        "char" | "int8_t" | "uint8_t" | "uint8_t_mavlink_version" => 1,
+        "int16_t" | "uint16_t" => 2,
+        "int32_t" | "uint32_t" | "float" => 4,
+        "int64_t" | "uint64_t" | "double" => 8,
+        _ => panic!("unknown MAVLink type {}", mavtype),
+    }
+}
+
+// rust_type() returns the Rust type of a MAVLink (element) type
+fn rust_type(mavtype: &str) -> &'static str {
+    match mavtype {
+        "char" | "uint8_t" | "uint8_t_mavlink_version" => "u8",
+        "int8_t" => "i8",
+        "int16_t" => "i16",
+        "uint16_t" => "u16",
+        "int32_t" => "i32",
+        "uint32_t" => "u32",
+        "int64_t" => "i64",
+        "uint64_t" => "u64",
+        "float" => "f32",
+        "double" => "f64",
+        _ => panic!("unknown MAVLink type {}", mavtype),
+    }
+}
+
+// crc_type() returns the MAVLink type as used in the calculation of CRC_EXTRA
+fn crc_type(mavtype: &str) -> &str {
+    match mavtype {
+        "uint8_t_mavlink_version" => "uint8_t",
+        _ => mavtype,
+    }
+}
+
+// rust_name() converts a MAVLink field name to a snake case Rust identifier
+fn rust_name(name: &str) -> String {
+    let chars: Vec<char> = name.chars().collect();
+
+    let mut snake = String::new();
+
+    for ii in 0 .. chars.len() {
+        let cc = chars[ii];
+
+        if cc.is_uppercase() && ii > 0 && chars[ii - 1] != '_' {
+            let after_lower = chars[ii - 1].is_lowercase() || chars[ii - 1].is_numeric();
+            let before_lower = ii + 1 < chars.len() && chars[ii + 1].is_lowercase() && chars[ii - 1].is_uppercase();
+
+            if after_lower || before_lower {
+                snake.push('_');
+            }
+        }
+        snake.extend(cc.to_lowercase());
+    }
+
+    match snake.as_str() {
+        "type" | "yield" | "match" | "mod" | "move" | "ref" | "static" | "struct" | "use" => snake + "_",
+        _ => snake,
+    }
+}
+
+// ---------------------------------------------------------------------------
+
+// wire_order() returns the fields in MAVLink (wire) order:  largest types first, otherwise as declared
+fn wire_order(message: &MessageDef) -> Vec<&Field> {
+    let mut fields: Vec<&Field> = message.fields.iter().collect();
+
+    fields.sort_by_key(|field| Reverse(type_size(&field.mavtype)));
+
+    fields
+}
+
+// crc_accumulate() adds one byte to an X.25 (MCRF4XX) checksum
+fn crc_accumulate(byte: u8, crc: u16) -> u16 {
+    let mut tmp = byte ^ (crc & 0xff) as u8;
+    tmp ^= tmp << 4;
+
+    (crc >> 8) ^ ((tmp as u16) << 8) ^ ((tmp as u16) << 3) ^ ((tmp as u16) >> 4)
+}
+
+// crc_extra() calculates the CRC_EXTRA byte of a message from its definition
+fn crc_extra(message: &MessageDef) -> u8 {
+    let mut crc: u16 = 0xffff;
+
+    let feed = |text: &str, crc: &mut u16| {
+        for byte in text.bytes() {
+            *crc = crc_accumulate(byte, *crc);
+        }
+    };
+
+    feed(&format!("{} ", message.name), &mut crc);
+
+    for field in wire_order(message) {
+        feed(&format!("{} ", crc_type(&field.mavtype)), &mut crc);
+        feed(&format!("{} ", field.name), &mut crc);
+
+        if field.arraylen != 0 {
+            crc = crc_accumulate(field.arraylen as u8, crc);
+        }
+    }
+
+    ((crc & 0xff) ^ (crc >> 8)) as u8
+}
+
+// ---------------------------------------------------------------------------
+
+// generate() returns the Rust source of the implementation of a message
+fn generate(message: &MessageDef) -> String {
+    let fields = wire_order(message);
+
+    let paylen: usize = fields.iter().map(|f| type_size(&f.mavtype) * if f.arraylen != 0 { f.arraylen } else { 1 }).sum();
+
+    let mut code = String::new();
+
+    code.push_str(&format!("// Generated by build.rs from {} in {}:  do not edit\n\n", message.name, message.dialect));
+
+    code.push_str("mod generated {\n");
+    code.push_str("    use std::io::Error;\n\n");
+    code.push_str("    use mavlink;\n");
+    code.push_str("    #[allow(unused_imports)]\n");
+    code.push_str("    use mavlink::byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};\n\n");
+
+    code.push_str(&format!("    /// The length of MAVLink {} messages in bytes\n", message.id));
+    if message.id > 255 {
+        code.push_str(&format!("    const MSGLEN: usize = msglen_v2!({});\n\n", paylen));
+    } else {
+        code.push_str(&format!("    const MSGLEN: usize = msglen!({});\n\n", paylen));
+    }
+
+    code.push_str(&format!("    /// The MAVLink {} ({}) message structure\n", message.id, message.name));
+    code.push_str("    pub struct Message {\n");
+    code.push_str("        buffy: [u8; MSGLEN],\n\n");
+    for field in fields.iter() {
+        code.push_str(&format!("        /// {}\n", field.description));
+        code.push_str(&format!("        pub {}: {},\n", rust_name(&field.name), field_type(field)));
+    }
+    code.push_str("    }\n\n");
+
+    code.push_str(&format!("    /// The default (all zero) MAVLink {} message\n", message.id));
+    code.push_str("    impl Default for Message {\n");
+    code.push_str("        fn default() -> Message {\n");
+    code.push_str("            Message {\n");
+    code.push_str("                buffy: [0; MSGLEN],\n\n");
+    for field in fields.iter() {
+        let zero = match rust_type(&field.mavtype) {
+            "f32" | "f64" => "0.0",
+            _             => "0",
+        };
+        let zero = if field.arraylen != 0 {
+            format!("[{}; {}]", zero, field.arraylen)
+        } else {
+            zero.to_string()
+        };
+        code.push_str(&format!("                {}: {},\n", rust_name(&field.name), zero));
+    }
+    code.push_str("            }\n");
+    code.push_str("        }\n");
+    code.push_str("    }\n\n");
+
+    code.push_str(&format!("    /// The implementation of the MAVLink message traits for the {} message type\n", message.id));
+    code.push_str("    impl mavlink::Message for Message {\n");
+    code.push_str(&format!("        const MSGID: u32 = {};\n", message.id));
+    code.push_str(&format!("        const EXTRA: u8 = {:#04x};\n", crc_extra(message)));
+    code.push_str(&format!("        const PAYLEN: usize = {};\n\n", paylen));
+
+    code.push_str("        fn message(&mut self) -> &mut [u8] {\n");
+    code.push_str("            &mut self.buffy\n");
+    code.push_str("        }\n\n");
+
+    code.push_str("        fn pack_payload(&self, buffy: &mut Vec<u8>) -> Result<(),Error> {\n");
+    for field in fields.iter() {
+        let name = rust_name(&field.name);
+
+        if field.arraylen != 0 {
+            code.push_str(&format!("            for ii in 0 .. {} {{\n", field.arraylen));
+            code.push_str(&format!("                buffy.{}(self.{}[ii])?;\n", write_method(&field.mavtype), name));
+            code.push_str("            }\n");
+        } else {
+            code.push_str(&format!("            buffy.{}(self.{})?;\n", write_method(&field.mavtype), name));
+        }
+    }
+    code.push_str("\n            Ok(())\n");
+    code.push_str("        }\n\n");
+
+    code.push_str("        fn unpack_payload(&mut self) -> Result<(),Error> {\n");
+    code.push_str(&format!("            let mut payload = &self.buffy[mavlink::{}..];\n\n", if message.id > 255 { "PAYLOAD_V2" } else { "PAYLOAD" }));
+    for field in fields.iter() {
+        let name = rust_name(&field.name);
+
+        if field.arraylen != 0 {
+            code.push_str(&format!("            for ii in 0 .. {} {{\n", field.arraylen));
+            code.push_str(&format!("                self.{}[ii] = payload.{}()?;\n", name, read_method(&field.mavtype)));
+            code.push_str("            }\n");
+        } else {
+            code.push_str(&format!("            self.{} = payload.{}()?;\n", name, read_method(&field.mavtype)));
+        }
+    }
+    code.push_str("\n            Ok(())\n");
+    code.push_str("        }\n\n");
+
+    code.push_str("        fn json(&self, json: &mut mavlink::json::Json) {\n");
+    for field in message.fields.iter() {
+        code.push_str(&format!("            {};\n", json_method(field)));
+    }
+    code.push_str("        }\n");
+    code.push_str("    }\n");
+    code.push_str("}\n\n");
+
+    code.push_str("pub use self::generated::Message;\n");
+
+    code
+}
+
+// field_type() returns the Rust type of a field (arrays included)
+fn field_type(field: &Field) -> String {
+    if field.arraylen != 0 {
+        format!("[{}; {}]", rust_type(&field.mavtype), field.arraylen)
+    } else {
+        rust_type(&field.mavtype).to_string()
+    }
+}
+
+// engineering_units() returns the units a scaled integer is better expressed in and the divisor
+fn engineering_units(units: &str) -> Option<(&'static str, &'static str)> {
+    match units {
+        "degE7" => Some(("deg", "1.0e7")),
+        "cdeg"  => Some(("deg", "1.0e2")),
+        "mm"    => Some(("m", "1.0e3")),
+        "cm"    => Some(("m", "1.0e2")),
+        "mm/s"  => Some(("m/s", "1.0e3")),
+        "cm/s"  => Some(("m/s", "1.0e2")),
+        "mrad"  => Some(("rad", "1.0e3")),
+        "cdegC" => Some(("degC", "1.0e2")),
+        "mV"    => Some(("V", "1.0e3")),
+        "cA"    => Some(("A", "1.0e2")),
+        _       => None,
+    }
+}
+
+// json_method() returns the call that adds a field to a JSON rendering (with its MAVLink name)
+fn json_method(field: &Field) -> String {
+    let name = rust_name(&field.name);
+
+    if field.arraylen != 0 {
+        if field.mavtype == "char" {
+            format!("json.text(\"{}\", &self.{})", field.name, name)
+        } else {
+            format!("json.array(\"{}\", &self.{}, \"{}\")", field.name, name, field.units)
+        }
+    } else if rust_type(&field.mavtype).starts_with('f') {
+        format!("json.float(\"{}\", self.{}, \"{}\")", field.name, name, field.units)
+    } else if let Some((units, divisor)) = engineering_units(&field.units) {
+        format!("json.float(\"{}\", self.{} as f64 / {}, \"{}\")", field.name, name, divisor, units)
+    } else {
+        format!("json.integer(\"{}\", self.{}, \"{}\")", field.name, name, field.units)
+    }
+}
+
+// write_method() returns the byteorder method that writes a MAVLink (element) type
+fn write_method(mavtype: &str) -> String {
+    match rust_type(mavtype) {
+        "u8" => "write_u8".to_string(),
+        "i8" => "write_i8".to_string(),
+        rtype => format!("write_{}::<LittleEndian>", rtype),
+    }
+}
+
+// read_method() returns the byteorder method that reads a MAVLink (element) type
+fn read_method(mavtype: &str) -> String {
+    match rust_type(mavtype) {
+        "u8" => "read_u8".to_string(),
+        "i8" => "read_i8".to_string(),
+        rtype => format!("read_{}::<LittleEndian>", rtype),
+    }
+}
+
+// EOF
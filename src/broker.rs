@@ -0,0 +1,856 @@
+//! ADS-B Simulator - see README.md
+//
+// © NewForester, 2018.  Available under MIT licence terms.
+//
+//! The broker module provides the ADS-B Simulator with an optional, embedded
+//! MQTT broker so that a closed-loop scenario can be run without installing
+//! and configuring mosquitto.
+//!
+//! The broker listens for MQTT (3.1 and 3.1.1) over TCP and, for the benefit
+//! of the UI, MQTT over WebSockets.  It adheres to the 'necessary and
+//! sufficient' policy:
+//!
+//!  * topic filters may use the `+` and `#` wildcards;
+//!  * retained messages and last-will messages are supported;
+//!  * messages are accepted at any QoS but are delivered at QoS 0;
+//!  * sessions are not persistent (there is no queuing for absent clients);
+//!  * there is no authentication.
+//!
+//! There is one thread per listener and one thread per connection.  The state
+//! shared by the connection threads (subscriptions and retained messages) is
+//! held in a `Mutex`.
+//!
+//! The `set_cli()` function provides the implementation of the parsing of
+//! certain command line parameters as described in README.md.
+//!
+use std::collections::BTreeMap;
+use std::io;
+use std::io::{Error, ErrorKind, Read, Write};
+use std::net::{Shutdown, TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+// ---------------------------------------------------------------------------
+
+/// The MQTT control packet types the broker handles
+const CONNECT: u8       = 1;
+const CONNACK: u8       = 2;
+const PUBLISH: u8       = 3;
+const PUBACK: u8        = 4;
+const PUBREC: u8        = 5;
+const PUBREL: u8        = 6;
+const PUBCOMP: u8       = 7;
+const SUBSCRIBE: u8     = 8;
+const SUBACK: u8        = 9;
+const UNSUBSCRIBE: u8   = 10;
+const UNSUBACK: u8      = 11;
+const PINGREQ: u8       = 12;
+const PINGRESP: u8      = 13;
+const DISCONNECT: u8    = 14;
+
+/// The largest MQTT packet the broker will accept
+const MAX_PACKET: usize = 256 * 1024;
+
+/// The largest WebSocket control frame payload (RFC 6455)
+const MAX_CONTROL: u64 = 125;
+
+/// The GUID that a WebSocket server appends to the client's key (RFC 6455)
+const WEBSOCKET_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+// ---------------------------------------------------------------------------
+
+/// The Broker structure holds the broker's parameters and the ports it is listening on
+pub struct Broker {
+    host:       String,
+    port:       u16,
+    wsport:     u16,
+
+    state:      Arc<Mutex<State>>,
+}
+
+/// The State structure holds what is shared by all connections
+struct State {
+    nextid:         u64,
+    peers:          BTreeMap<u64, Arc<Mutex<Peer>>>,
+    subscriptions:  Vec<(u64, String)>,
+    retained:       BTreeMap<String, Vec<u8>>,
+}
+
+/// The Peer structure holds the means to send packets to one client
+struct Peer {
+    clientid:   String,
+    stream:     TcpStream,
+    websocket:  bool,
+}
+
+/// The Will structure holds a client's last-will message
+struct Will {
+    topic:      String,
+    payload:    Vec<u8>,
+    retain:     bool,
+}
+
+// ---------------------------------------------------------------------------
+
+/// The implementation of methods for the Broker type
+impl Broker {
+    // new() creates and initialises a Broker structure
+    pub fn new() -> Broker {
+        Broker {
+            host:       "0.0.0.0".to_string(),
+            port:       1883,
+            wsport:     9001,
+            state:      Arc::new(Mutex::new(State {
+                nextid:         0,
+                peers:          BTreeMap::new(),
+                subscriptions:  Vec::new(),
+                retained:       BTreeMap::new(),
+            })),
+        }
+    }
+
+    // set_cli() extract broker parameters (port:wsport) from a CLI parameter string
+    pub fn set_cli(&mut self, cli: &str) -> Result<&mut Self, String> {
+        let pair: Vec<&str> = cli.split(':').collect();
+
+        if pair.len() > 2 {
+            return Err(format!("'{}' is not of the form 'port:wsport'", cli));
+        }
+
+        if ! pair[0].is_empty() {
+            self.port = pair[0].parse().map_err(|_| format!("'{}' is not a port number", pair[0]))?;
+        }
+
+        if pair.len() > 1 && ! pair[1].is_empty() {
+            self.wsport = pair[1].parse().map_err(|_| format!("'{}' is not a port number", pair[1]))?;
+        }
+
+        Ok(self)
+    }
+
+    // set_host() sets the address the broker listens on (by default, all)
+    pub fn set_host(&mut self, host: &str) -> &mut Self {
+        self.host = host.to_string();
+
+        self
+    }
+
+    // get_port() returns the MQTT port (once started, the port actually listened on)
+    pub fn get_port(&self) -> u16 {
+        self.port
+    }
+
+    // get_wsport() returns the WebSocket port, 0 if there is none (once started, the port actually listened on)
+    pub fn get_wsport(&self) -> u16 {
+        self.wsport
+    }
+
+    // start() opens the listeners and starts the threads that accept connections
+    pub fn start(&mut self) -> Result<&mut Self, Error> {
+        let listener = TcpListener::bind((self.host.as_str(), self.port))?;
+
+        self.port = listener.local_addr()?.port();
+
+        Self::listen(listener, false, &self.state);
+
+        if self.wsport != 0 {
+            let listener = TcpListener::bind((self.host.as_str(), self.wsport))?;
+
+            self.wsport = listener.local_addr()?.port();
+
+            Self::listen(listener, true, &self.state);
+        }
+
+        Ok(self)
+    }
+
+    // listen() starts a thread that accepts connections and starts a thread for each
+    fn listen(listener: TcpListener, websocket: bool, state: &Arc<Mutex<State>>) {
+        let state = state.clone();
+
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let stream = match stream {
+                    Ok(stream) => stream,
+                    Err(e) => {
+                        println!("Broker accept error: {}", e);
+                        continue;
+                    }
+                };
+
+                let state = state.clone();
+
+                thread::spawn(move || {
+                    let address = stream.peer_addr().map(|a| a.to_string()).unwrap_or_default();
+
+                    if let Err(e) = Self::serve(stream, websocket, &state) {
+                        println!("Broker connection {} closed: {}", address, e);
+                    }
+                });
+            }
+        });
+    }
+
+    // serve() handles one connection from start to finish
+    fn serve(stream: TcpStream, websocket: bool, state: &Arc<Mutex<State>>) -> Result<(), Error> {
+        stream.set_write_timeout(Some(Duration::new(5, 0)))?;
+        stream.set_nodelay(true)?;
+
+        let peer = Arc::new(Mutex::new(Peer {clientid: String::new(), stream: stream.try_clone()?, websocket}));
+
+        let mut reader: Box<dyn Read> =
+            if websocket {
+                let mut stream = stream.try_clone()?;
+
+                handshake(&mut stream)?;
+
+                Box::new(WebSocketReader {stream, peer: peer.clone(), remaining: 0, mask: [0; 4], index: 0})
+            } else {
+                Box::new(stream)
+            };
+
+        // the first packet must be a CONNECT
+        let (header, body) = read_packet(&mut *reader)?;
+
+        if header >> 4 != CONNECT {
+            return Err(Error::new(ErrorKind::InvalidData, "first packet is not CONNECT"));
+        }
+
+        let (clientid, mut will) = parse_connect(&body)?;
+
+        peer.lock().unwrap().clientid = clientid.clone();
+
+        let id = {
+            let mut state = state.lock().unwrap();
+
+            state.nextid += 1;
+
+            let id = state.nextid;
+
+            state.peers.insert(id, peer.clone());
+
+            id
+        };
+
+        peer.lock().unwrap().send(&[CONNACK << 4, 2, 0, 0])?;
+
+        println!("Broker client '{}' connected", clientid);
+
+        let result = Self::converse(id, &peer, &mut *reader, &mut will, state);
+
+        // tidy up - and tell the world when the client went away without saying goodbye
+        {
+            let mut state = state.lock().unwrap();
+
+            state.peers.remove(&id);
+            state.subscriptions.retain(|&(subscriber, _)| subscriber != id);
+        }
+
+        if let Some(will) = will {
+            Self::publish(state, &will.topic, &will.payload, will.retain);
+        }
+
+        let _ = peer.lock().unwrap().stream.shutdown(Shutdown::Both);
+
+        println!("Broker client '{}' disconnected", clientid);
+
+        result
+    }
+
+    // converse() handles the packets received from a client until it disconnects
+    fn converse(id: u64, peer: &Arc<Mutex<Peer>>, reader: &mut dyn Read, will: &mut Option<Will>, state: &Arc<Mutex<State>>) -> Result<(), Error> {
+        loop {
+            let (header, body) = read_packet(reader)?;
+
+            match header >> 4 {
+                PUBLISH => {
+                    let qos = (header >> 1) & 0x03;
+                    let retain = header & 0x01 != 0;
+
+                    let mut body = &body[..];
+
+                    let topic = read_string(&mut body)?;
+
+                    if qos > 0 {
+                        let packetid = read_u16(&mut body)?;
+
+                        let reply = if qos == 1 { PUBACK } else { PUBREC };
+
+                        peer.lock().unwrap().send(&[reply << 4, 2, (packetid >> 8) as u8, packetid as u8])?;
+                    }
+
+                    Self::publish(state, &topic, body, retain);
+                }
+                PUBREL => {
+                    let packetid = read_u16(&mut &body[..])?;
+
+                    peer.lock().unwrap().send(&[PUBCOMP << 4, 2, (packetid >> 8) as u8, packetid as u8])?;
+                }
+                PUBACK | PUBREC | PUBCOMP => {
+                    // nothing is ever sent at QoS 1 or 2 so there should be none of these
+                }
+                SUBSCRIBE => {
+                    let mut body = &body[..];
+
+                    let packetid = read_u16(&mut body)?;
+
+                    let mut granted = vec![(packetid >> 8) as u8, packetid as u8];
+                    let mut filters = Vec::new();
+
+                    while ! body.is_empty() {
+                        let filter = read_string(&mut body)?;
+
+                        read_u8(&mut body)?;
+
+                        granted.push(0);
+                        filters.push(filter);
+                    }
+
+                    let mut reply = vec![SUBACK << 4];
+
+                    push_length(&mut reply, granted.len());
+                    reply.extend(granted);
+
+                    // record the subscriptions before the SUBACK lest the client publish first
+                    let retained: Vec<(String, Vec<u8>)> = {
+                        let mut state = state.lock().unwrap();
+
+                        for filter in filters.iter() {
+                            if ! state.subscriptions.iter().any(|&(subscriber, ref f)| subscriber == id && f == filter) {
+                                state.subscriptions.push((id, filter.clone()));
+                            }
+                        }
+
+                        state.retained.iter()
+                            .filter(|&(topic, _)| filters.iter().any(|filter| matches(filter, topic)))
+                            .map(|(topic, payload)| (topic.clone(), payload.clone()))
+                            .collect()
+                    };
+
+                    let mut peer = peer.lock().unwrap();
+
+                    peer.send(&reply)?;
+
+                    for (topic, payload) in retained.iter() {
+                        peer.send(&publish_packet(topic, payload, true))?;
+                    }
+                }
+                UNSUBSCRIBE => {
+                    let mut body = &body[..];
+
+                    let packetid = read_u16(&mut body)?;
+
+                    let mut filters = Vec::new();
+
+                    while ! body.is_empty() {
+                        filters.push(read_string(&mut body)?);
+                    }
+
+                    state.lock().unwrap().subscriptions.retain(|&(subscriber, ref f)| subscriber != id || ! filters.contains(f));
+
+                    peer.lock().unwrap().send(&[UNSUBACK << 4, 2, (packetid >> 8) as u8, packetid as u8])?;
+                }
+                PINGREQ => {
+                    peer.lock().unwrap().send(&[PINGRESP << 4, 0])?;
+                }
+                DISCONNECT => {
+                    *will = None;
+
+                    return Ok(());
+                }
+                other => {
+                    return Err(Error::new(ErrorKind::InvalidData, format!("unexpected packet type {}", other)));
+                }
+            }
+        }
+    }
+
+    // publish() delivers a message to all subscribers (once each) and retains it if asked to
+    fn publish(state: &Arc<Mutex<State>>, topic: &str, payload: &[u8], retain: bool) {
+        let peers: Vec<Arc<Mutex<Peer>>> = {
+            let mut state = state.lock().unwrap();
+
+            if retain {
+                if payload.is_empty() {
+                    state.retained.remove(topic);
+                } else {
+                    state.retained.insert(topic.to_string(), payload.to_vec());
+                }
+            }
+
+            let mut subscribers: Vec<u64> = state.subscriptions.iter()
+                .filter(|&(_, filter)| matches(filter, topic))
+                .map(|&(subscriber, _)| subscriber)
+                .collect();
+
+            subscribers.sort();
+            subscribers.dedup();
+
+            subscribers.iter().filter_map(|id| state.peers.get(id).cloned()).collect()
+        };
+
+        let packet = publish_packet(topic, payload, false);
+
+        for peer in peers {
+            let mut peer = peer.lock().unwrap();
+
+            if let Err(e) = peer.send(&packet) {
+                println!("Broker send to '{}' failed: {}", peer.clientid, e);
+            }
+        }
+    }
+}
+
+/// The implementation of the Default trait for the Broker type
+impl Default for Broker {
+    fn default() -> Broker {
+        Broker::new()
+    }
+}
+
+// ---------------------------------------------------------------------------
+
+/// The implementation of methods for the Peer type
+impl Peer {
+    // send() sends one MQTT packet (in a WebSocket frame if need be)
+    fn send(&mut self, packet: &[u8]) -> Result<(), Error> {
+        if self.websocket {
+            self.send_frame(0x2, packet)
+        } else {
+            self.stream.write_all(packet)
+        }
+    }
+
+    // send_frame() sends one (final, unmasked) WebSocket frame with the given opcode
+    fn send_frame(&mut self, opcode: u8, payload: &[u8]) -> Result<(), Error> {
+        let mut frame = vec![0x80 | opcode];
+
+        if payload.len() < 126 {
+            frame.push(payload.len() as u8);
+        } else if payload.len() < 65536 {
+            frame.push(126);
+            frame.push((payload.len() >> 8) as u8);
+            frame.push(payload.len() as u8);
+        } else {
+            frame.push(127);
+            for ii in (0 .. 8).rev() {
+                frame.push((payload.len() as u64 >> (8 * ii)) as u8);
+            }
+        }
+
+        frame.extend_from_slice(payload);
+
+        self.stream.write_all(&frame)
+    }
+}
+
+// ---------------------------------------------------------------------------
+
+/// The WebSocketReader structure unwraps the payload of the WebSocket frames received from a client
+/// (and answers its pings by way of the peer)
+struct WebSocketReader {
+    stream:     TcpStream,
+    peer:       Arc<Mutex<Peer>>,
+    remaining:  u64,
+    mask:       [u8; 4],
+    index:      usize,
+}
+
+impl Read for WebSocketReader {
+    // read() reads payload bytes, reading frame headers as need be
+    fn read(&mut self, buffy: &mut [u8]) -> Result<usize, Error> {
+        while self.remaining == 0 {
+            let mut header = [0u8; 2];
+
+            self.stream.read_exact(&mut header)?;
+
+            let opcode = header[0] & 0x0f;
+
+            let mut length = (header[1] & 0x7f) as u64;
+
+            if length == 126 {
+                let mut extended = [0u8; 2];
+                self.stream.read_exact(&mut extended)?;
+                length = (extended[0] as u64) << 8 | extended[1] as u64;
+            } else if length == 127 {
+                let mut extended = [0u8; 8];
+                self.stream.read_exact(&mut extended)?;
+                length = extended.iter().fold(0, |acc, &b| acc << 8 | b as u64);
+            }
+
+            if header[1] & 0x80 != 0 {
+                self.stream.read_exact(&mut self.mask)?;
+            } else {
+                self.mask = [0; 4];
+            }
+
+            self.index = 0;
+
+            match opcode {
+                0x0 | 0x2 => {
+                    self.remaining = length;
+                }
+                0x8 => {
+                    return Ok(0);
+                }
+                0x9 | 0xa if length > MAX_CONTROL => {
+                    return Err(Error::new(ErrorKind::InvalidData, "WebSocket control frame too long"));
+                }
+                0x9 => {
+                    let mut payload = vec![0u8; length as usize];
+
+                    self.stream.read_exact(&mut payload)?;
+
+                    for (ii, byte) in payload.iter_mut().enumerate() {
+                        *byte ^= self.mask[ii % 4];
+                    }
+
+                    self.peer.lock().unwrap().send_frame(0xa, &payload)?;
+                }
+                _ => {
+                    // text frames and pongs:  discard (without believing the length)
+                    io::copy(&mut (&mut self.stream).take(length), &mut io::sink())?;
+                }
+            }
+        }
+
+        let count = buffy.len().min(self.remaining as usize);
+        let count = self.stream.read(&mut buffy[.. count])?;
+
+        if count == 0 {
+            return Ok(0);
+        }
+
+        for byte in buffy[.. count].iter_mut() {
+            *byte ^= self.mask[self.index % 4];
+            self.index += 1;
+        }
+
+        self.remaining -= count as u64;
+
+        Ok(count)
+    }
+}
+
+// ---------------------------------------------------------------------------
+
+// matches() returns whether a topic matches a topic filter (which may have wildcards)
+pub fn matches(filter: &str, topic: &str) -> bool {
+    let mut levels = topic.split('/');
+
+    for part in filter.split('/') {
+        match (part, levels.next()) {
+            ("#", _) => return true,
+            ("+", Some(_)) => (),
+            (part, Some(level)) if part == level => (),
+            _ => return false,
+        }
+    }
+
+    levels.next().is_none()
+}
+
+// publish_packet() returns an MQTT PUBLISH packet (QoS 0)
+fn publish_packet(topic: &str, payload: &[u8], retain: bool) -> Vec<u8> {
+    let length = 2 + topic.len() + payload.len();
+
+    let mut packet = vec![PUBLISH << 4 | if retain { 1 } else { 0 }];
+
+    push_length(&mut packet, length);
+
+    packet.push((topic.len() >> 8) as u8);
+    packet.push(topic.len() as u8);
+    packet.extend_from_slice(topic.as_bytes());
+    packet.extend_from_slice(payload);
+
+    packet
+}
+
+// push_length() appends the remaining length of an MQTT packet (a variable length integer)
+fn push_length(packet: &mut Vec<u8>, length: usize) {
+    let mut remaining = length;
+
+    loop {
+        let mut byte = (remaining % 128) as u8;
+
+        remaining /= 128;
+
+        if remaining > 0 {
+            byte |= 0x80;
+        }
+
+        packet.push(byte);
+
+        if remaining == 0 {
+            break;
+        }
+    }
+}
+
+// read_packet() reads one MQTT packet and returns its first byte and its body
+fn read_packet(reader: &mut dyn Read) -> Result<(u8, Vec<u8>), Error> {
+    let header = read_byte(reader)?;
+
+    let mut length = 0usize;
+    let mut shift = 0;
+
+    loop {
+        let byte = read_byte(reader)?;
+
+        length |= ((byte & 0x7f) as usize) << shift;
+
+        if byte & 0x80 == 0 {
+            break;
+        }
+
+        shift += 7;
+
+        if shift > 21 {
+            return Err(Error::new(ErrorKind::InvalidData, "malformed remaining length"));
+        }
+    }
+
+    if length > MAX_PACKET {
+        return Err(Error::new(ErrorKind::InvalidData, "packet too large"));
+    }
+
+    let mut body = vec![0u8; length];
+
+    reader.read_exact(&mut body)?;
+
+    Ok((header, body))
+}
+
+// read_byte() reads one byte, treating the end of the stream as an error
+fn read_byte(reader: &mut dyn Read) -> Result<u8, Error> {
+    let mut byte = [0u8; 1];
+
+    reader.read_exact(&mut byte)?;
+
+    Ok(byte[0])
+}
+
+// read_u8() takes a byte from the front of a packet body
+fn read_u8(body: &mut &[u8]) -> Result<u8, Error> {
+    if body.is_empty() {
+        return Err(Error::new(ErrorKind::UnexpectedEof, "packet too short"));
+    }
+
+    let byte = body[0];
+
+    *body = &body[1 ..];
+
+    Ok(byte)
+}
+
+// read_u16() takes a big endian u16 from the front of a packet body
+fn read_u16(body: &mut &[u8]) -> Result<u16, Error> {
+    Ok((read_u8(body)? as u16) << 8 | read_u8(body)? as u16)
+}
+
+// read_bytes() takes length prefixed bytes from the front of a packet body
+fn read_bytes(body: &mut &[u8]) -> Result<Vec<u8>, Error> {
+    let length = read_u16(body)? as usize;
+
+    if body.len() < length {
+        return Err(Error::new(ErrorKind::UnexpectedEof, "packet too short"));
+    }
+
+    let bytes = body[.. length].to_vec();
+
+    *body = &body[length ..];
+
+    Ok(bytes)
+}
+
+// read_string() takes a length prefixed UTF-8 string from the front of a packet body
+fn read_string(body: &mut &[u8]) -> Result<String, Error> {
+    String::from_utf8(read_bytes(body)?).map_err(|_| Error::new(ErrorKind::InvalidData, "string is not UTF-8"))
+}
+
+// parse_connect() returns the client id and last-will message of a CONNECT packet
+fn parse_connect(body: &[u8]) -> Result<(String, Option<Will>), Error> {
+    let mut body = body;
+
+    let protocol = read_string(&mut body)?;
+    let level = read_u8(&mut body)?;
+
+    if ! (protocol == "MQTT" && level == 4 || protocol == "MQIsdp" && level == 3) {
+        return Err(Error::new(ErrorKind::InvalidData, format!("unsupported protocol {} {}", protocol, level)));
+    }
+
+    let flags = read_u8(&mut body)?;
+
+    read_u16(&mut body)?;               // keep alive:  not enforced
+
+    let clientid = read_string(&mut body)?;
+
+    let will =
+        if flags & 0x04 != 0 {
+            Some(Will {
+                topic:      read_string(&mut body)?,
+                payload:    read_bytes(&mut body)?,
+                retain:     flags & 0x20 != 0,
+            })
+        } else {
+            None
+        };
+
+    Ok((clientid, will))
+}
+
+// ---------------------------------------------------------------------------
+
+// handshake() performs the server side of the WebSocket opening handshake
+fn handshake(stream: &mut TcpStream) -> Result<(), Error> {
+    let mut request = Vec::new();
+
+    while ! request.ends_with(b"\r\n\r\n") {
+        let mut byte = [0u8; 1];
+
+        stream.read_exact(&mut byte)?;
+
+        request.push(byte[0]);
+
+        if request.len() > 8192 {
+            return Err(Error::new(ErrorKind::InvalidData, "WebSocket request too long"));
+        }
+    }
+
+    let request = String::from_utf8_lossy(&request);
+
+    let mut key = None;
+    let mut protocol = None;
+
+    for line in request.lines() {
+        let mut pair = line.splitn(2, ':');
+
+        let name = pair.next().unwrap_or("").trim().to_lowercase();
+        let value = pair.next().unwrap_or("").trim();
+
+        if name == "sec-websocket-key" {
+            key = Some(value.to_string());
+        }
+
+        if name == "sec-websocket-protocol" {
+            protocol = value.split(',').next().map(|p| p.trim().to_string());
+        }
+    }
+
+    let key = match key {
+        Some(key) => key,
+        None => {
+            stream.write_all(b"HTTP/1.1 400 Bad Request\r\n\r\n")?;
+            return Err(Error::new(ErrorKind::InvalidData, "not a WebSocket request"));
+        }
+    };
+
+    let mut response = format!(
+        "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: {}\r\n",
+        accept(&key));
+
+    if let Some(protocol) = protocol {
+        response.push_str(&format!("Sec-WebSocket-Protocol: {}\r\n", protocol));
+    }
+
+    response.push_str("\r\n");
+
+    stream.write_all(response.as_bytes())
+}
+
+// accept() returns the Sec-WebSocket-Accept value for a Sec-WebSocket-Key
+pub fn accept(key: &str) -> String {
+    let mut text = key.as_bytes().to_vec();
+
+    text.extend_from_slice(WEBSOCKET_GUID.as_bytes());
+
+    base64(&sha1(&text))
+}
+
+// sha1() returns the SHA-1 digest of a byte array (FIPS 180-4)
+fn sha1(message: &[u8]) -> [u8; 20] {
+    let mut h: [u32; 5] = [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476, 0xc3d2e1f0];
+
+    let mut padded = message.to_vec();
+
+    padded.push(0x80);
+
+    while padded.len() % 64 != 56 {
+        padded.push(0);
+    }
+
+    for ii in (0 .. 8).rev() {
+        padded.push(((message.len() as u64 * 8) >> (8 * ii)) as u8);
+    }
+
+    for block in padded.chunks(64) {
+        let mut w = [0u32; 80];
+
+        for ii in 0 .. 16 {
+            w[ii] = (block[4 * ii] as u32) << 24 | (block[4 * ii + 1] as u32) << 16 | (block[4 * ii + 2] as u32) << 8 | block[4 * ii + 3] as u32;
+        }
+
+        for ii in 16 .. 80 {
+            w[ii] = (w[ii - 3] ^ w[ii - 8] ^ w[ii - 14] ^ w[ii - 16]).rotate_left(1);
+        }
+
+        let (mut a, mut b, mut c, mut d, mut e) = (h[0], h[1], h[2], h[3], h[4]);
+
+        for (ii, word) in w.iter().enumerate() {
+            let (f, k) = match ii {
+                0  ..= 19 => ((b & c) | (!b & d), 0x5a827999),
+                20 ..= 39 => (b ^ c ^ d, 0x6ed9eba1),
+                40 ..= 59 => ((b & c) | (b & d) | (c & d), 0x8f1bbcdc),
+                _         => (b ^ c ^ d, 0xca62c1d6),
+            };
+
+            let temp = a.rotate_left(5).wrapping_add(f).wrapping_add(e).wrapping_add(k).wrapping_add(*word);
+
+            e = d;
+            d = c;
+            c = b.rotate_left(30);
+            b = a;
+            a = temp;
+        }
+
+        h[0] = h[0].wrapping_add(a);
+        h[1] = h[1].wrapping_add(b);
+        h[2] = h[2].wrapping_add(c);
+        h[3] = h[3].wrapping_add(d);
+        h[4] = h[4].wrapping_add(e);
+    }
+
+    let mut digest = [0u8; 20];
+
+    for ii in 0 .. 20 {
+        digest[ii] = (h[ii / 4] >> (24 - 8 * (ii % 4))) as u8;
+    }
+
+    digest
+}
+
+// base64() returns the base 64 encoding of a byte array (RFC 4648)
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut text = String::new();
+
+    for chunk in bytes.chunks(3) {
+        let triple = (chunk[0] as u32) << 16
+                   | (*chunk.get(1).unwrap_or(&0) as u32) << 8
+                   | *chunk.get(2).unwrap_or(&0) as u32;
+
+        for ii in 0 .. 4 {
+            if ii <= chunk.len() {
+                text.push(ALPHABET[(triple >> (18 - 6 * ii)) as usize & 0x3f] as char);
+            } else {
+                text.push('=');
+            }
+        }
+    }
+
+    text
+}
+
+// EOF
@@ -0,0 +1,323 @@
+//! ADS-B Simulator - see README.md
+//
+// © NewForester, 2018.  Available under MIT licence terms.
+//
+//! The cli module parses the ADS-B Simulator's command line parameters.
+//!
+//! Each option may be given in the original form `-uav=value` or by name in
+//! the more usual forms `--uav=value` and `--uav value`.  Some options have
+//! a long name as well (`-mq` is also `--mqtt`, `-mqtls` is also `--mqtt-tls`
+//! and `-i` is also `--inet`).
+//!
+//! The parsing of option values is delegated to the `set_cli()` functions of
+//! the modules concerned.  These return an error that identifies the bad token
+//! and this module adds the option to it so that the user is told exactly
+//! what is wrong and where.  Nothing here panics on malformed human input.
+//!
+use broker::Broker;
+use coords::CwithV;
+use gdl90;
+use inet::Inet;
+use linkstats::LinkStats;
+use mavlink::Link;
+use mavlink::signing::Signing;
+use mqtt::Client;
+use recovery::Recovery;
+use sitl::Sitl;
+use snapshot::Snapshot;
+use transport::{Serial, Stdout, Tlog};
+
+// ---------------------------------------------------------------------------
+
+/// The options:  short name, long name, value and description
+const OPTIONS: &[(&str, &str, &str, &str)] = &[
+    ("uav",     "uav",      "(x y z),(vx vy vz)",   "start position and velocity of the UAV"),
+    ("ufo",     "ufo",      "(x y z),(vx vy vz)",   "start position and velocity of the UFO"),
+    ("sitl",    "sitl",     "port:host",            "take the UAV position from a SITL autopilot over UDP"),
+    ("recover", "recover",  "strategy:hold",        "how the UAV gets back to its route after avoidance (default resume:1)"),
+    ("mq",      "mqtt",     "id,host:port,pub:subs", "MQTT client id, broker, publish topic and subscriptions"),
+    ("mqauth",  "mqtt-auth", "user:password",       "MQTT user name and password"),
+    ("mqtls",   "mqtt-tls", "cafile[,cert,key]",    "connect to the MQTT broker over TLS"),
+    ("mqqos",   "mqtt-qos", "qos,topic:qos,...",    "MQTT QoS by default and by topic (default 0 publish, 1 subscribe)"),
+    ("mqretain", "mqtt-retain", "topic,...",        "publish messages to these topics retained"),
+    ("mqwill",  "mqtt-will", "topic:message",       "MQTT last will (default pubTopic/status:offline)"),
+    ("mqretry", "mqtt-retry", "delay:maxdelay",     "MQTT reconnection backoff in seconds (default 1:30)"),
+    ("mqbuffer", "mqtt-buffer", "messages",         "messages to keep while disconnected from MQTT (default 0)"),
+    ("mqtemplate", "mqtt-template", "pub;sub",      "MQTT topic templates (default {pubtopic}/{msgid};/{icao}/{msgid})"),
+    ("mqjson",  "mqtt-json", "template",            "also publish messages as JSON (default template {pubtopic}/{msgid}/json)"),
+    ("icao",    "icao",     "hex",                  "ICAO address of the UAV (default the MQTT pubTopic)"),
+    ("i",       "inet",     "src:dst:host",         "send messages over UDP"),
+    ("serial",  "serial",   "device:baud",          "send messages over a serial line"),
+    ("tlog",    "tlog",     "file",                 "write messages to a telemetry log file"),
+    ("stdout",  "stdout",   "msgid,...",            "print messages (all if none listed) in hex"),
+    ("broker",  "broker",   "port:wsport",          "run an embedded MQTT broker (WebSocket port 0 for none)"),
+    ("gdl90",   "gdl90",    "host:port",            "also send GDL 90 messages over UDP"),
+    ("json",    "json",     "directory",            "also write aircraft.json snapshots"),
+    ("id",      "id",       "sysid:compid",         "MAVLink system and component ids"),
+    ("sign",    "sign",     "key:linkid:badevery",  "sign MAVLink 2 messages (and insist 84 and 202 messages are signed)"),
+    ("stats",   "stats",    "seconds:all",          "link statistics interval (0 for never), :all if every message is received"),
+    ("h",       "help",     "",                     "print this help and exit"),
+];
+
+// ---------------------------------------------------------------------------
+
+/// The Options structure holds everything the command line parameters configure
+pub struct Options {
+    pub help:       bool,
+
+    pub mqtt:       Client,
+    pub inet:       Option<Inet>,
+    pub serial:     Option<Serial>,
+    pub tlog:       Option<Tlog>,
+    pub stdout:     Option<Stdout>,
+    pub broker:     Option<Broker>,
+
+    pub uav:        CwithV,
+    pub ufo:        Option<CwithV>,
+    pub sitl:       Option<Sitl>,
+    pub recovery:   Recovery,
+
+    pub gdl90:      Option<gdl90::Output>,
+    pub snapshot:   Option<Snapshot>,
+
+    pub link:       Link,
+    pub signing:    Option<Signing>,
+    pub linkstats:  LinkStats,
+}
+
+// ---------------------------------------------------------------------------
+
+/// The implementation of methods for the Options type
+impl Options {
+    // new() creates and initialises an Options structure (all defaults)
+    pub fn new() -> Options {
+        Options {
+            help:       false,
+            mqtt:       Client::new(),
+            inet:       None,
+            serial:     None,
+            tlog:       None,
+            stdout:     None,
+            broker:     None,
+            uav:        CwithV::new(),
+            ufo:        None,
+            sitl:       None,
+            recovery:   Recovery::new(),
+            gdl90:      None,
+            snapshot:   None,
+            link:       Link::new(),
+            signing:    None,
+            linkstats:  LinkStats::new(),
+        }
+    }
+
+    // parse() sets the options from the command line parameters (not including the program name)
+    pub fn parse<I>(&mut self, arguments: I) -> Result<&mut Self, String>
+                where I: IntoIterator<Item = String> {
+        let mut arguments = arguments.into_iter();
+
+        while let Some(argument) = arguments.next() {
+            if ! argument.starts_with('-') || argument == "-" || argument == "--" {
+                return Err(format!("unexpected parameter '{}'", argument));
+            }
+
+            let (name, value) = match argument.find('=') {
+                Some(equals) => (argument[.. equals].to_string(), Some(argument[equals + 1 ..].to_string())),
+                None         => (argument.clone(), None),
+            };
+
+            let option = Self::lookup(&name).ok_or_else(|| format!("unknown option '{}'", name))?;
+
+            if option.1 == "help" {
+                self.help = true;
+                continue;
+            }
+
+            let value = match value {
+                Some(value) => value,
+                None => {
+                    if ! name.starts_with("--") {
+                        return Err(format!("option '{}' needs a value:  {}={}", name, name, option.2));
+                    }
+
+                    arguments.next().ok_or_else(|| format!("option '{}' needs a value:  {} {}", name, name, option.2))?
+                }
+            };
+
+            self.set(option.1, &value).map_err(|e| format!("bad {} parameter '{}': {}", name, value, e))?;
+        }
+
+        Ok(self)
+    }
+
+    // usage() returns the help text
+    pub fn usage() -> String {
+        let mut text = String::from("Usage: adsbsim [option=value] ...\n\nOptions:\n");
+
+        for &(short, long, value, description) in OPTIONS {
+            let names = format!("-{}, --{}", short, long);
+
+            let names = if value.is_empty() { names } else { format!("{}={}", names, value) };
+
+            text.push_str(&format!("  {:40} {}\n", names, description));
+        }
+
+        text.push_str("\nSee README.md for the details.\n");
+
+        text
+    }
+
+    // lookup() returns the option with the given (short or long) name
+    fn lookup(name: &str) -> Option<&'static (&'static str, &'static str, &'static str, &'static str)> {
+        if name.starts_with("--") {
+            OPTIONS.iter().find(|option| option.1 == &name[2 ..])
+        } else {
+            OPTIONS.iter().find(|option| option.0 == &name[1 ..])
+        }
+    }
+
+    // set() sets one option (by long name) from its value
+    fn set(&mut self, option: &str, value: &str) -> Result<(), String> {
+        match option {
+            "uav" => {
+                self.uav.set_cli(value)?;
+            }
+            "ufo" => {
+                let mut ufo = CwithV::new();
+
+                ufo.set_cli(value)?;
+
+                self.ufo = Some(ufo);
+            }
+            "sitl" => {
+                let mut input = Sitl::new();
+
+                input.set_cli(value)?;
+
+                self.sitl = Some(input);
+            }
+            "recover" => {
+                self.recovery.set_cli(value)?;
+            }
+            "mqtt" => {
+                self.mqtt.set_cli(value)?;
+            }
+            "mqtt-auth" => {
+                self.mqtt.set_auth_cli(value)?;
+            }
+            "mqtt-tls" => {
+                self.mqtt.set_tls_cli(value)?;
+            }
+            "mqtt-qos" => {
+                self.mqtt.set_qos_cli(value)?;
+            }
+            "mqtt-retain" => {
+                self.mqtt.set_retain_cli(value)?;
+            }
+            "mqtt-will" => {
+                self.mqtt.set_will_cli(value)?;
+            }
+            "mqtt-template" => {
+                self.mqtt.set_template_cli(value)?;
+            }
+            "mqtt-json" => {
+                self.mqtt.set_json_cli(value)?;
+            }
+            "icao" => {
+                self.mqtt.set_icao_cli(value)?;
+            }
+            "mqtt-retry" => {
+                self.mqtt.set_retry_cli(value)?;
+            }
+            "mqtt-buffer" => {
+                self.mqtt.set_buffer_cli(value)?;
+            }
+            "inet" => {
+                let mut output = Inet::new();
+
+                output.set_cli(value)?;
+
+                self.inet = Some(output);
+            }
+            "serial" => {
+                let mut output = Serial::new();
+
+                output.set_cli(value)?;
+
+                self.serial = Some(output);
+            }
+            "tlog" => {
+                let mut output = Tlog::new();
+
+                output.set_cli(value);
+
+                self.tlog = Some(output);
+            }
+            "stdout" => {
+                let mut output = Stdout::new();
+
+                output.set_cli(value)?;
+
+                self.stdout = Some(output);
+            }
+            "broker" => {
+                let mut broker = Broker::new();
+
+                broker.set_cli(value)?;
+
+                self.broker = Some(broker);
+            }
+            "gdl90" => {
+                let mut output = gdl90::Output::new();
+
+                output.set_cli(value)?;
+
+                self.gdl90 = Some(output);
+            }
+            "json" => {
+                let mut output = Snapshot::new();
+
+                output.set_cli(value);
+
+                self.snapshot = Some(output);
+            }
+            "id" => {
+                let pair: Vec<&str> = value.split(':').collect();
+
+                if pair.len() != 2 {
+                    return Err(format!("'{}' is not of the form 'sysid:compid'", value));
+                }
+
+                let sysid = pair[0].parse().map_err(|_| format!("'{}' is not a system id (0 to 255)", pair[0]))?;
+                let compid = pair[1].parse().map_err(|_| format!("'{}' is not a component id (0 to 255)", pair[1]))?;
+
+                self.link.set_source(sysid, compid);
+                self.mqtt.set_source(sysid, compid);
+            }
+            "sign" => {
+                let mut signing = Signing::new();
+
+                signing.set_cli(value)?;
+
+                self.signing = Some(signing);
+            }
+            "stats" => {
+                self.linkstats.set_cli(value)?;
+            }
+            _ => {
+                return Err(format!("option '{}' is not implemented", option));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// The implementation of the Default trait for the Options type
+impl Default for Options {
+    fn default() -> Options {
+        Options::new()
+    }
+}
+
+// EOF
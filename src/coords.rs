@@ -57,65 +57,85 @@ impl CwithV {
         }
     }
 
-    fn drop_end_characters(string: &str) ->  &str {
-        &string[1 .. string.len() - 1]
+    fn drop_end_characters(string: &str) -> Result<&str, String> {
+        let trimmed = string.trim();
+
+        if trimmed.starts_with('(') && trimmed.ends_with(')') && trimmed.len() > 1 {
+            Ok(&trimmed[1 .. trimmed.len() - 1])
+        } else {
+            Err(format!("'{}' is not of the form '(a b c)'", string))
+        }
     }
 
-    fn drop_final_character(string: &str) ->  &str {
-        &string[.. string.len() - 1]
+    fn convert_or(string: &str, default: f32) -> Result<f32, String> {
+        if string.is_empty() {
+            Ok(default)
+        } else {
+            f32::from_str(string).map_err(|_| format!("'{}' is not a number", string))
+        }
     }
 
-    fn convert_or(string: &str, default: f32) -> f32 {
-        f32::from_str(string).unwrap_or(default)
+    fn split_triple(string: &str) -> Result<Vec<&str>, String> {
+        let mut triple: Vec<&str> = Self::drop_end_characters(string)?.split(' ').collect();
+
+        if triple.len() > 3 {
+            return Err(format!("'{}' has more than three values", string));
+        }
+
+        triple.resize(3, "");
+
+        Ok(triple)
     }
 
-    const HOME_LAT: f32 = 51.10116770;
-    const HOME_LONG: f32 = -2.05134590;
+    const HOME_LAT: f32 = 51.10117;
+    const HOME_LONG: f32 = -2.0513458;
     const HOME_ALT: f32 = 128.0;
 
     // set_cli() (re)initialises a CwithV structure from a CLI parameter string
-    pub fn set_cli(&mut self, cli: &str) -> &mut Self {
-        let fission: Vec<&str> = cli.split(',').collect();
+    pub fn set_cli(&mut self, cli: &str) -> Result<&mut Self, String> {
+        let fission: Vec<&str> = cli.splitn(2, "),").collect();
 
-        let coords: Vec<&str> = Self::drop_end_characters(fission[0]).split(" ").collect();
+        let position = if fission.len() > 1 { format!("{})", fission[0]) } else { fission[0].to_string() };
+
+        let coords = Self::split_triple(&position)?;
 
         if coords[0].ends_with("m") {
-            let metres = Self::drop_final_character(coords[0]);
+            let metres = &coords[0][.. coords[0].len() - 1];
 
-            self.latitude = Self::get_latitude_m(Self::HOME_LAT) + Self::convert_or(metres, 0.0);
+            self.latitude = Self::get_latitude_m(Self::HOME_LAT) + Self::convert_or(metres, 0.0)?;
         }
         else
         {
-            self.latitude = Self::get_latitude_m(Self::convert_or(coords[0], Self::HOME_LAT));
+            self.latitude = Self::get_latitude_m(Self::convert_or(coords[0], Self::HOME_LAT)?);
         }
 
         if coords[1].ends_with("m") {
-            let metres = Self::drop_final_character(coords[1]);
+            let metres = &coords[1][.. coords[1].len() - 1];
 
-            self.longitude = Self::get_longitude_m(Self::HOME_LONG) + Self::convert_or(metres, 0.0);
+            self.longitude = Self::get_longitude_m(Self::HOME_LONG) + Self::convert_or(metres, 0.0)?;
         }
         else
         {
-            self.longitude = Self::get_longitude_m(Self::convert_or(coords[1], Self::HOME_LONG));
+            self.longitude = Self::get_longitude_m(Self::convert_or(coords[1], Self::HOME_LONG)?);
         }
 
         if coords[2].ends_with("m") {
-            let metres = Self::drop_final_character(coords[2]);
+            let metres = &coords[2][.. coords[2].len() - 1];
 
-            self.altitude = Self::HOME_ALT + Self::convert_or(metres, 0.0);
+            self.altitude = Self::HOME_ALT + Self::convert_or(metres, 0.0)?;
         }
         else
         {
-            self.altitude = Self::convert_or(coords[2], Self::HOME_ALT);
+            self.altitude = Self::convert_or(coords[2], Self::HOME_ALT)?;
         }
 
-        let vels: Vec<&str> = Self::drop_end_characters(fission[1]).split(" ").collect();
+        let vels = if fission.len() > 1 { Self::split_triple(fission[1])? } else { vec![""; 3] };
 
-        self.ns_velocity = Self::convert_or(vels[0], 0.0);
-        self.ew_velocity = Self::convert_or(vels[1], 0.0);
-        self.ud_velocity = Self::convert_or(vels[2], 0.0);
+        self.ns_velocity = Self::convert_or(vels[0], 0.0)?;
+        self.ew_velocity = Self::convert_or(vels[1], 0.0)?;
+        self.ud_velocity = Self::convert_or(vels[2], 0.0)?;
 
-        self
+        Ok(self)
     }
 
     // set_position() sets the 3D Cartesian position in m (converting latitude and longitude from degrees to m)
@@ -145,11 +165,84 @@ impl CwithV {
         self
     }
 
-    // update_position() increments the 3D position by the 3D velocity
+    // update_position() increments the 3D position by the 3D velocity (one second's worth)
     pub fn update_position(&mut self) -> &mut Self {
-        self.latitude  += self.ns_velocity;
-        self.longitude += self.ew_velocity;
-        self.altitude  += self.ud_velocity;
+        self.advance_position(1.0)
+    }
+
+    // advance_position() increments the 3D position by the 3D velocity for the given number of seconds
+    pub fn advance_position(&mut self, seconds: f32) -> &mut Self {
+        self.latitude  += self.ns_velocity * seconds;
+        self.longitude += self.ew_velocity * seconds;
+        self.altitude  += self.ud_velocity * seconds;
+
+        self
+    }
+
+    // displace() moves the 3D position by the given distances in m (north, east and up)
+    pub fn displace(&mut self, north: f32, east: f32, up: f32) -> &mut Self {
+        self.latitude  += north;
+        self.longitude += east;
+        self.altitude  += up;
+
+        self
+    }
+
+    // distance() returns the 3D distance in m to another CwithV structure
+    pub fn distance(&self, other: &CwithV) -> f32 {
+        let ns = (self.latitude - other.latitude) as f64;
+        let ew = (self.longitude - other.longitude) as f64;
+        let ud = (self.altitude - other.altitude) as f64;
+
+        (ns * ns + ew * ew + ud * ud).sqrt() as f32
+    }
+
+    // towards() returns the position the given distance in m from this one towards (or beyond) another
+    pub fn towards(&self, other: &CwithV, distance: f32) -> CwithV {
+        let mut position = self.clone();
+        let length = self.distance(other);
+
+        if length != 0.0 {
+            let scale = distance / length;
+
+            position.displace((other.latitude - self.latitude) * scale,
+                              (other.longitude - self.longitude) * scale,
+                              (other.altitude - self.altitude) * scale);
+        }
+
+        position
+    }
+
+    // along() returns how far in m along the line from one position towards another is the point on it nearest this one
+    // (negative if behind the first)
+    pub fn along(&self, from: &CwithV, to: &CwithV) -> f32 {
+        let length = from.distance(to);
+
+        if length == 0.0 {
+            return 0.0;
+        }
+
+        let dot = (self.latitude - from.latitude) as f64 * (to.latitude - from.latitude) as f64
+                + (self.longitude - from.longitude) as f64 * (to.longitude - from.longitude) as f64
+                + (self.altitude - from.altitude) as f64 * (to.altitude - from.altitude) as f64;
+
+        (dot / length as f64) as f32
+    }
+
+    // head_for() sets the 3D velocity to head straight for another position at the given speed in m/s
+    // (but no faster than would reach it in the given number of seconds)
+    pub fn head_for(&mut self, other: &CwithV, speed: f32, seconds: f32) -> &mut Self {
+        let distance = self.distance(other);
+
+        if distance == 0.0 {
+            return self.set_velocity(0.0, 0.0, 0.0);
+        }
+
+        let scale = speed.min(distance / seconds) / distance;
+
+        self.ns_velocity = (other.latitude - self.latitude) * scale;
+        self.ew_velocity = (other.longitude - self.longitude) * scale;
+        self.ud_velocity = (other.altitude - self.altitude) * scale;
 
         self
     }
@@ -211,8 +304,8 @@ impl CwithV {
         (ew_velocity * ew_velocity + ns_velocity * ns_velocity).sqrt() as f32
     }
 
-    const DEGREE_TO_M_LAT: f32 = 111229.0639884;
-    const DEGREE_TO_M_LONG: f32 = 71695.7536163;
+    const DEGREE_TO_M_LAT: f32 = 111229.06;
+    const DEGREE_TO_M_LONG: f32 = 71695.75;
 
     fn get_latitude_m(latitude_degrees: f32) -> f32 {
         latitude_degrees * Self::DEGREE_TO_M_LAT
@@ -229,4 +322,11 @@ impl CwithV {
     }
 }
 
+/// The implementation of the Default trait for the CwithV type
+impl Default for CwithV {
+    fn default() -> CwithV {
+        CwithV::new()
+    }
+}
+
 // EOF
@@ -0,0 +1,366 @@
+//! ADS-B Simulator - see README.md
+//
+// © NewForester, 2018.  Available under MIT licence terms.
+//
+//! The gdl90 module provides the ADS-B Simulator with an alternative output
+//! protocol:  GDL 90 as spoken by many EFBs and autopilot integrations.
+//!
+//! The encoder functions map the content of the MAVLink 202 (ownship) and
+//! 246 (traffic report) messages into GDL 90 messages.  They do not use the
+//! position and velocity of the craft directly so what goes out as GDL 90
+//! is exactly what goes out as MAVLink.
+//!
+//! Each message is framed with flag bytes, has a CRC and is byte-stuffed
+//! as described in the GDL 90 Data Interface Specification (560-1058-00).
+//!
+//! The `set_cli()` function provides the implementation of the parsing of
+//! certain command line parameters as described in README.md.
+//!
+//! Only the Heartbeat (0), Ownship Report (10), Ownship Geometric Altitude (11)
+//! and Traffic Report (20) messages are implemented.
+//!
+extern crate chrono;
+
+use self::chrono::{Timelike, Utc};
+
+use std::io::Error;
+use std::net::UdpSocket;
+
+use mavlink;
+
+// ---------------------------------------------------------------------------
+
+/// The GDL 90 message ids of the messages implemented
+const HEARTBEAT: u8 = 0;
+const OWNSHIP_REPORT: u8 = 10;
+const OWNSHIP_GEOMETRIC_ALTITUDE: u8 = 11;
+const TRAFFIC_REPORT: u8 = 20;
+
+/// The GDL 90 frame flag byte and the control-escape byte used for stuffing
+const FLAG: u8 = 0x7e;
+const ESCAPE: u8 = 0x7d;
+
+/// The 'not available' values of the 12 bit fields in the (traffic) reports
+const ALTITUDE_INVALID: u16 = 0xfff;
+const VELOCITY_INVALID: u16 = 0xfff;
+const VERTICAL_INVALID: u16 = 0x800;
+
+/// The flag of the MAVLink 246 message that says the vertical velocity is valid
+const VERTICAL_VELOCITY_VALID: u16 = 0x0080;
+
+/// The ADS-B emitter category the simulator reports for its own craft unless told otherwise (UAV)
+pub const EMITTER_UAV: u8 = 14;
+
+// ---------------------------------------------------------------------------
+
+/// The Output structure holds the UDP socket GDL 90 messages are sent from
+pub struct Output {
+    host:       String,
+    port:       u32,
+
+    socket:     Option<UdpSocket>,
+}
+
+// ---------------------------------------------------------------------------
+
+/// The implementation of methods for the Output type
+impl Output {
+    // new() creates and initialises an Output structure
+    pub fn new() -> Output {
+        Output {
+            host:       "127.0.0.1".to_string(),
+            port:       4000,
+            socket:     None,
+        }
+    }
+
+    // set_cli() extract GDL 90 parameters from a CLI parameter string
+    pub fn set_cli(&mut self, cli: &str) -> Result<&mut Self, String> {
+        let pair: Vec<&str> = cli.split(':').collect();
+
+        if pair.len() > 2 {
+            return Err(format!("'{}' is not of the form 'host:port'", cli));
+        }
+
+        if ! pair[0].is_empty() {
+            self.host = pair[0].to_string();
+        }
+
+        if pair.len() > 1 && ! pair[1].is_empty() {
+            self.port = pair[1].parse().map_err(|_| format!("'{}' is not a port number", pair[1]))?;
+        }
+
+        Ok(self)
+    }
+
+    // get_address() returns the INET socket address GDL 90 messages are sent to
+    pub fn get_address(&self) -> String {
+        format!("{}:{}", self.host, self.port)
+    }
+
+    // open() binds the socket GDL 90 messages are sent from (broadcast is allowed)
+    pub fn open(&mut self) -> Result<&mut Self, Error> {
+        let socket = UdpSocket::bind("0.0.0.0:0")?;
+
+        socket.set_broadcast(true)?;
+
+        self.socket = Some(socket);
+
+        Ok(self)
+    }
+
+    // send() sends one framed GDL 90 message
+    pub fn send(&self, message: &[u8]) -> Result<usize, Error> {
+        match self.socket {
+            Some(ref socket) => socket.send_to(message, self.get_address()),
+            None             => Ok(0),
+        }
+    }
+}
+
+/// The implementation of the Default trait for the Output type
+impl Default for Output {
+    fn default() -> Output {
+        Output::new()
+    }
+}
+
+// ---------------------------------------------------------------------------
+
+// heartbeat() returns a framed GDL 90 heartbeat message
+pub fn heartbeat(gps_valid: bool) -> Vec<u8> {
+    let timestamp = Utc::now().num_seconds_from_midnight();
+
+    let mut status1: u8 = 0x01;             // UAT initialised
+    let mut status2: u8 = 0x01;             // UTC OK
+
+    if gps_valid {
+        status1 |= 0x80;
+    }
+    if timestamp & 0x10000 != 0 {
+        status2 |= 0x80;
+    }
+
+    frame(HEARTBEAT, &[
+        status1,
+        status2,
+        timestamp as u8,
+        (timestamp >> 8) as u8,
+        0x00,                               // no uplink or basic/long messages received
+        0x00,
+    ])
+}
+
+// ownship_report() returns a framed GDL 90 ownship report for a MAVLink 202 message
+// (the MAVLink and GDL 90 emitter categories are one and the same)
+pub fn ownship_report(ownship: &mavlink::msg202::Message, icao: u32, callsign: &str, emitter: u8) -> Vec<u8> {
+    let nsvog = ownship.nsvog as f32 / 1.0e2;
+    let ewvog = ownship.ewvog as f32 / 1.0e2;
+
+    let track = ewvog.atan2(nsvog).to_degrees();
+
+    let report = Report {
+        icao,
+        latitude:       ownship.latitude,
+        longitude:      ownship.longitude,
+        altitude:       ownship.altpres,
+        groundspeed:    (nsvog * nsvog + ewvog * ewvog).sqrt(),
+        rateofclimb:    ownship.velvert as f32 / 1.0e2,
+        track:          if track < 0.0 { track + 360.0 } else { track },
+        emitter,
+        callsign:       callsign.as_bytes(),
+    };
+
+    frame(OWNSHIP_REPORT, &report.pack())
+}
+
+// ownship_geometric_altitude() returns a framed GDL 90 geometric altitude message for a MAVLink 202 message
+pub fn ownship_geometric_altitude(ownship: &mavlink::msg202::Message) -> Vec<u8> {
+    // geometric altitude is height above the WGS-84 ellipsoid:  that is the GNSS altitude
+    let altitude = (metres_to_feet(ownship.altgnss as f32 / 1.0e3) / 5.0).round() as i16;
+
+    frame(OWNSHIP_GEOMETRIC_ALTITUDE, &[
+        (altitude >> 8) as u8,
+        altitude as u8,
+        0x7f,                               // VFOM not available, no warning
+        0xff,
+    ])
+}
+
+// traffic_report() returns a framed GDL 90 traffic report for a MAVLink 246 message
+pub fn traffic_report(traffic: &mavlink::msg246::Message) -> Vec<u8> {
+    let callsign: Vec<u8> = traffic.callsign.iter().cloned().take_while(|&c| c != 0).collect();
+
+    let report = Report {
+        icao:           traffic.icao_address,
+        latitude:       traffic.lat,
+        longitude:      traffic.lon,
+        altitude:       traffic.altitude,
+        groundspeed:    traffic.hor_velocity as f32 / 1.0e2,
+        rateofclimb:    if traffic.flags & VERTICAL_VELOCITY_VALID != 0 { traffic.ver_velocity as f32 / 1.0e2 } else { f32::NAN },
+        track:          traffic.heading as f32 / 1.0e2,
+        emitter:        traffic.emitter_type,
+        callsign:       &callsign,
+    };
+
+    frame(TRAFFIC_REPORT, &report.pack())
+}
+
+// ---------------------------------------------------------------------------
+
+/// The Report structure holds the fields common to ownship and traffic reports (in MAVLink units)
+struct Report<'a> {
+    icao:           u32,
+    latitude:       i32,
+    longitude:      i32,
+    altitude:       i32,
+    groundspeed:    f32,
+    rateofclimb:    f32,
+    track:          f32,
+    emitter:        u8,
+    callsign:       &'a [u8],
+}
+
+/// The implementation of methods for the Report type
+impl<'a> Report<'a> {
+    // pack() returns the 27 byte payload of an ownship or traffic report
+    fn pack(&self) -> Vec<u8> {
+        let mut buffy: Vec<u8> = Vec::with_capacity(27);
+
+        buffy.push(0x00);                   // no alert, ADS-B with ICAO address
+        push24(&mut buffy, self.icao);
+
+        push24(&mut buffy, semicircles(self.latitude));
+        push24(&mut buffy, semicircles(self.longitude));
+
+        let altitude = Self::pack_altitude(self.altitude);
+        buffy.push((altitude >> 4) as u8);
+        buffy.push(((altitude & 0x0f) << 4) as u8 | 0x09);     // airborne, true track
+
+        buffy.push(0xa9);                   // NIC 10, NACp 9
+
+        let groundspeed = Self::pack_groundspeed(self.groundspeed);
+        let rateofclimb = Self::pack_rateofclimb(self.rateofclimb);
+        buffy.push((groundspeed >> 4) as u8);
+        buffy.push(((groundspeed & 0x0f) << 4) as u8 | ((rateofclimb >> 8) & 0x0f) as u8);
+        buffy.push(rateofclimb as u8);
+
+        buffy.push((self.track * 256.0 / 360.0).round() as u32 as u8);
+        buffy.push(self.emitter);
+
+        for ii in 0 .. 8 {
+            let cc = if ii < self.callsign.len() { self.callsign[ii] } else { b' ' };
+
+            buffy.push(if cc.is_ascii_alphanumeric() { cc.to_ascii_uppercase() } else { b' ' });
+        }
+
+        buffy.push(0x00);                   // no emergency
+
+        buffy
+    }
+
+    // pack_altitude() converts an altitude in mm to 25 ft increments offset by -1000 ft
+    fn pack_altitude(altitude: i32) -> u16 {
+        let feet = metres_to_feet(altitude as f32 / 1.0e3);
+
+        if ! (-1000.0 ..= 101350.0).contains(&feet) {
+            ALTITUDE_INVALID
+        } else {
+            ((feet + 1000.0) / 25.0).round() as u16
+        }
+    }
+
+    // pack_groundspeed() converts a speed in m/s to knots
+    fn pack_groundspeed(speed: f32) -> u16 {
+        let knots = (speed * 1.943844).round();
+
+        if knots < 0.0 {
+            VELOCITY_INVALID
+        } else if knots > 4094.0 {
+            0xffe
+        } else {
+            knots as u16
+        }
+    }
+
+    // pack_rateofclimb() converts a vertical speed in m/s to units of 64 ft/min
+    fn pack_rateofclimb(rateofclimb: f32) -> u16 {
+        let units = (metres_to_feet(rateofclimb) * 60.0 / 64.0).round();
+
+        if units.is_nan() {
+            VERTICAL_INVALID
+        } else if units > 510.0 {
+            0x1fe
+        } else if units < -510.0 {
+            0xe02
+        } else {
+            (units as i16 as u16) & 0xfff
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+
+// metres_to_feet() converts a length in m to ft
+fn metres_to_feet(metres: f32) -> f32 {
+    metres / 0.3048
+}
+
+// semicircles() converts a scaled integer angle (degrees * 1e7) to 24 bit 'semicircles'
+fn semicircles(angle: i32) -> u32 {
+    ((angle as f64 / 1.0e7) * (0x800000 as f64 / 180.0)).round() as i32 as u32 & 0xffffff
+}
+
+// push24() appends the least significant 24 bits of a value most significant byte first
+fn push24(buffy: &mut Vec<u8>, value: u32) {
+    buffy.push((value >> 16) as u8);
+    buffy.push((value >> 8) as u8);
+    buffy.push(value as u8);
+}
+
+// crc() calculates the GDL 90 CRC-CCITT of a message id and data
+pub fn crc(message: &[u8]) -> u16 {
+    let mut crc: u16 = 0;
+
+    for byte in message {
+        let mut entry = crc >> 8 << 8;
+
+        for _bit in 0 .. 8 {
+            entry = (entry << 1) ^ if entry & 0x8000 != 0 { 0x1021 } else { 0 };
+        }
+
+        crc = entry ^ (crc << 8) ^ *byte as u16;
+    }
+
+    crc
+}
+
+// frame() returns a message id and data with CRC added, byte-stuffed and flagged
+pub fn frame(msgid: u8, data: &[u8]) -> Vec<u8> {
+    let mut message: Vec<u8> = Vec::with_capacity(data.len() + 3);
+
+    message.push(msgid);
+    message.extend_from_slice(data);
+
+    let crc = crc(&message);
+
+    message.push(crc as u8);
+    message.push((crc >> 8) as u8);
+
+    let mut buffy: Vec<u8> = Vec::with_capacity(message.len() * 2 + 2);
+
+    buffy.push(FLAG);
+    for byte in message {
+        if byte == FLAG || byte == ESCAPE {
+            buffy.push(ESCAPE);
+            buffy.push(byte ^ 0x20);
+        } else {
+            buffy.push(byte);
+        }
+    }
+    buffy.push(FLAG);
+
+    buffy
+}
+
+// EOF
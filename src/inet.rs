@@ -0,0 +1,103 @@
+//! ADS-B Simulator - see README.md
+//
+// © NewForester, 2018.  Available under MIT licence terms.
+//
+//! The inet module provides the ADS-B Simulator with the means to send its
+//! MAVLink messages over UDP instead of MQTT.
+//!
+//! The simulator binds to the source port to 'reserve' it.  It does not
+//! receive messages on this port.
+//!
+//! The `set_cli()` function provides the implementation of the parsing of
+//! certain command line parameters as described in README.md.  It checks
+//! only their syntax:  the host is looked up (once) when the socket is opened.
+//!
+use std::io::{Error, ErrorKind};
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+
+// ---------------------------------------------------------------------------
+
+/// The Inet structure holds the UDP socket messages are sent from and where they are sent
+pub struct Inet {
+    ouraddress: String,
+    dstaddress: String,
+
+    socket:     Option<(UdpSocket, SocketAddr)>,
+}
+
+// ---------------------------------------------------------------------------
+
+/// The implementation of methods for the Inet type
+impl Inet {
+    // new() creates and initialises an Inet structure
+    pub fn new() -> Inet {
+        Inet {
+            ouraddress: "0.0.0.0:0".to_string(),
+            dstaddress: "127.0.0.1:14550".to_string(),
+            socket:     None,
+        }
+    }
+
+    // set_cli() extract UDP network parameters from a CLI parameter string
+    pub fn set_cli(&mut self, cli: &str) -> Result<&mut Self, String> {
+        let fission: Vec<&str> = cli.split(':').collect();
+
+        if fission.len() < 2 || fission.len() > 3 {
+            return Err(format!("'{}' is not of the form 'src:dst:host'", cli));
+        }
+
+        let src: u16 = fission[0].parse().map_err(|_| format!("'{}' is not a port number", fission[0]))?;
+        let dst: u16 = fission[1].parse().map_err(|_| format!("'{}' is not a port number", fission[1]))?;
+
+        let host = if fission.len() == 2 || fission[2].is_empty() { "127.0.0.1" } else { fission[2] };
+
+        if ! host.chars().all(|cc| cc.is_ascii_alphanumeric() || cc == '-' || cc == '.') {
+            return Err(format!("'{}' is not a host name or address", host));
+        }
+
+        // any local address so that messages may go to other hosts as well as this one
+        self.ouraddress = format!("0.0.0.0:{}", src);
+        self.dstaddress = format!("{}:{}", host, dst);
+
+        Ok(self)
+    }
+
+    // get_address() returns the socket address messages are sent to
+    pub fn get_address(&self) -> &str {
+        &self.dstaddress
+    }
+
+    // get_source() returns the socket address messages are sent from
+    pub fn get_source(&self) -> &str {
+        &self.ouraddress
+    }
+
+    // open() looks up the socket address messages are sent to and binds the UDP socket they are sent from
+    pub fn open(&mut self) -> Result<&mut Self, Error> {
+        let dstsocket = match self.dstaddress.to_socket_addrs()?.next() {
+            Some(dstsocket) => dstsocket,
+            None            => return Err(Error::new(ErrorKind::NotFound, format!("'{}' is not a known host", self.dstaddress))),
+        };
+
+        self.socket = Some((UdpSocket::bind(&self.ouraddress)?, dstsocket));
+
+        Ok(self)
+    }
+
+    // send() sends one message to the destination address
+    pub fn send(&self, message: &[u8]) -> Result<usize, Error> {
+        match self.socket {
+            Some((ref socket, dstsocket)) => socket.send_to(message, dstsocket),
+            None                          => Ok(0),
+        }
+    }
+}
+
+/// The implementation of the Default trait for the Inet type
+impl Default for Inet {
+    fn default() -> Inet {
+        Inet::new()
+    }
+}
+
+// EOF
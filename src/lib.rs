@@ -0,0 +1,37 @@
+//! ADS-B Simulator - see README.md
+//
+// © NewForester, 2018.  Available under MIT licence terms.
+//
+//! The adsbsim library is everything the ADS-B Simulator is made of less its
+//! main routine so that others may reuse the parts in their own programs and
+//! test harnesses.
+//!
+//! The parts most likely to be of use are:
+//!
+//!  * `coords::CwithV` - the position and velocity of a craft and its kinematics
+//!  * `mavlink` - the MAVLink message encoders/decoders and the `Link` context
+//!  * `gdl90` - the GDL 90 encoders
+//!  * `transport` - the transports the simulator sends messages over (`mqtt`, `inet` and others)
+//!
+//! The public API of these modules is that described by their doc comments.
+//! It will only change in a backward incompatible manner with a change of
+//! (minor) version number while the version is 0.x.
+//!
+pub mod broker;
+pub mod cli;
+pub mod coords;
+pub mod gdl90;
+pub mod inet;
+pub mod linkstats;
+pub mod mavlink;
+pub mod mission;
+pub mod mqtt;
+pub mod peers;
+pub mod rates;
+pub mod recovery;
+pub mod simulator;
+pub mod sitl;
+pub mod snapshot;
+pub mod transport;
+
+// EOF
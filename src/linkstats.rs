@@ -0,0 +1,189 @@
+//! ADS-B Simulator - see README.md
+//
+// © NewForester, 2018.  Available under MIT licence terms.
+//
+//! The linkstats module keeps link quality statistics for the MAVLink
+//! messages the ADS-B Simulator receives so that odd UAV behaviour can be
+//! told apart from lost avoidance commands.
+//!
+//! Statistics are kept per source (system and component id in the message
+//! header).  For each source, the module counts the messages received, the
+//! messages lost (gaps in the sequence numbers), duplicates (a sequence number
+//! repeated), checksum failures and, when messages must be signed, signature
+//! failures.  Frames are counted as they arrive, MAVLink 1 or 2, before they
+//! are converted or their signatures checked.
+//!
+//! The sequence number is per source (link), not per message id, so a gap only
+//! means loss if every message the source sends is received.  With the default
+//! MQTT topics, one per message id, the simulator subscribes to only some of
+//! them so gaps are not counted unless told (by `set_cli()`) that the whole
+//! link is received, as it is, say, over UDP or with a wildcard subscription.
+//!
+//! The `set_cli()` function provides the implementation of the parsing of
+//! certain command line parameters as described in README.md.
+//!
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+use mavlink;
+
+// ---------------------------------------------------------------------------
+
+/// The Source structure holds the statistics of one source
+struct Source {
+    lastseqno:  u8,
+    received:   u64,
+    lost:       u64,
+    duplicates: u64,
+    crcfails:   u64,
+    sigfails:   u64,
+}
+
+// ---------------------------------------------------------------------------
+
+/// The LinkStats structure holds the statistics of all sources (keyed by system and component id)
+pub struct LinkStats {
+    interval:   Duration,
+    wholelink:  bool,
+    lastreport: Option<Instant>,
+    sources:    BTreeMap<(u8, u8), Source>,
+}
+
+// ---------------------------------------------------------------------------
+
+/// The implementation of methods for the LinkStats type
+impl LinkStats {
+    // new() creates and initialises a LinkStats structure
+    pub fn new() -> LinkStats {
+        LinkStats {
+            interval:   Duration::new(10, 0),
+            wholelink:  false,
+            lastreport: None,
+            sources:    BTreeMap::new(),
+        }
+    }
+
+    // get_lost() returns the number of messages lost from a source (always 0 unless the whole link is received)
+    pub fn get_lost(&self, sysid: u8, compid: u8) -> u64 {
+        self.sources.get(&(sysid, compid)).map_or(0, |source| source.lost)
+    }
+
+    // get_crcfails() returns the number of messages from a source discarded for a bad checksum
+    pub fn get_crcfails(&self, sysid: u8, compid: u8) -> u64 {
+        self.sources.get(&(sysid, compid)).map_or(0, |source| source.crcfails)
+    }
+
+    // get_sigfails() returns the number of messages from a source rejected for a bad (or missing) signature
+    pub fn get_sigfails(&self, sysid: u8, compid: u8) -> u64 {
+        self.sources.get(&(sysid, compid)).map_or(0, |source| source.sigfails)
+    }
+
+    // set_cli() extract the reporting interval (in seconds, 0 for never) and whether the whole link is received from a CLI parameter string
+    pub fn set_cli(&mut self, cli: &str) -> Result<&mut Self, String> {
+        let pair: Vec<&str> = cli.split(':').collect();
+
+        if pair.len() > 2 || (pair.len() > 1 && pair[1] != "all") {
+            return Err(format!("'{}' is not of the form 'seconds:all'", cli));
+        }
+
+        let seconds = pair[0].parse().map_err(|_| format!("'{}' is not a number of seconds", pair[0]))?;
+
+        self.interval = Duration::new(seconds, 0);
+        self.wholelink = pair.len() > 1;
+
+        Ok(self)
+    }
+
+    // received() updates the statistics for a received message and returns whether its checksum is good
+    pub fn received(&mut self, buffy: &[u8]) -> bool {
+        let (seqno, source) = match (mavlink::seqno(buffy), mavlink::source(buffy)) {
+            (Some(seqno), Some(source)) => (seqno, source),
+            _                           => return false,
+        };
+
+        let source = self.sources.entry(source).or_insert(Source {
+            lastseqno:  seqno,
+            received:   0,
+            lost:       0,
+            duplicates: 0,
+            crcfails:   0,
+            sigfails:   0,
+        });
+
+        if mavlink::check_crc(buffy) == Some(false) {
+            source.crcfails += 1;
+
+            return false;
+        }
+
+        if source.received != 0 {
+            if seqno == source.lastseqno {
+                source.duplicates += 1;
+            } else if self.wholelink {
+                source.lost += seqno.wrapping_sub(source.lastseqno.wrapping_add(1)) as u64;
+            }
+        }
+
+        source.received += 1;
+        source.lastseqno = seqno;
+
+        true
+    }
+
+    // rejected() counts a received message whose signature is bad (or missing)
+    pub fn rejected(&mut self, buffy: &[u8]) -> &mut Self {
+        if let Some(source) = mavlink::source(buffy).and_then(|source| self.sources.get_mut(&source)) {
+            source.sigfails += 1;
+        }
+
+        self
+    }
+
+    // report() prints the statistics of all sources if the reporting interval has passed
+    pub fn report(&mut self, now: Instant) -> &mut Self {
+        if self.interval.as_secs() == 0 {
+            return self;
+        }
+
+        match self.lastreport {
+            Some(lastreport) if now.duration_since(lastreport) < self.interval => return self,
+            Some(_) => (),
+            None => {
+                self.lastreport = Some(now);
+                return self;
+            }
+        }
+
+        self.lastreport = Some(now);
+
+        for (&(sysid, compid), source) in self.sources.iter() {
+            let expected = source.received + source.lost;
+
+            let lost = if self.wholelink {
+                format!("{} lost ({:.1}%)", source.lost, if expected == 0 { 0.0 } else { 100.0 * source.lost as f64 / expected as f64 })
+            } else {
+                "loss not known".to_string()
+            };
+
+            println!("link {}/{}: {} received, {}, {} duplicates, {} CRC failures, {} signature failures",
+                sysid, compid,
+                source.received,
+                lost,
+                source.duplicates,
+                source.crcfails,
+                source.sigfails,
+            );
+        }
+
+        self
+    }
+}
+
+/// The implementation of the Default trait for the LinkStats type
+impl Default for LinkStats {
+    fn default() -> LinkStats {
+        LinkStats::new()
+    }
+}
+
+// EOF
@@ -0,0 +1,135 @@
+//! ADS-B Simulator - see README.md
+//
+// © NewForester, 2018.  Available under MIT licence terms.
+//
+//! The json module renders MAVLink messages as JSON for the benefit of those
+//! who would rather not decode MAVLink themselves.
+//!
+//! Each message type adds its fields to a `Json` structure by name, with
+//! scaled integers converted to engineering units (degrees rather than
+//! degE7, metres rather than mm and so on), and the `render()` function wraps
+//! them up with the message header:
+//!
+//! ```text
+//! {"msgid":246,"msgname":"ADSB_VEHICLE","seqno":4,"sysid":25,"compid":89,
+//!  "fields":{"ICAO_address":3146073,"lat":51.5,...},"units":{"lat":"deg",...}}
+//! ```
+//!
+//! There is no JSON crate:  escaping strings and printing numbers is all
+//! that is needed.
+//!
+use std::fmt::Display;
+use std::io::Error;
+
+use mavlink;
+use mavlink::MavMessage;
+
+// ---------------------------------------------------------------------------
+
+/// The Json structure holds the fields of a message and their units as JSON members
+pub struct Json {
+    fields:     Vec<String>,
+    units:      Vec<String>,
+}
+
+// ---------------------------------------------------------------------------
+
+/// The implementation of methods for the Json type
+impl Json {
+    // new() creates and initialises a Json structure (with no fields)
+    pub fn new() -> Json {
+        Json {
+            fields:     Vec::new(),
+            units:      Vec::new(),
+        }
+    }
+
+    // integer() adds an integer field
+    pub fn integer<T: Display>(&mut self, name: &str, value: T, units: &str) -> &mut Self {
+        self.member(name, value.to_string(), units)
+    }
+
+    // float() adds a floating point field (null if it is not a number)
+    pub fn float<T: Display + Into<f64> + Copy>(&mut self, name: &str, value: T, units: &str) -> &mut Self {
+        let value = if value.into().is_finite() { value.to_string() } else { "null".to_string() };
+
+        self.member(name, value, units)
+    }
+
+    // text() adds a character array field (up to the first null)
+    pub fn text(&mut self, name: &str, value: &[u8]) -> &mut Self {
+        let text: Vec<u8> = value.iter().cloned().take_while(|&byte| byte != 0).collect();
+
+        self.member(name, quote(&String::from_utf8_lossy(&text)), "")
+    }
+
+    // array() adds an array field
+    pub fn array<T: Display>(&mut self, name: &str, values: &[T], units: &str) -> &mut Self {
+        let values: Vec<String> = values.iter().map(|value| value.to_string()).collect();
+
+        self.member(name, format!("[{}]", values.join(",")), units)
+    }
+
+    // member() adds a field that is already JSON
+    fn member(&mut self, name: &str, value: String, units: &str) -> &mut Self {
+        self.fields.push(format!("{}:{}", quote(name), value));
+
+        if ! units.is_empty() {
+            self.units.push(format!("{}:{}", quote(name), quote(units)));
+        }
+
+        self
+    }
+}
+
+/// The implementation of the Default trait for the Json type
+impl Default for Json {
+    fn default() -> Json {
+        Json::new()
+    }
+}
+
+// ---------------------------------------------------------------------------
+
+// render() returns the JSON rendering of a MAVLink 1 or 2 message (header and fields)
+pub fn render(buffy: &[u8]) -> Result<String, Error> {
+    let frame = mavlink::to_v1(buffy)?;
+    let buffy = &frame[..];
+
+    let message = MavMessage::parse(buffy)?;
+
+    let mut json = Json::new();
+
+    message.json(&mut json);
+
+    // messages whose ids do not fit in a byte are still MAVLink 2
+    let seqno = mavlink::seqno(buffy).unwrap_or(0);
+    let (sysid, compid) = mavlink::source(buffy).unwrap_or((0, 0));
+
+    Ok(format!("{{\"msgid\":{},\"msgname\":{},\"seqno\":{},\"sysid\":{},\"compid\":{},\"fields\":{{{}}},\"units\":{{{}}}}}",
+               message.msgid(), quote(MavMessage::name(message.msgid()).unwrap_or("")),
+               seqno, sysid, compid, json.fields.join(","), json.units.join(",")))
+}
+
+// quote() returns a string as a JSON string
+fn quote(text: &str) -> String {
+    let mut quoted = String::from("\"");
+
+    for cc in text.chars() {
+        match cc {
+            '"'  => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            cc if (cc as u32) < 0x20 => quoted.push_str(&format!("\\u{:04x}", cc as u32)),
+            cc => quoted.push(cc),
+        }
+    }
+
+    quoted.push('"');
+
+    quoted
+}
+
+// EOF
@@ -13,10 +13,26 @@
 //! Message specific packing/unpacking is delegated to modules implementing
 //! these traits.
 //!
+//! The header fields that belong to a link rather than a message (the sequence
+//! number and the system and component ids) are held in a `Link` structure.
+//! Each simulated device or output has its own so there is no global state.
+//!
+//! Every message can also render its fields as JSON (see the json module).
+//!
+//! Messages are serialised and parsed as MAVLink 1 unless their ids do not
+//! fit in a byte, in which case they are MAVLink 2.  `to_v2()` and `to_v1()`
+//! convert between MAVLink 1 and MAVLink 2 framing, which is needed for
+//! message signing (see the signing module).
+//!
+//! The `MavMessage` enumeration holds any one of the supported messages.
+//! It is the one place message ids are mapped to message types so received
+//! messages are parsed into the appropriate type without the need to match
+//! on raw message ids elsewhere.
+//!
 extern crate byteorder;
 extern crate crc16;
 
-use std::io::Error;
+use std::io::{Error, ErrorKind};
 
 use self::byteorder::{LittleEndian, WriteBytesExt};
 
@@ -24,7 +40,6 @@ use mavlink;
 
 // ---------------------------------------------------------------------------
 
-#[macro_export]
 /// msglen!() returns a MAVLink message length given its payload length
 macro_rules! msglen {
     ($paylen:expr) => (
@@ -32,7 +47,13 @@ macro_rules! msglen {
     )
 }
 
-#[macro_export]
+/// msglen_v2!() returns an (unsigned) MAVLink 2 message length given its payload length
+macro_rules! msglen_v2 {
+    ($paylen:expr) => (
+        $paylen + mavlink::HDR_SIZE_V2 + mavlink::CRC_SIZE
+    )
+}
+
 // paylen!() returns the payload length given a MAVLink message length
 macro_rules! paylen {
     ($msglen:expr) => (
@@ -48,11 +69,74 @@ const CRC_SIZE: usize = 2;
 
 /// The offset of the payload within a MAVLink message
 const PAYLOAD:  usize = HDR_SIZE;
+const PAYLOAD_V2: usize = HDR_SIZE_V2;
+
+/// The MAVLink 1 and MAVLink 2 start of message markers
+const STX_V1:   u8 = 0xfe;
+const STX_V2:   u8 = 0xfd;
+
+/// The sizes, in bytes, of the MAVLink 2 message header and signature, respectively
+const HDR_SIZE_V2: usize = 10;
+const SIG_SIZE: usize = 13;
+
+/// The MAVLink 2 incompatibility flag that says a message is signed
+const IFLAG_SIGNED: u8 = 0x01;
 
 // ---------------------------------------------------------------------------
 
-/// The unique sequence number used in the header of all published messages
-static mut SEQNO_OUT: u8 = 0;
+/// The Link structure holds the header fields that belong to a link and not to a message
+pub struct Link {
+    seqno:      u8,
+    sysid:      u8,
+    compid:     u8,
+}
+
+// ---------------------------------------------------------------------------
+
+/// The implementation of methods for the Link type
+impl Link {
+    // new() creates and initialises a Link structure
+    pub fn new() -> Link {
+        Link {
+            seqno:      0,
+            sysid:      0x19,
+            compid:     0x59,
+        }
+    }
+
+    // set_source() sets the system and component ids of messages sent over the link
+    pub fn set_source(&mut self, sysid: u8, compid: u8) -> &mut Self {
+        self.sysid = sysid;
+        self.compid = compid;
+
+        self
+    }
+
+    // get_sysid() returns the system id of messages sent over the link
+    pub fn get_sysid(&self) -> u8 {
+        self.sysid
+    }
+    // get_compid() returns the component id of messages sent over the link
+    pub fn get_compid(&self) -> u8 {
+        self.compid
+    }
+
+    // next_seqno() returns the sequence number of the next message sent over the link
+    fn next_seqno(&mut self) -> u8 {
+        let seqno = self.seqno;
+
+        self.seqno = self.seqno.wrapping_add(1);
+
+        seqno
+    }
+}
+
+/// The implementation of the Default trait for the Link type
+impl Default for Link {
+    fn default() -> Link {
+        Link::new()
+    }
+}
 
 // ---------------------------------------------------------------------------
 
@@ -63,17 +147,22 @@ struct Header {
     pub seqno:      u8,
     pub sysid:      u8,
     pub compid:     u8,
-    pub msgid:      u8,
+    pub msgid:      u32,
 }
 
 // ---------------------------------------------------------------------------
 
 /// The definition and partial implementation of the MAVLink message traits
 pub trait Message {
-    const MSGID: u8;
+    const MSGID: u32;
     const EXTRA: u8;
     const PAYLEN: usize;
 
+    // msglen() returns the message length:  MAVLink 2 if the message id does not fit in a byte
+    fn msglen() -> usize {
+        if Self::MSGID > 255 { msglen_v2!(Self::PAYLEN) } else { msglen!(Self::PAYLEN) }
+    }
+
     // dump() prints the message byte array (for debugging use only)
     fn dump(&mut self) -> &Self {
         print!("Dump message {:3}:", Self::MSGID);
@@ -88,25 +177,19 @@ pub trait Message {
     // message() returns the message byte array (for internal use only)
     fn message(&mut self) -> &mut [u8];
 
-    // the serialise() trait converts a MAVLink message type into a byte array
-    fn serialise(&mut self) -> &[u8] {
-        let mut buffy: Vec<u8> = Vec::with_capacity(msglen!(Self::PAYLEN));
+    // the serialise() trait converts a MAVLink message type into a byte array for the given link
+    fn serialise(&mut self, link: &mut Link) -> &[u8] {
+        let mut buffy: Vec<u8> = Vec::with_capacity(Self::msglen());
 
-        match Self::pack_message(self, &mut buffy) {
+        match Self::pack_message(self, link, &mut buffy) {
             Ok(_)  => {
-                let message = self.message();
-
-                for ii in 0 .. msglen!(Self::PAYLEN) {
-                    message[ii] = buffy[ii];
-                }
+                self.message()[.. Self::msglen()].copy_from_slice(&buffy[.. Self::msglen()]);
             }
             Err(_) => {
                 println!("Serialisation of message {} failed", Self::MSGID);
 
-                let message = self.message();
-
-                for ii in 0 .. msglen!(Self::PAYLEN) {
-                    message[ii] = 0;
+                for byte in self.message().iter_mut().take(Self::msglen()) {
+                    *byte = 0;
                 }
             }
         }
@@ -115,19 +198,9 @@ pub trait Message {
     }
 
     // the deserialise() trait converts a byte array into a MAVLink message type
-    fn deserialise(&mut self, buffy: &Vec<u8>) -> &mut Self {
-        {
-            let message = self.message();
-
-            for ii in 0 .. msglen!(Self::PAYLEN) {
-                message[ii] = buffy[ii];
-            }
-        }
-
-        match self.unpack_message() {
-            Ok(_)  => {
-                ();
-            }
+    fn deserialise(&mut self, buffy: &[u8]) -> &mut Self {
+        match self.load(buffy) {
+            Ok(_)  => {}
             Err(_) => {
                 println!("Deserialisation of message {} failed", Self::MSGID);
             }
@@ -136,9 +209,20 @@ pub trait Message {
         self
     }
 
+    // load() copies a byte array into the message and unpacks it (for internal use only)
+    fn load(&mut self, buffy: &[u8]) -> Result<(),Error> {
+        if buffy.len() < Self::msglen() {
+            return Err(Error::new(ErrorKind::UnexpectedEof, "Message too short"));
+        }
+
+        self.message()[.. Self::msglen()].copy_from_slice(&buffy[.. Self::msglen()]);
+
+        self.unpack_message()
+    }
+
     // pack_message() implements the MAVLink message serialise() trait
-    fn pack_message(&mut self, buffy: &mut Vec<u8>) -> Result<(),Error> {
-        Self::pack_header(buffy)?;
+    fn pack_message(&mut self, link: &mut Link, buffy: &mut Vec<u8>) -> Result<(),Error> {
+        Self::pack_header(link, buffy)?;
 
         self.pack_payload(buffy)?;
 
@@ -148,26 +232,34 @@ pub trait Message {
     }
 
     // pack_header() serialises the MAVLink message header
-    fn pack_header(buffy: &mut Vec<u8>) -> Result<(),Error> {
-        let mut header = Header {
-            mavstx:     0xfe,
+    fn pack_header(link: &mut Link, buffy: &mut Vec<u8>) -> Result<(),Error> {
+        let header = Header {
+            mavstx:     if Self::MSGID > 255 { STX_V2 } else { STX_V1 },
             paylen:     Self::PAYLEN as u8,
-            seqno:      0,
-            sysid:      0x19,
-            compid:     0x59,
+            seqno:      link.next_seqno(),
+            sysid:      link.sysid,
+            compid:     link.compid,
             msgid:      Self::MSGID,
         };
 
-        unsafe {header.seqno = SEQNO_OUT;}
-
         buffy.write_u8(header.mavstx)?;
         buffy.write_u8(header.paylen)?;
+
+        // MAVLink 2 has (no) incompatibility and compatibility flags and a three byte message id
+        if header.mavstx == STX_V2 {
+            buffy.write_u8(0)?;
+            buffy.write_u8(0)?;
+        }
+
         buffy.write_u8(header.seqno)?;
         buffy.write_u8(header.sysid)?;
         buffy.write_u8(header.compid)?;
-        buffy.write_u8(header.msgid)?;
 
-        unsafe {SEQNO_OUT = SEQNO_OUT.wrapping_add(1);}
+        if header.mavstx == STX_V2 {
+            buffy.write_u24::<LittleEndian>(header.msgid)?;
+        } else {
+            buffy.write_u8(header.msgid as u8)?;
+        }
 
         Ok(())
     }
@@ -200,15 +292,354 @@ pub trait Message {
 
     // unpack_payload() deserialises a MAVLink message payload
     fn unpack_payload(&mut self) -> Result<(),Error>;
+
+    // json() adds the message fields, in engineering units, to a JSON rendering
+    fn json(&self, json: &mut json::Json);
 }
 
 // ---------------------------------------------------------------------------
 
+// The JSON rendering of messages
+pub mod json;
+
+// MAVLink 2 message signing
+pub mod signing;
+
 // MAVLink message implementations
+pub mod msg0;
+pub mod msg20;
+pub mod msg21;
+pub mod msg22;
+pub mod msg23;
+pub mod msg24;
+pub mod msg33;
+pub mod msg42;
+pub mod msg44;
+pub mod msg46;
+pub mod msg47;
+pub mod msg51;
 pub mod msg66;
+pub mod msg73;
+pub mod msg76;
+pub mod msg77;
 pub mod msg84;
 pub mod msg202;
 pub mod msg203;
 pub mod msg246;
+pub mod msg10001;
+pub mod msg10002;
+pub mod msg10003;
+
+// ---------------------------------------------------------------------------
+
+// check_crc() returns whether a received MAVLink 1 or MAVLink 2 message's checksum is correct (if it can tell)
+pub fn check_crc(buffy: &[u8]) -> Option<bool> {
+    let length = framelen(buffy)?;
+
+    let extra = MavMessage::extra(msgid(buffy)?)?;
+
+    let end = length - CRC_SIZE - if is_signed(buffy) { SIG_SIZE } else { 0 };
+
+    Some(checksum(&buffy[1..end], extra) == (buffy[end] as u16 | (buffy[end + 1] as u16) << 8))
+}
+
+// seqno() returns the sequence number of a MAVLink 1 or MAVLink 2 message
+pub fn seqno(buffy: &[u8]) -> Option<u8> {
+    match buffy.first() {
+        Some(&STX_V2) if buffy.len() >= HDR_SIZE_V2 => Some(buffy[4]),
+        Some(_)       if buffy.len() >= HDR_SIZE    => Some(buffy[2]),
+        _                                           => None,
+    }
+}
+
+// source() returns the system and component ids of a MAVLink 1 or MAVLink 2 message
+pub fn source(buffy: &[u8]) -> Option<(u8, u8)> {
+    match buffy.first() {
+        Some(&STX_V2) if buffy.len() >= HDR_SIZE_V2 => Some((buffy[5], buffy[6])),
+        Some(_)       if buffy.len() >= HDR_SIZE    => Some((buffy[3], buffy[4])),
+        _                                           => None,
+    }
+}
+
+// framelen() returns the length of the MAVLink 1 or MAVLink 2 message at the start of a byte array, if there is one
+pub fn framelen(buffy: &[u8]) -> Option<usize> {
+    let length = match buffy.first() {
+        Some(&STX_V1) if buffy.len() > 1 => msglen!(buffy[1] as usize),
+        Some(&STX_V2) if buffy.len() > 2 => msglen_v2!(buffy[1] as usize) + if is_signed(buffy) { SIG_SIZE } else { 0 },
+        _ => return None,
+    };
+
+    if buffy.len() >= length { Some(length) } else { None }
+}
+
+// msgid() returns the message id of a MAVLink 1 or MAVLink 2 message
+pub fn msgid(buffy: &[u8]) -> Option<u32> {
+    match buffy.first() {
+        Some(&STX_V2) if buffy.len() >= HDR_SIZE_V2 => Some(buffy[7] as u32 | (buffy[8] as u32) << 8 | (buffy[9] as u32) << 16),
+        Some(_)       if buffy.len() >= HDR_SIZE    => Some(buffy[5] as u32),
+        _                                           => None,
+    }
+}
+
+// to_v2() converts a MAVLink 1 message to MAVLink 2 (with the given incompatibility flags but no signature)
+// and gives a MAVLink 2 message the given flags (less any signature)
+pub fn to_v2(buffy: &[u8], iflags: u8) -> Result<Vec<u8>, Error> {
+    let length = framelen(buffy).ok_or_else(|| Error::new(ErrorKind::InvalidData, "Not a MAVLink message"))?;
+
+    let msgid = msgid(buffy).unwrap_or(0);
+
+    let extra = MavMessage::extra(msgid)
+                    .ok_or_else(|| Error::new(ErrorKind::InvalidData, format!("Unsupported message {}", msgid)))?;
+
+    if buffy[0] == STX_V2 {
+        let mut frame = buffy[.. length - CRC_SIZE - if is_signed(buffy) { SIG_SIZE } else { 0 }].to_vec();
+
+        frame[2] = iflags;
+
+        let crc = checksum(&frame[1..], extra);
+
+        frame.write_u16::<LittleEndian>(crc)?;
+
+        return Ok(frame);
+    }
+
+    let payload = &buffy[PAYLOAD .. PAYLOAD + buffy[1] as usize];
+
+    // MAVLink 2 drops the trailing zeros of the payload (but not the first byte)
+    let mut paylen = payload.len();
+
+    while paylen > 1 && payload[paylen - 1] == 0 {
+        paylen -= 1;
+    }
+
+    let mut frame = Vec::with_capacity(HDR_SIZE_V2 + paylen + CRC_SIZE + SIG_SIZE);
+
+    frame.extend_from_slice(&[STX_V2, paylen as u8, iflags, 0, buffy[2], buffy[3], buffy[4], buffy[5], 0, 0]);
+    frame.extend_from_slice(&payload[.. paylen]);
+
+    let crc = checksum(&frame[1..], extra);
+
+    frame.write_u16::<LittleEndian>(crc)?;
+
+    Ok(frame)
+}
+
+// to_v1() converts a MAVLink 2 message to MAVLink 1 (ignoring any signature) and returns a MAVLink 1 message as is,
+// as it does a (checked) MAVLink 2 message whose id does not fit in a byte
+pub fn to_v1(buffy: &[u8]) -> Result<Vec<u8>, Error> {
+    if buffy.first() != Some(&STX_V2) {
+        return Ok(buffy.to_vec());
+    }
+
+    let length = framelen(buffy).ok_or_else(|| Error::new(ErrorKind::UnexpectedEof, "Message too short"))?;
+
+    let end = HDR_SIZE_V2 + buffy[1] as usize;
+
+    let msgid = msgid(buffy).unwrap_or(0);
+
+    let (extra, paylen) = match (MavMessage::extra(msgid), MavMessage::paylen(msgid)) {
+        (Some(extra), Some(paylen)) => (extra, paylen),
+        _ => return Err(Error::new(ErrorKind::InvalidData, format!("Unsupported message {}", msgid))),
+    };
+
+    if buffy[1] as usize > paylen {
+        return Err(Error::new(ErrorKind::InvalidData, "Payload length mismatch"));
+    }
+
+    if checksum(&buffy[1..end], extra) != (buffy[end] as u16 | (buffy[end + 1] as u16) << 8) {
+        return Err(Error::new(ErrorKind::InvalidData, "CRC mismatch"));
+    }
+
+    if msgid > 255 {
+        return Ok(buffy[.. length].to_vec());
+    }
+
+    let mut frame = Vec::with_capacity(msglen!(paylen));
+
+    frame.extend_from_slice(&[STX_V1, paylen as u8, buffy[4], buffy[5], buffy[6], msgid as u8]);
+    frame.extend_from_slice(&buffy[HDR_SIZE_V2 .. end]);
+    frame.resize(HDR_SIZE + paylen, 0);
+
+    let crc = checksum(&frame[1..], extra);
+
+    frame.write_u16::<LittleEndian>(crc)?;
+
+    Ok(frame)
+}
+
+// is_signed() returns whether a message is a signed MAVLink 2 message
+fn is_signed(buffy: &[u8]) -> bool {
+    buffy.len() > 2 && buffy[0] == STX_V2 && buffy[2] & IFLAG_SIGNED != 0
+}
+
+// checksum() returns the MAVLink checksum of the given bytes and CRC_EXTRA byte
+fn checksum(bytes: &[u8], extra: u8) -> u16 {
+    let mut crc = crc16::State::<crc16::MCRF4XX>::new();
+
+    crc.update(bytes);
+    crc.update(&[extra]);
+
+    crc.get()
+}
+
+// ---------------------------------------------------------------------------
+
+/// mavmessages!() declares the MavMessage enumeration and its dispatch methods
+macro_rules! mavmessages {
+    ($($msgid:expr => $variant:ident ($module:ident, $name:expr),)*) => (
+        #[allow(dead_code)]
+        /// The MavMessage enumeration holds any one of the supported MAVLink messages
+        pub enum MavMessage {
+            $($variant($module::Message),)*
+        }
+
+        /// The implementation of methods for the MavMessage type
+        impl MavMessage {
+            // parse() converts a received byte array into a MAVLink message of the appropriate type
+            pub fn parse(buffy: &[u8]) -> Result<MavMessage, Error> {
+                if buffy.first() == Some(&STX_V2) {
+                    return MavMessage::parse_v2(buffy);
+                }
+
+                if buffy.len() < msglen!(0) {
+                    return Err(Error::new(ErrorKind::UnexpectedEof, "Message too short"));
+                }
+
+                match buffy[5] as u32 {
+                    $($msgid => {
+                        if buffy[1] as usize != <$module::Message as Message>::PAYLEN {
+                            return Err(Error::new(ErrorKind::InvalidData, "Payload length mismatch"));
+                        }
+
+                        match check_crc(buffy) {
+                            Some(true) => (),
+                            Some(false) => return Err(Error::new(ErrorKind::InvalidData, "CRC mismatch")),
+                            None => return Err(Error::new(ErrorKind::UnexpectedEof, "Message too short")),
+                        }
+
+                        let mut message = $module::Message::new();
+
+                        message.load(buffy)?;
+
+                        Ok(MavMessage::$variant(message))
+                    })*
+                    msgid => {
+                        Err(Error::new(ErrorKind::InvalidData, format!("Unsupported message {}", msgid)))
+                    }
+                }
+            }
+
+            // parse_v2() converts a received MAVLink 2 message into a MAVLink message of the appropriate type
+            fn parse_v2(buffy: &[u8]) -> Result<MavMessage, Error> {
+                let frame = to_v1(buffy)?;
+
+                if frame[0] == STX_V1 {
+                    return MavMessage::parse(&frame);
+                }
+
+                // restore the trailing zeros MAVLink 2 may have dropped from the payload
+                let end = HDR_SIZE_V2 + frame[1] as usize;
+
+                match msgid(&frame).unwrap_or(0) {
+                    $($msgid => {
+                        let mut padded = frame[.. end].to_vec();
+
+                        padded.resize(msglen_v2!(<$module::Message as Message>::PAYLEN), 0);
+
+                        let mut message = $module::Message::new();
+
+                        message.load(&padded)?;
+
+                        Ok(MavMessage::$variant(message))
+                    })*
+                    msgid => {
+                        Err(Error::new(ErrorKind::InvalidData, format!("Unsupported message {}", msgid)))
+                    }
+                }
+            }
+
+            // extra() returns the CRC_EXTRA byte of a supported message id
+            pub fn extra(msgid: u32) -> Option<u8> {
+                match msgid {
+                    $($msgid => Some(<$module::Message as Message>::EXTRA),)*
+                    _ => None,
+                }
+            }
+
+            // paylen() returns the (MAVLink 1) payload length of a supported message id
+            pub fn paylen(msgid: u32) -> Option<usize> {
+                match msgid {
+                    $($msgid => Some(<$module::Message as Message>::PAYLEN),)*
+                    _ => None,
+                }
+            }
+
+            // name() returns the MAVLink name of a supported message id
+            pub fn name(msgid: u32) -> Option<&'static str> {
+                match msgid {
+                    $($msgid => Some($name),)*
+                    _ => None,
+                }
+            }
+
+            // id() returns the message id of a supported MAVLink message name
+            pub fn id(name: &str) -> Option<u32> {
+                match name {
+                    $($name => Some($msgid),)*
+                    _ => None,
+                }
+            }
+
+            // msgid() returns the MAVLink message id of the message held
+            pub fn msgid(&self) -> u32 {
+                match *self {
+                    $(MavMessage::$variant(_) => $msgid,)*
+                }
+            }
+
+            // json() adds the fields of the message held to a JSON rendering
+            pub fn json(&self, json: &mut json::Json) {
+                match *self {
+                    $(MavMessage::$variant(ref message) => message.json(json),)*
+                }
+            }
+
+            #[allow(dead_code)]
+            // serialise() converts the message held into a byte array ready for transmission over the given link
+            pub fn serialise(&mut self, link: &mut Link) -> &[u8] {
+                match *self {
+                    $(MavMessage::$variant(ref mut message) => message.serialise(link),)*
+                }
+            }
+        }
+    )
+}
+
+// The MAVLink messages supported:  add new message types here
+mavmessages! {
+    0     => Heartbeat (msg0, "HEARTBEAT"),
+    20    => ParamRequestRead (msg20, "PARAM_REQUEST_READ"),
+    21    => ParamRequestList (msg21, "PARAM_REQUEST_LIST"),
+    22    => ParamValue (msg22, "PARAM_VALUE"),
+    23    => ParamSet (msg23, "PARAM_SET"),
+    24    => GpsRaw (msg24, "GPS_RAW_INT"),
+    33    => GlobalPosition (msg33, "GLOBAL_POSITION_INT"),
+    42    => MissionCurrent (msg42, "MISSION_CURRENT"),
+    44    => MissionCount (msg44, "MISSION_COUNT"),
+    46    => MissionItemReached (msg46, "MISSION_ITEM_REACHED"),
+    47    => MissionAck (msg47, "MISSION_ACK"),
+    51    => MissionRequest (msg51, "MISSION_REQUEST_INT"),
+    66    => DataStreamRequest (msg66, "REQUEST_DATA_STREAM"),
+    73    => MissionItem (msg73, "MISSION_ITEM_INT"),
+    76    => CommandLong (msg76, "COMMAND_LONG"),
+    77    => CommandAck (msg77, "COMMAND_ACK"),
+    84    => SetTargetPosition (msg84, "SET_POSITION_TARGET_LOCAL_NED"),
+    202   => Ownship (msg202, "OWNSHIP"),
+    203   => Status (msg203, "STATUS"),
+    246   => TrafficReport (msg246, "ADSB_VEHICLE"),
+    10001 => TransponderConfig (msg10001, "UAVIONIX_ADSB_OUT_CFG"),
+    10002 => TransponderDynamic (msg10002, "UAVIONIX_ADSB_OUT_DYNAMIC"),
+    10003 => TransponderHealth (msg10003, "UAVIONIX_ADSB_TRANSCEIVER_HEALTH_REPORT"),
+}
 
 // EOF
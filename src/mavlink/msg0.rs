@@ -0,0 +1,44 @@
+//! ADS-B Simulator - see README.md
+//
+// © NewForester, 2018.  Available under MIT licence terms.
+//
+//! The msg0 module implements the _mavlink message trait_ for the
+//! MAVLink 'heartbeat' message (id 0).
+//!
+//! The message structure and the trait implementation are generated by
+//! build.rs from the HEARTBEAT definition in common.xml.
+//!
+//! The heartbeat generated is that of an ADS-B device:  it has no autopilot
+//! and is always active.  Heartbeats received are only used to tell whether
+//! their sender is still there so no getter/setter functions are implemented.
+//!
+include!(concat!(env!("OUT_DIR"), "/msg0.rs"));
+
+// ---------------------------------------------------------------------------
+
+/// The MAV_TYPE, MAV_AUTOPILOT and MAV_STATE values of an ADS-B device
+const MAV_TYPE_ADSB: u8 = 27;
+const MAV_AUTOPILOT_INVALID: u8 = 8;
+const MAV_STATE_ACTIVE: u8 = 4;
+
+/// The MAVLink protocol version reported
+const MAVLINK_VERSION: u8 = 3;
+
+// ---------------------------------------------------------------------------
+
+/// The implementation of methods for the MAVLink 0 message type
+impl Message {
+    // new() creates and initialises a MAVLink 0 message structure
+    pub fn new() -> Message {
+        let mut safe = Message::default();
+
+        safe.type_ = MAV_TYPE_ADSB;
+        safe.autopilot = MAV_AUTOPILOT_INVALID;
+        safe.system_status = MAV_STATE_ACTIVE;
+        safe.mavlink_version = MAVLINK_VERSION;
+
+        safe
+    }
+}
+
+// EOF
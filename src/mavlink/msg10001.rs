@@ -0,0 +1,50 @@
+//! ADS-B Simulator - see README.md
+//
+// © NewForester, 2018.  Available under MIT licence terms.
+//
+//! The msg10001 module implements the _mavlink message trait_ for the
+//! uAvionix 'ADS-B OUT configuration' message (id 10001).
+//!
+//! The message structure and the trait implementation are generated by
+//! build.rs from the UAVIONIX_ADSB_OUT_CFG definition in uAvionix.xml.
+//!
+//! The message is sent by autopilots to configure their transponder.  The
+//! ADS-B Simulator uses the getter functions to take the ICAO address,
+//! callsign and emitter type of the UAV.  It needs MAVLink 2.
+//!
+include!(concat!(env!("OUT_DIR"), "/msg10001.rs"));
+
+// ---------------------------------------------------------------------------
+
+/// The implementation of methods for the MAVLink 10001 message type
+impl Message {
+    // new() creates and initialises a MAVLink 10001 message structure
+    pub fn new() -> Message {
+        Message::default()
+    }
+
+    // set_callsign() sets the message callsign from the given string safely (at most 8 characters)
+    pub fn set_callsign(&mut self, callsign: &str) -> &mut Self {
+        let safe = callsign.as_bytes();
+
+        for ii in 0 .. self.callsign.len() {
+            self.callsign[ii] = if ii < safe.len() && ii < 8 { safe[ii] } else { 0 };
+        }
+
+        self
+    }
+
+    // get_icao() returns the (24 bit) ICAO address
+    pub fn get_icao(&self) -> u32 {
+        self.icao & 0x00ff_ffff
+    }
+
+    // get_callsign() returns the callsign (up to the first null and less any trailing spaces)
+    pub fn get_callsign(&self) -> String {
+        let callsign: Vec<u8> = self.callsign.iter().cloned().take_while(|&cc| cc != 0).collect();
+
+        String::from_utf8_lossy(&callsign).trim_end().to_string()
+    }
+}
+
+// EOF
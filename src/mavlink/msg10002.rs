@@ -0,0 +1,27 @@
+//! ADS-B Simulator - see README.md
+//
+// © NewForester, 2018.  Available under MIT licence terms.
+//
+//! The msg10002 module implements the _mavlink message trait_ for the
+//! uAvionix 'ADS-B OUT dynamic' message (id 10002).
+//!
+//! The message structure and the trait implementation are generated by
+//! build.rs from the UAVIONIX_ADSB_OUT_DYNAMIC definition in uAvionix.xml.
+//!
+//! The message is sent by autopilots five times a second.  The ADS-B
+//! Simulator takes only the squawk code from it:  the position of the UAV
+//! comes from elsewhere.  It needs MAVLink 2.
+//!
+include!(concat!(env!("OUT_DIR"), "/msg10002.rs"));
+
+// ---------------------------------------------------------------------------
+
+/// The implementation of methods for the MAVLink 10002 message type
+impl Message {
+    // new() creates and initialises a MAVLink 10002 message structure
+    pub fn new() -> Message {
+        Message::default()
+    }
+}
+
+// EOF
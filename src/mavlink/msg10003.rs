@@ -0,0 +1,37 @@
+//! ADS-B Simulator - see README.md
+//
+// © NewForester, 2018.  Available under MIT licence terms.
+//
+//! The msg10003 module implements the _mavlink message trait_ for the
+//! uAvionix 'transceiver health report' message (id 10003).
+//!
+//! The message structure and the trait implementation are generated by
+//! build.rs from the UAVIONIX_ADSB_TRANSCEIVER_HEALTH_REPORT definition in
+//! uAvionix.xml.
+//!
+//! The message is sent by the transponder.  The ADS-B Simulator sends it
+//! once an autopilot has configured it (with a 10001 message) to say all is
+//! well.  It needs MAVLink 2.
+//!
+include!(concat!(env!("OUT_DIR"), "/msg10003.rs"));
+
+// ---------------------------------------------------------------------------
+
+/// The rfHealth bit that says the transponder is working (UAVIONIX_ADSB_RF_HEALTH_OK)
+pub const RF_HEALTH_OK: u8 = 1;
+
+// ---------------------------------------------------------------------------
+
+/// The implementation of methods for the MAVLink 10003 message type
+impl Message {
+    // new() creates and initialises a MAVLink 10003 message structure (reporting all is well)
+    pub fn new() -> Message {
+        let mut message = Message::default();
+
+        message.rf_health = RF_HEALTH_OK;
+
+        message
+    }
+}
+
+// EOF
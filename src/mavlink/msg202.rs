@@ -100,9 +100,11 @@ impl Message {
 
         self
     }
-    // set_altitude() sets the message altitude (converting a float to a scaled integer)
+    // set_altitude() sets the message altitudes (converting a float to a scaled integer)
+    // (the simulated atmosphere is standard so pressure and GNSS altitude are one and the same)
     pub fn set_altitude(&mut self, altitude: f32) -> &mut Self {
         self.altpres = (altitude * 1.0e3) as i32;
+        self.altgnss = self.altpres;
 
         self
     }
@@ -126,8 +128,10 @@ impl Message {
         self
     }
 
-    // set_cwithv() sets message position and velocity from those held in the given CwithV structure
+    // set_cwithv() sets message position and velocity from those held in the given CwithV structure (as of now)
     pub fn set_cwithv(&mut self, cwithv: &CwithV) -> &mut Self {
+        self.utctime = Utc::now().timestamp() as u32;
+
         self.set_latitude(cwithv.get_latitude());
         self.set_longitude(cwithv.get_longitude());
         self.set_altitude(cwithv.get_altitude());
@@ -175,11 +179,18 @@ impl Message {
     }
 }
 
+/// The implementation of the Default trait for the Message type
+impl Default for Message {
+    fn default() -> Message {
+        Message::new()
+    }
+}
+
 // ---------------------------------------------------------------------------
 
 /// The implementation of the MAVLink message traits for the 202 message type
 impl mavlink::Message for Message {
-    const MSGID: u8 = 202;
+    const MSGID: u32 = 202;
     const EXTRA: u8 = 0x07;
     const PAYLEN: usize = paylen!(MSGLEN);
 
@@ -190,7 +201,7 @@ impl mavlink::Message for Message {
 
     // pack_payload() implements the MAVLink message serialise() trait
     fn pack_payload(&self, buffy: &mut Vec<u8>) -> Result<(),Error> {
-        buffy.write_u32::<LittleEndian>(Utc::now().timestamp() as u32)?;
+        buffy.write_u32::<LittleEndian>(self.utctime)?;
 
         buffy.write_i32::<LittleEndian>(self.latitude)?;
         buffy.write_i32::<LittleEndian>(self.longitude)?;
@@ -245,6 +256,27 @@ impl mavlink::Message for Message {
 
         Ok(())
     }
+
+    // json() adds the message fields, in engineering units, to a JSON rendering
+    fn json(&self, json: &mut mavlink::json::Json) {
+        json.integer("utctime", self.utctime, "s")
+            .float("latitude", self.latitude as f64 / 1.0e7, "deg")
+            .float("longitude", self.longitude as f64 / 1.0e7, "deg")
+            .float("altpres", self.altpres as f64 / 1.0e3, "m")
+            .float("altgnss", self.altgnss as f64 / 1.0e3, "m")
+            .float("acchoriz", self.acchoriz as f64 / 1.0e3, "m")
+            .float("accvert", self.accvert as f64 / 1.0e2, "m")
+            .float("accvel", self.accvel as f64 / 1.0e3, "m/s")
+            .float("velvert", self.velvert as f64 / 1.0e2, "m/s")
+            .float("nsvog", self.nsvog as f64 / 1.0e2, "m/s")
+            .float("ewvog", self.ewvog as f64 / 1.0e2, "m/s")
+            .integer("state", self.state, "")
+            .integer("squawk", self.squawk, "")
+            .integer("fixtype", self.fixtype, "")
+            .integer("numsats", self.numsats, "")
+            .integer("emstatus", self.emstatus, "")
+            .integer("control", self.control, "");
+    }
 }
 
 // EOF
@@ -5,16 +5,16 @@
 //! The msg203 module implements the _mavlink message trait_ for the
 //! MAVLink 'status' message (id 203).
 //!
-//! Only message serialise is actually implemented
-//! as the ADS-B Simulator need only generate this message.
+//! Both message serialise and deserialise are implemented although the
+//! ADS-B Simulator need only generate this message.
 //!
 //! No getter/setter functions are implemented:  the message is generated
 //! by the ADS-B device being simulated but the receiver ignores it.
 //!
-use std::io::{Error, ErrorKind};
+use std::io::{Error};
 
 use mavlink;
-use mavlink::byteorder::{WriteBytesExt};
+use mavlink::byteorder::{ReadBytesExt, WriteBytesExt};
 
 // ---------------------------------------------------------------------------
 
@@ -44,11 +44,18 @@ impl Message {
     }
 }
 
+/// The implementation of the Default trait for the Message type
+impl Default for Message {
+    fn default() -> Message {
+        Message::new()
+    }
+}
+
 // ---------------------------------------------------------------------------
 
 /// The implementation of the MAVLink message traits for the 203 message type
 impl mavlink::Message for Message {
-    const MSGID: u8 = 203;
+    const MSGID: u32 = 203;
     const EXTRA: u8 = 0x55;
     const PAYLEN: usize = paylen!(MSGLEN);
 
@@ -66,7 +73,16 @@ impl mavlink::Message for Message {
 
     // unpack_payload() implements the MAVLink message deserialise() trait
     fn unpack_payload(&mut self) -> Result<(),Error> {
-        Err(Error::new(ErrorKind::Other, "Not implemented"))
+        let mut payload = &self.buffy[mavlink::PAYLOAD..];
+
+        self.status = payload.read_u8()?;
+
+        Ok(())
+    }
+
+    // json() adds the message fields to a JSON rendering
+    fn json(&self, json: &mut mavlink::json::Json) {
+        json.integer("status", self.status, "");
     }
 }
 
@@ -0,0 +1,26 @@
+//! ADS-B Simulator - see README.md
+//
+// © NewForester, 2018.  Available under MIT licence terms.
+//
+//! The msg21 module implements the _mavlink message trait_ for the
+//! MAVLink 'parameter list request' message (id 21).
+//!
+//! The message structure and the trait implementation are generated by
+//! build.rs from the PARAM_REQUEST_LIST definition in common.xml.
+//!
+//! The message is sent by ground stations to read all the parameters of the
+//! ADS-B Simulator.  No getter/setter functions are needed.
+//!
+include!(concat!(env!("OUT_DIR"), "/msg21.rs"));
+
+// ---------------------------------------------------------------------------
+
+/// The implementation of methods for the MAVLink 21 message type
+impl Message {
+    // new() creates and initialises a MAVLink 21 message structure
+    pub fn new() -> Message {
+        Message::default()
+    }
+}
+
+// EOF
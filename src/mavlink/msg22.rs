@@ -0,0 +1,62 @@
+//! ADS-B Simulator - see README.md
+//
+// © NewForester, 2018.  Available under MIT licence terms.
+//
+//! The msg22 module implements the _mavlink message trait_ for the
+//! MAVLink 'parameter value' message (id 22).
+//!
+//! The message structure and the trait implementation are generated by
+//! build.rs from the PARAM_VALUE definition in common.xml.
+//!
+//! The message is sent by the ADS-B Simulator in answer to each parameter
+//! request and each PARAM_SET (23) message addressed to it.
+//!
+include!(concat!(env!("OUT_DIR"), "/msg22.rs"));
+
+// ---------------------------------------------------------------------------
+
+/// The types of a parameter value (MAV_PARAM_TYPE):  those the simulator uses
+pub const MAV_PARAM_TYPE_UINT8: u8 = 1;
+pub const MAV_PARAM_TYPE_UINT16: u8 = 3;
+pub const MAV_PARAM_TYPE_UINT32: u8 = 5;
+pub const MAV_PARAM_TYPE_REAL32: u8 = 9;
+
+// ---------------------------------------------------------------------------
+
+/// The implementation of methods for the MAVLink 22 message type
+impl Message {
+    // new() creates and initialises a MAVLink 22 message structure
+    pub fn new() -> Message {
+        Message::default()
+    }
+
+    // set_value() sets the parameter, its value and type and where it comes in the list of parameters
+    pub fn set_value(&mut self, param_id: &str, value: f32, type_: u8, index: usize, count: usize) -> &mut Self {
+        self.param_value = value;
+        self.param_type = type_;
+        self.param_index = index as u16;
+        self.param_count = count as u16;
+
+        self.set_param_id(param_id)
+    }
+
+    // set_param_id() sets the parameter id from the given string safely (at most 16 characters, null terminated if fewer)
+    pub fn set_param_id(&mut self, param_id: &str) -> &mut Self {
+        let safe = param_id.as_bytes();
+
+        for ii in 0 .. self.param_id.len() {
+            self.param_id[ii] = if ii < safe.len() { safe[ii] } else { 0 };
+        }
+
+        self
+    }
+
+    // get_param_id() returns the parameter id (up to the first null, if any)
+    pub fn get_param_id(&self) -> String {
+        let param_id: Vec<u8> = self.param_id.iter().cloned().take_while(|&cc| cc != 0).collect();
+
+        String::from_utf8_lossy(&param_id).to_string()
+    }
+}
+
+// EOF
@@ -0,0 +1,45 @@
+//! ADS-B Simulator - see README.md
+//
+// © NewForester, 2018.  Available under MIT licence terms.
+//
+//! The msg23 module implements the _mavlink message trait_ for the
+//! MAVLink 'parameter set' message (id 23).
+//!
+//! The message structure and the trait implementation are generated by
+//! build.rs from the PARAM_SET definition in common.xml.
+//!
+//! The message is sent by ground stations to change one parameter of the
+//! ADS-B Simulator.  The value of an integer parameter is its value cast to
+//! a float (as most ground stations and autopilots do).
+//!
+include!(concat!(env!("OUT_DIR"), "/msg23.rs"));
+
+// ---------------------------------------------------------------------------
+
+/// The implementation of methods for the MAVLink 23 message type
+impl Message {
+    // new() creates and initialises a MAVLink 23 message structure
+    pub fn new() -> Message {
+        Message::default()
+    }
+
+    // set_param_id() sets the parameter id from the given string safely (at most 16 characters, null terminated if fewer)
+    pub fn set_param_id(&mut self, param_id: &str) -> &mut Self {
+        let safe = param_id.as_bytes();
+
+        for ii in 0 .. self.param_id.len() {
+            self.param_id[ii] = if ii < safe.len() { safe[ii] } else { 0 };
+        }
+
+        self
+    }
+
+    // get_param_id() returns the parameter id (up to the first null, if any)
+    pub fn get_param_id(&self) -> String {
+        let param_id: Vec<u8> = self.param_id.iter().cloned().take_while(|&cc| cc != 0).collect();
+
+        String::from_utf8_lossy(&param_id).to_string()
+    }
+}
+
+// EOF
@@ -0,0 +1,55 @@
+//! ADS-B Simulator - see README.md
+//
+// © NewForester, 2018.  Available under MIT licence terms.
+//
+//! The msg24 module implements the _mavlink message trait_ for the
+//! MAVLink 'raw GPS' message (id 24).
+//!
+//! The message structure and the trait implementation are generated by
+//! build.rs from the GPS_RAW_INT definition in common.xml.
+//!
+//! The message is sent by autopilots.  The ADS-B Simulator uses the getter
+//! functions to take the position of the UAV from a SITL autopilot that does
+//! not send GLOBAL_POSITION_INT (33) messages.
+//!
+include!(concat!(env!("OUT_DIR"), "/msg24.rs"));
+
+use ::coords::CwithV;
+
+// ---------------------------------------------------------------------------
+
+/// The value of the vel and cog fields when they are unknown
+const UNKNOWN: u16 = 0xffff;
+
+// ---------------------------------------------------------------------------
+
+/// The implementation of methods for the MAVLink 24 message type
+impl Message {
+    // new() creates and initialises a MAVLink 24 message structure
+    pub fn new() -> Message {
+        Message::default()
+    }
+
+    // has_fix() returns whether the message has a position (a 2D fix or better)
+    pub fn has_fix(&self) -> bool {
+        self.fix_type >= 2
+    }
+
+    // get_cwithv() sets the given CwithV structure to the message position and, if known, horizontal velocity
+    pub fn get_cwithv(&self, cwithv: &mut CwithV) -> &Self {
+        cwithv.set_position(self.lat as f32 / 1.0e7, self.lon as f32 / 1.0e7, self.alt as f32 / 1.0e3);
+
+        // there is no vertical velocity:  keep the last
+        if self.vel != UNKNOWN && self.cog != UNKNOWN {
+            let speed = self.vel as f32 / 1.0e2;
+            let course = (self.cog as f32 / 1.0e2).to_radians();
+            let climb = cwithv.get_rateofclimb();
+
+            cwithv.set_velocity(speed * course.cos(), speed * course.sin(), climb);
+        }
+
+        self
+    }
+}
+
+// EOF
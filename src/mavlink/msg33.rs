@@ -0,0 +1,50 @@
+//! ADS-B Simulator - see README.md
+//
+// © NewForester, 2018.  Available under MIT licence terms.
+//
+//! The msg33 module implements the _mavlink message trait_ for the
+//! MAVLink 'global position' message (id 33).
+//!
+//! The message structure and the trait implementation are generated by
+//! build.rs from the GLOBAL_POSITION_INT definition in common.xml.
+//!
+//! The message is sent by autopilots.  The ADS-B Simulator uses the getter
+//! functions to take the position of the UAV from a SITL autopilot.
+//!
+include!(concat!(env!("OUT_DIR"), "/msg33.rs"));
+
+use ::coords::CwithV;
+
+// ---------------------------------------------------------------------------
+
+/// The implementation of methods for the MAVLink 33 message type
+impl Message {
+    // new() creates and initialises a MAVLink 33 message structure
+    pub fn new() -> Message {
+        Message::default()
+    }
+
+    // set_cwithv() sets the message position and velocity from the given CwithV structure
+    pub fn set_cwithv(&mut self, cwithv: &CwithV) -> &mut Self {
+        self.lat = (cwithv.get_latitude() * 1.0e7) as i32;
+        self.lon = (cwithv.get_longitude() * 1.0e7) as i32;
+        self.alt = (cwithv.get_altitude() * 1.0e3) as i32;
+
+        self.vx = (cwithv.get_ns_velocity() * 1.0e2) as i16;
+        self.vy = (cwithv.get_ew_velocity() * 1.0e2) as i16;
+        self.vz = (cwithv.get_rateofclimb() * -1.0e2) as i16;
+
+        self
+    }
+
+    // get_cwithv() sets the given CwithV structure to the message position and velocity (the message z axis is down)
+    pub fn get_cwithv(&self, cwithv: &mut CwithV) -> &Self {
+        cwithv.set_position(self.lat as f32 / 1.0e7, self.lon as f32 / 1.0e7, self.alt as f32 / 1.0e3);
+
+        cwithv.set_velocity(self.vx as f32 / 1.0e2, self.vy as f32 / 1.0e2, self.vz as f32 / -1.0e2);
+
+        self
+    }
+}
+
+// EOF
@@ -0,0 +1,26 @@
+//! ADS-B Simulator - see README.md
+//
+// © NewForester, 2018.  Available under MIT licence terms.
+//
+//! The msg42 module implements the _mavlink message trait_ for the
+//! MAVLink 'mission current' message (id 42).
+//!
+//! The message structure and the trait implementation are generated by
+//! build.rs from the MISSION_CURRENT definition in common.xml.
+//!
+//! The message is sent by the ADS-B Simulator in each burst once a mission
+//! has been uploaded.  No getter/setter functions are needed.
+//!
+include!(concat!(env!("OUT_DIR"), "/msg42.rs"));
+
+// ---------------------------------------------------------------------------
+
+/// The implementation of methods for the MAVLink 42 message type
+impl Message {
+    // new() creates and initialises a MAVLink 42 message structure
+    pub fn new() -> Message {
+        Message::default()
+    }
+}
+
+// EOF
@@ -0,0 +1,26 @@
+//! ADS-B Simulator - see README.md
+//
+// © NewForester, 2018.  Available under MIT licence terms.
+//
+//! The msg44 module implements the _mavlink message trait_ for the
+//! MAVLink 'mission count' message (id 44).
+//!
+//! The message structure and the trait implementation are generated by
+//! build.rs from the MISSION_COUNT definition in common.xml.
+//!
+//! The message is sent by ground stations to start the upload of a mission.
+//! No getter/setter functions are needed.
+//!
+include!(concat!(env!("OUT_DIR"), "/msg44.rs"));
+
+// ---------------------------------------------------------------------------
+
+/// The implementation of methods for the MAVLink 44 message type
+impl Message {
+    // new() creates and initialises a MAVLink 44 message structure
+    pub fn new() -> Message {
+        Message::default()
+    }
+}
+
+// EOF
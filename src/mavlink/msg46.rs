@@ -0,0 +1,26 @@
+//! ADS-B Simulator - see README.md
+//
+// © NewForester, 2018.  Available under MIT licence terms.
+//
+//! The msg46 module implements the _mavlink message trait_ for the
+//! MAVLink 'mission item reached' message (id 46).
+//!
+//! The message structure and the trait implementation are generated by
+//! build.rs from the MISSION_ITEM_REACHED definition in common.xml.
+//!
+//! The message is sent by the ADS-B Simulator as the UAV reaches each
+//! waypoint of its mission.  No getter/setter functions are needed.
+//!
+include!(concat!(env!("OUT_DIR"), "/msg46.rs"));
+
+// ---------------------------------------------------------------------------
+
+/// The implementation of methods for the MAVLink 46 message type
+impl Message {
+    // new() creates and initialises a MAVLink 46 message structure
+    pub fn new() -> Message {
+        Message::default()
+    }
+}
+
+// EOF
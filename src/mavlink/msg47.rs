@@ -0,0 +1,42 @@
+//! ADS-B Simulator - see README.md
+//
+// © NewForester, 2018.  Available under MIT licence terms.
+//
+//! The msg47 module implements the _mavlink message trait_ for the
+//! MAVLink 'mission acknowledgement' message (id 47).
+//!
+//! The message structure and the trait implementation are generated by
+//! build.rs from the MISSION_ACK definition in common.xml.
+//!
+//! The message is sent by the ADS-B Simulator at the end of each mission
+//! upload.
+//!
+include!(concat!(env!("OUT_DIR"), "/msg47.rs"));
+
+// ---------------------------------------------------------------------------
+
+/// The results of a mission upload (MAV_MISSION_RESULT):  those the simulator uses
+pub const MAV_MISSION_ACCEPTED: u8 = 0;
+pub const MAV_MISSION_UNSUPPORTED_FRAME: u8 = 2;
+pub const MAV_MISSION_UNSUPPORTED: u8 = 3;
+
+// ---------------------------------------------------------------------------
+
+/// The implementation of methods for the MAVLink 47 message type
+impl Message {
+    // new() creates and initialises a MAVLink 47 message structure
+    pub fn new() -> Message {
+        Message::default()
+    }
+
+    // set_result() sets the system and component the acknowledgement is for and the result
+    pub fn set_result(&mut self, target: (u8, u8), result: u8) -> &mut Self {
+        self.target_system = target.0;
+        self.target_component = target.1;
+        self.type_ = result;
+
+        self
+    }
+}
+
+// EOF
@@ -0,0 +1,35 @@
+//! ADS-B Simulator - see README.md
+//
+// © NewForester, 2018.  Available under MIT licence terms.
+//
+//! The msg51 module implements the _mavlink message trait_ for the
+//! MAVLink 'mission request' message (id 51).
+//!
+//! The message structure and the trait implementation are generated by
+//! build.rs from the MISSION_REQUEST_INT definition in common.xml.
+//!
+//! The message is sent by the ADS-B Simulator to ask for each item in turn
+//! during a mission upload.
+//!
+include!(concat!(env!("OUT_DIR"), "/msg51.rs"));
+
+// ---------------------------------------------------------------------------
+
+/// The implementation of methods for the MAVLink 51 message type
+impl Message {
+    // new() creates and initialises a MAVLink 51 message structure
+    pub fn new() -> Message {
+        Message::default()
+    }
+
+    // set_request() sets the system and component asked and the sequence number of the item wanted
+    pub fn set_request(&mut self, target: (u8, u8), seq: u16) -> &mut Self {
+        self.target_system = target.0;
+        self.target_component = target.1;
+        self.seq = seq;
+
+        self
+    }
+}
+
+// EOF
@@ -0,0 +1,72 @@
+//! ADS-B Simulator - see README.md
+//
+// © NewForester, 2018.  Available under MIT licence terms.
+//
+//! The msg73 module implements the _mavlink message trait_ for the
+//! MAVLink 'mission item' message (id 73).
+//!
+//! The message structure and the trait implementation are generated by
+//! build.rs from the MISSION_ITEM_INT definition in common.xml.
+//!
+//! The message is sent by ground stations with each item of a mission being
+//! uploaded.  The ADS-B Simulator uses the getter functions to take the
+//! position of each waypoint.
+//!
+include!(concat!(env!("OUT_DIR"), "/msg73.rs"));
+
+use ::coords::CwithV;
+
+// ---------------------------------------------------------------------------
+
+/// The co-ordinate frames of a mission item (MAV_FRAME):  those the simulator accepts
+pub const MAV_FRAME_GLOBAL: u8 = 0;
+pub const MAV_FRAME_GLOBAL_RELATIVE_ALT: u8 = 3;
+pub const MAV_FRAME_GLOBAL_INT: u8 = 5;
+pub const MAV_FRAME_GLOBAL_RELATIVE_ALT_INT: u8 = 6;
+
+/// The commands of a mission item (MAV_CMD):  those the simulator accepts
+pub const MAV_CMD_NAV_WAYPOINT: u16 = 16;
+pub const MAV_CMD_DO_CHANGE_SPEED: u16 = 178;
+
+// ---------------------------------------------------------------------------
+
+/// The implementation of methods for the MAVLink 73 message type
+impl Message {
+    // new() creates and initialises a MAVLink 73 message structure
+    pub fn new() -> Message {
+        Message::default()
+    }
+
+    // set_waypoint() sets the message to a waypoint at the position of the given CwithV structure (absolute altitude)
+    pub fn set_waypoint(&mut self, seq: u16, cwithv: &CwithV) -> &mut Self {
+        self.seq = seq;
+        self.frame = MAV_FRAME_GLOBAL_INT;
+        self.command = MAV_CMD_NAV_WAYPOINT;
+        self.autocontinue = 1;
+
+        self.x = (cwithv.get_latitude() * 1.0e7) as i32;
+        self.y = (cwithv.get_longitude() * 1.0e7) as i32;
+        self.z = cwithv.get_altitude();
+
+        self
+    }
+
+    // is_global() returns whether the position is a latitude, longitude and altitude (rather than local)
+    pub fn is_global(&self) -> bool {
+        [MAV_FRAME_GLOBAL, MAV_FRAME_GLOBAL_RELATIVE_ALT, MAV_FRAME_GLOBAL_INT, MAV_FRAME_GLOBAL_RELATIVE_ALT_INT].contains(&self.frame)
+    }
+
+    // is_relative() returns whether the altitude is relative to home (rather than above mean sea level)
+    pub fn is_relative(&self) -> bool {
+        self.frame == MAV_FRAME_GLOBAL_RELATIVE_ALT || self.frame == MAV_FRAME_GLOBAL_RELATIVE_ALT_INT
+    }
+
+    // get_cwithv() sets the position of the given CwithV structure to the message position (latitude and longitude in degE7)
+    pub fn get_cwithv(&self, cwithv: &mut CwithV) -> &Self {
+        cwithv.set_position(self.x as f32 / 1.0e7, self.y as f32 / 1.0e7, self.z);
+
+        self
+    }
+}
+
+// EOF
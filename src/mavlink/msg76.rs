@@ -0,0 +1,32 @@
+//! ADS-B Simulator - see README.md
+//
+// © NewForester, 2018.  Available under MIT licence terms.
+//
+//! The msg76 module implements the _mavlink message trait_ for the
+//! MAVLink 'command long' message (id 76).
+//!
+//! The message structure and the trait implementation are generated by
+//! build.rs from the COMMAND_LONG definition in common.xml.
+//!
+//! The message is sent by ground stations and autopilots.  The ADS-B
+//! Simulator acts on MAV_CMD_SET_MESSAGE_INTERVAL commands and answers all
+//! commands addressed to it with a COMMAND_ACK (77) message.
+//!
+include!(concat!(env!("OUT_DIR"), "/msg76.rs"));
+
+// ---------------------------------------------------------------------------
+
+/// The command that sets the interval between messages (param1 message id, param2 interval in us)
+pub const MAV_CMD_SET_MESSAGE_INTERVAL: u16 = 511;
+
+// ---------------------------------------------------------------------------
+
+/// The implementation of methods for the MAVLink 76 message type
+impl Message {
+    // new() creates and initialises a MAVLink 76 message structure
+    pub fn new() -> Message {
+        Message::default()
+    }
+}
+
+// EOF
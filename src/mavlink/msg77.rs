@@ -0,0 +1,41 @@
+//! ADS-B Simulator - see README.md
+//
+// © NewForester, 2018.  Available under MIT licence terms.
+//
+//! The msg77 module implements the _mavlink message trait_ for the
+//! MAVLink 'command acknowledgement' message (id 77).
+//!
+//! The message structure and the trait implementation are generated by
+//! build.rs from the COMMAND_ACK definition in common.xml.
+//!
+//! The message is sent by the ADS-B Simulator in answer to each COMMAND_LONG
+//! (76) message addressed to it.
+//!
+include!(concat!(env!("OUT_DIR"), "/msg77.rs"));
+
+// ---------------------------------------------------------------------------
+
+/// The results of a command (MAV_RESULT):  those the simulator uses
+pub const MAV_RESULT_ACCEPTED: u8 = 0;
+pub const MAV_RESULT_DENIED: u8 = 2;
+pub const MAV_RESULT_UNSUPPORTED: u8 = 3;
+
+// ---------------------------------------------------------------------------
+
+/// The implementation of methods for the MAVLink 77 message type
+impl Message {
+    // new() creates and initialises a MAVLink 77 message structure
+    pub fn new() -> Message {
+        Message::default()
+    }
+
+    // set_result() sets the command acknowledged and its result
+    pub fn set_result(&mut self, command: u16, result: u8) -> &mut Self {
+        self.command = command;
+        self.result = result;
+
+        self
+    }
+}
+
+// EOF
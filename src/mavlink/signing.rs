@@ -0,0 +1,322 @@
+//! ADS-B Simulator - see README.md
+//
+// © NewForester, 2018.  Available under MIT licence terms.
+//
+//! The signing module implements MAVLink 2 message signing.
+//!
+//! A signed message is a MAVLink 2 message followed by a 13 byte signature:
+//! the link id, a 48 bit timestamp (in units of 10 microseconds since the
+//! start of 2015) and the first 6 bytes of the SHA-256 hash of the secret key
+//! and everything that comes before them.  The timestamps of each stream
+//! (system id, component id and link id) must increase so that old messages
+//! cannot be replayed.
+//!
+//! The simulator otherwise speaks MAVLink 1 so `sign()` converts an outgoing
+//! message into a signed MAVLink 2 message and `verify()` checks an incoming
+//! message and converts it back.  Messages 84 and 202 steer the simulation so
+//! they are rejected unless they are signed.  Other messages may be unsigned.
+//!
+//! To test the rejection paths of the receiver, every so many messages may be
+//! sent with a bad signature.
+//!
+//! There is no crypto crate:  SHA-256 is short enough to write out.
+//!
+//! The `set_cli()` function provides the implementation of the parsing of
+//! certain command line parameters as described in README.md.
+//!
+use std::collections::HashMap;
+use std::io::{Error, ErrorKind};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use mavlink;
+
+// ---------------------------------------------------------------------------
+
+/// The messages that must be signed
+const MUST_SIGN: [u32; 2] = [84, 202];
+
+/// The start of 2015 (the MAVLink signing epoch) in seconds since the start of 1970
+const EPOCH_2015: u64 = 1_420_070_400;
+
+/// How far behind the local timestamp that of the first message of a stream may be (one minute)
+const MAX_AGE: u64 = 60 * 100_000;
+
+/// The SHA-256 round constants
+const K: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+
+// ---------------------------------------------------------------------------
+
+#[derive(Clone)]
+/// The Signing structure holds the secret key and link id and the timestamps of messages sent and received
+pub struct Signing {
+    key:        [u8; 32],
+    linkid:     u8,
+
+    // Every how many messages to sign badly (0 for never) and how many have been signed
+    badevery:   u32,
+    signed:     u32,
+
+    // The local time as an Instant and as a timestamp and the timestamp of the last message sent
+    epoch:      (Instant, u64),
+    lastsent:   u64,
+
+    // The timestamp of the last message of each stream (system id, component id, link id) and the messages rejected
+    streams:    HashMap<(u8, u8, u8), u64>,
+    rejected:   u64,
+}
+
+// ---------------------------------------------------------------------------
+
+/// The implementation of methods for the Signing type
+impl Signing {
+    // new() creates and initialises a Signing structure (with a key of all zeros)
+    pub fn new() -> Signing {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+        let timestamp = now.as_secs().saturating_sub(EPOCH_2015) * 100_000 + now.subsec_micros() as u64 / 10;
+
+        Signing {
+            key:        [0; 32],
+            linkid:     0,
+            badevery:   0,
+            signed:     0,
+            epoch:      (Instant::now(), timestamp),
+            lastsent:   0,
+            streams:    HashMap::new(),
+            rejected:   0,
+        }
+    }
+
+    // set_cli() extracts the secret key (64 hex digits or a passphrase), link id and bad signature rate from a CLI parameter string
+    pub fn set_cli(&mut self, cli: &str) -> Result<&mut Self, String> {
+        let parts: Vec<&str> = cli.split(':').collect();
+
+        if parts.len() > 3 || parts[0].is_empty() {
+            return Err(format!("'{}' is not of the form 'key:linkid:badevery'", cli));
+        }
+
+        if parts[0].len() == 64 && parts[0].chars().all(|cc| cc.is_ascii_hexdigit()) {
+            for (ii, byte) in self.key.iter_mut().enumerate() {
+                *byte = u8::from_str_radix(&parts[0][2 * ii .. 2 * ii + 2], 16).unwrap();
+            }
+        } else {
+            // as do ground stations, the key of a passphrase is its hash
+            self.key = sha256(parts[0].as_bytes());
+        }
+
+        if parts.len() > 1 && ! parts[1].is_empty() {
+            self.linkid = parts[1].parse().map_err(|_| format!("'{}' is not a link id (0 to 255)", parts[1]))?;
+        }
+
+        if parts.len() > 2 && ! parts[2].is_empty() {
+            self.badevery = parts[2].parse().map_err(|_| format!("'{}' is not a number of messages", parts[2]))?;
+        }
+
+        Ok(self)
+    }
+
+    // set_epoch() sets the timestamp that corresponds to the given instant (for testing with a simulated clock)
+    pub fn set_epoch(&mut self, now: Instant, timestamp: u64) -> &mut Self {
+        self.epoch = (now, timestamp);
+
+        self
+    }
+
+    // get_linkid() returns the link id of messages sent
+    pub fn get_linkid(&self) -> u8 {
+        self.linkid
+    }
+
+    // get_rejected() returns the number of messages rejected
+    pub fn get_rejected(&self) -> u64 {
+        self.rejected
+    }
+
+    // sign() converts a MAVLink 1 message into a signed MAVLink 2 message
+    pub fn sign(&mut self, buffy: &[u8], now: Instant) -> Result<Vec<u8>, Error> {
+        let mut frame = mavlink::to_v2(buffy, mavlink::IFLAG_SIGNED)?;
+
+        // the timestamp must increase even if the clock has not
+        let timestamp = self.timestamp(now).max(self.lastsent + 1);
+
+        self.lastsent = timestamp;
+
+        frame.push(self.linkid);
+
+        for ii in 0 .. 6 {
+            frame.push((timestamp >> (8 * ii)) as u8);
+        }
+
+        let mut signature = signature(&self.key, &frame);
+
+        self.signed = self.signed.wrapping_add(1);
+
+        if self.badevery != 0 && self.signed.is_multiple_of(self.badevery) {
+            signature[0] ^= 0xff;
+        }
+
+        frame.extend_from_slice(&signature);
+
+        Ok(frame)
+    }
+
+    // verify() checks the signature (if any) of a message, counts it if rejected and returns it as a MAVLink 1 message
+    pub fn verify(&mut self, buffy: &[u8], now: Instant) -> Result<Vec<u8>, Error> {
+        let result = self.check(buffy, now);
+
+        if result.is_err() {
+            self.rejected += 1;
+        }
+
+        result
+    }
+
+    // check() checks the signature and timestamp of a message and converts it to MAVLink 1
+    fn check(&mut self, buffy: &[u8], now: Instant) -> Result<Vec<u8>, Error> {
+        let frame = mavlink::to_v1(buffy)?;
+
+        let signed = buffy.len() > 2 && buffy[0] == mavlink::STX_V2 && buffy[2] & mavlink::IFLAG_SIGNED != 0;
+
+        if ! signed {
+            let msgid = mavlink::msgid(&frame).unwrap_or(0);
+
+            if MUST_SIGN.contains(&msgid) {
+                return Err(Error::new(ErrorKind::PermissionDenied, format!("Unsigned message {}", msgid)));
+            }
+
+            return Ok(frame);
+        }
+
+        let end = mavlink::HDR_SIZE_V2 + buffy[1] as usize + mavlink::CRC_SIZE;
+
+        let linkid = buffy[end];
+        let timestamp = buffy[end + 1 .. end + 7].iter().rev().fold(0u64, |timestamp, &byte| timestamp << 8 | byte as u64);
+
+        if signature(&self.key, &buffy[.. end + 7])[..] != buffy[end + 7 .. end + mavlink::SIG_SIZE] {
+            return Err(Error::new(ErrorKind::PermissionDenied, "Bad signature"));
+        }
+
+        let stream = (buffy[5], buffy[6], linkid);
+
+        match self.streams.get(&stream) {
+            Some(&last) if timestamp <= last => {
+                return Err(Error::new(ErrorKind::PermissionDenied, "Timestamp not after the last (replayed ?)"));
+            }
+            None if timestamp + MAX_AGE < self.timestamp(now) => {
+                return Err(Error::new(ErrorKind::PermissionDenied, "Timestamp too old"));
+            }
+            _ => (),
+        }
+
+        self.streams.insert(stream, timestamp);
+
+        Ok(frame)
+    }
+
+    // timestamp() returns the signing timestamp of an instant
+    fn timestamp(&self, now: Instant) -> u64 {
+        let (instant, timestamp) = self.epoch;
+
+        if now > instant {
+            let elapsed = now - instant;
+
+            timestamp + elapsed.as_secs() * 100_000 + elapsed.subsec_micros() as u64 / 10
+        } else {
+            timestamp
+        }
+    }
+}
+
+/// The implementation of the Default trait for the Signing type
+impl Default for Signing {
+    fn default() -> Signing {
+        Signing::new()
+    }
+}
+
+// ---------------------------------------------------------------------------
+
+// signature() returns the signature of a message:  the first 6 bytes of the hash of the key and the message
+fn signature(key: &[u8; 32], message: &[u8]) -> [u8; 6] {
+    let mut data = key.to_vec();
+
+    data.extend_from_slice(message);
+
+    let hash = sha256(&data);
+    let mut signature = [0; 6];
+
+    signature.copy_from_slice(&hash[.. 6]);
+
+    signature
+}
+
+// sha256() returns the SHA-256 hash of the given bytes
+pub fn sha256(bytes: &[u8]) -> [u8; 32] {
+    let mut hash: [u32; 8] = [
+        0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19,
+    ];
+
+    // pad with a 1 bit, zeros and the length in bits to a whole number of 64 byte blocks
+    let mut data = bytes.to_vec();
+
+    data.push(0x80);
+
+    while data.len() % 64 != 56 {
+        data.push(0);
+    }
+
+    for ii in (0 .. 8).rev() {
+        data.push(((bytes.len() as u64 * 8) >> (8 * ii)) as u8);
+    }
+
+    for block in data.chunks(64) {
+        let mut w = [0u32; 64];
+
+        for ii in 0 .. 16 {
+            w[ii] = (block[4 * ii] as u32) << 24 | (block[4 * ii + 1] as u32) << 16 |
+                    (block[4 * ii + 2] as u32) << 8 | block[4 * ii + 3] as u32;
+        }
+
+        for ii in 16 .. 64 {
+            let s0 = w[ii - 15].rotate_right(7) ^ w[ii - 15].rotate_right(18) ^ (w[ii - 15] >> 3);
+            let s1 = w[ii - 2].rotate_right(17) ^ w[ii - 2].rotate_right(19) ^ (w[ii - 2] >> 10);
+
+            w[ii] = w[ii - 16].wrapping_add(s0).wrapping_add(w[ii - 7]).wrapping_add(s1);
+        }
+
+        let mut v = hash;
+
+        for (&k, &w) in K.iter().zip(w.iter()) {
+            let s1 = v[4].rotate_right(6) ^ v[4].rotate_right(11) ^ v[4].rotate_right(25);
+            let ch = (v[4] & v[5]) ^ (! v[4] & v[6]);
+            let t1 = v[7].wrapping_add(s1).wrapping_add(ch).wrapping_add(k).wrapping_add(w);
+            let s0 = v[0].rotate_right(2) ^ v[0].rotate_right(13) ^ v[0].rotate_right(22);
+            let maj = (v[0] & v[1]) ^ (v[0] & v[2]) ^ (v[1] & v[2]);
+            let t2 = s0.wrapping_add(maj);
+
+            v = [t1.wrapping_add(t2), v[0], v[1], v[2], v[3].wrapping_add(t1), v[4], v[5], v[6]];
+        }
+
+        for (word, &v) in hash.iter_mut().zip(v.iter()) {
+            *word = word.wrapping_add(v);
+        }
+    }
+
+    let mut digest = [0; 32];
+
+    for (ii, byte) in digest.iter_mut().enumerate() {
+        *byte = (hash[ii / 4] >> (24 - 8 * (ii % 4))) as u8;
+    }
+
+    digest
+}
+
+// EOF
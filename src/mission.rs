@@ -0,0 +1,249 @@
+//! ADS-B Simulator - see README.md
+//
+// © NewForester, 2018.  Available under MIT licence terms.
+//
+//! The mission module holds the route the UAV is to fly, as uploaded by a
+//! ground station with the MAVLink mission protocol, and steers the UAV along
+//! it.
+//!
+//! An upload starts with a MISSION_COUNT (44) message.  The simulator asks
+//! for each item in turn with a MISSION_REQUEST_INT (51) message, asking again
+//! each tick until the MISSION_ITEM_INT (73) message arrives, and answers the
+//! last with a MISSION_ACK (47) message.  An upload that goes quiet for five
+//! seconds is dropped.  The new mission replaces the old only once complete.
+//!
+//! Waypoints (MAV_CMD_NAV_WAYPOINT) in a global frame are flown in turn in
+//! straight lines at the speed set by the last MAV_CMD_DO_CHANGE_SPEED item
+//! or else at the speed of the UAV on the command line.  The UAV holds its
+//! position once it has reached the last of them.  Relative altitudes are
+//! relative to the altitude of the UAV on the command line (there is no home
+//! position).  No other commands are accepted.
+//!
+use std::time::{Duration, Instant};
+
+use coords::CwithV;
+use mavlink::msg47::{MAV_MISSION_ACCEPTED, MAV_MISSION_UNSUPPORTED_FRAME, MAV_MISSION_UNSUPPORTED};
+use mavlink::msg73;
+use mavlink::msg73::{MAV_CMD_NAV_WAYPOINT, MAV_CMD_DO_CHANGE_SPEED};
+
+// ---------------------------------------------------------------------------
+
+/// How long an upload may go quiet, the speed (m/s) if the UAV has none and the acceptance radius (m) if a waypoint has none
+const UPLOAD_TIMEOUT: Duration = Duration::from_secs(5);
+const DEFAULT_SPEED: f32 = 10.0;
+const DEFAULT_RADIUS: f32 = 5.0;
+
+// ---------------------------------------------------------------------------
+
+/// The Item structure holds one mission item:  a waypoint (position and acceptance radius) or a change of speed
+struct Item {
+    command:    u16,
+    position:   CwithV,
+    radius:     f32,
+    speed:      f32,
+}
+
+/// The Upload structure holds an upload in progress:  who from, how many items and those received so far
+struct Upload {
+    source:     (u8, u8),
+    count:      u16,
+    items:      Vec<Item>,
+    lastheard:  Instant,
+}
+
+/// The Mission structure holds the mission flown, the item flown to now (and where the leg to it started),
+/// the speed set and any upload in progress
+pub struct Mission {
+    home:       f32,
+    items:      Vec<Item>,
+    current:    usize,
+    legstart:   Option<CwithV>,
+    speed:      Option<f32>,
+
+    upload:     Option<Upload>,
+}
+
+// ---------------------------------------------------------------------------
+
+/// The implementation of methods for the Mission type
+impl Mission {
+    // new() creates and initialises a Mission structure (with no mission) given the altitude relative altitudes are relative to
+    pub fn new(home: f32) -> Mission {
+        Mission {
+            home,
+            items:      Vec::new(),
+            current:    0,
+            legstart:   None,
+            speed:      None,
+            upload:     None,
+        }
+    }
+
+    // start_upload() starts an upload of the given number of items and returns the result (to acknowledge) if it is already over
+    pub fn start_upload(&mut self, source: (u8, u8), count: u16, now: Instant) -> Option<u8> {
+        self.upload = Some(Upload {
+            source,
+            count,
+            items:      Vec::with_capacity(count as usize),
+            lastheard:  now,
+        });
+
+        self.finish_upload()
+    }
+
+    // add_item() adds an item to the upload in progress and returns the result (to acknowledge) if it is over
+    // or why the item is ignored
+    pub fn add_item(&mut self, message: &msg73::Message, now: Instant) -> Result<Option<u8>, String> {
+        let result = {
+            let upload = match self.upload {
+                Some(ref mut upload) => upload,
+                None => return Err(format!("mission item {} not expected", message.seq)),
+            };
+
+            // an item out of turn is ignored (the one wanted will be asked for again)
+            if message.seq as usize != upload.items.len() {
+                return Err(format!("mission item {} out of sequence ({} wanted)", message.seq, upload.items.len()));
+            }
+
+            upload.lastheard = now;
+
+            match Self::item(message, self.home) {
+                Ok(item) => {
+                    upload.items.push(item);
+                    None
+                },
+                Err(result) => Some(result),
+            }
+        };
+
+        match result {
+            Some(result) => {
+                self.upload = None;
+                Ok(Some(result))
+            },
+            None => Ok(self.finish_upload()),
+        }
+    }
+
+    // finish_upload() replaces the mission flown with that uploaded once it has all its items
+    fn finish_upload(&mut self) -> Option<u8> {
+        if self.upload.as_ref().is_none_or(|upload| upload.items.len() < upload.count as usize) {
+            return None;
+        }
+
+        if let Some(upload) = self.upload.take() {
+            self.items = upload.items;
+            self.current = 0;
+            self.legstart = None;
+            self.speed = None;
+        }
+
+        Some(MAV_MISSION_ACCEPTED)
+    }
+
+    // item() converts a mission item message to an item (or returns why it cannot be flown)
+    fn item(message: &msg73::Message, home: f32) -> Result<Item, u8> {
+        let mut item = Item {
+            command:    message.command,
+            position:   CwithV::new(),
+            radius:     if message.param2 > 0.0 { message.param2 } else { DEFAULT_RADIUS },
+            speed:      message.param2,
+        };
+
+        match message.command {
+            MAV_CMD_NAV_WAYPOINT => {
+                if ! message.is_global() {
+                    return Err(MAV_MISSION_UNSUPPORTED_FRAME);
+                }
+
+                message.get_cwithv(&mut item.position);
+
+                if message.is_relative() {
+                    item.position.displace(0.0, 0.0, home);
+                }
+            },
+            MAV_CMD_DO_CHANGE_SPEED => (),
+            _ => return Err(MAV_MISSION_UNSUPPORTED),
+        }
+
+        Ok(item)
+    }
+
+    // abandon_quiet() drops an upload that has gone quiet and returns whether it did
+    pub fn abandon_quiet(&mut self, now: Instant) -> bool {
+        if self.upload.as_ref().is_some_and(|upload| now.duration_since(upload.lastheard) >= UPLOAD_TIMEOUT) {
+            self.upload = None;
+            return true;
+        }
+
+        false
+    }
+
+    // get_request() returns who to ask for which item next, if there is an upload in progress
+    pub fn get_request(&self) -> Option<((u8, u8), u16)> {
+        self.upload.as_ref().map(|upload| (upload.source, upload.items.len() as u16))
+    }
+
+    // get_count() returns the number of items in the mission flown
+    pub fn get_count(&self) -> usize {
+        self.items.len()
+    }
+
+    // get_current() returns the sequence number of the item flown to now (the count once the mission is over)
+    pub fn get_current(&self) -> u16 {
+        self.current as u16
+    }
+
+    // get_speed() returns the speed to fly at:  that set by the mission or else the given cruising speed (if any)
+    pub fn get_speed(&self, cruise: f32) -> f32 {
+        self.speed.unwrap_or(if cruise > 0.0 { cruise } else { DEFAULT_SPEED })
+    }
+
+    // get_leg() returns where the leg being flown starts and ends (the last waypoint twice once the mission is over)
+    pub fn get_leg(&self) -> Option<(CwithV, CwithV)> {
+        let waypoint = |item: &&Item| item.command == MAV_CMD_NAV_WAYPOINT;
+
+        let to = self.items[self.current ..].iter().find(waypoint).or_else(|| self.items.iter().rev().find(waypoint))?.position.clone();
+        let from = self.legstart.clone().unwrap_or_else(|| to.clone());
+
+        Some((from, to))
+    }
+
+    // steer() sets the velocity of the UAV to head for the next waypoint in time for the next tick
+    // and returns the sequence numbers of the waypoints reached
+    pub fn steer(&mut self, uav: &mut CwithV, cruise: f32, seconds: f32) -> Vec<u16> {
+        let mut reached = Vec::new();
+
+        // the first leg starts wherever the UAV is
+        if self.legstart.is_none() {
+            self.legstart = Some(uav.clone());
+        }
+
+        while let Some(item) = self.items.get(self.current) {
+            if item.command == MAV_CMD_DO_CHANGE_SPEED {
+                if item.speed > 0.0 {
+                    self.speed = Some(item.speed);
+                }
+            } else if uav.distance(&item.position) <= item.radius {
+                reached.push(self.current as u16);
+                self.legstart = Some(item.position.clone());
+            } else {
+                uav.head_for(&item.position, self.get_speed(cruise), seconds);
+
+                return reached;
+            }
+
+            self.current += 1;
+        }
+
+        // hold position at the last waypoint once the mission is over
+        match self.get_leg() {
+            Some((_, last)) => uav.head_for(&last, self.get_speed(cruise), seconds),
+            None            => uav.set_velocity(0.0, 0.0, 0.0),
+        };
+
+        reached
+    }
+}
+
+// EOF
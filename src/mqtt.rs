@@ -9,19 +9,102 @@
 //! certain command line parameters as described in README.md.
 //!
 //! The `publish()` and `subscribe()` functions implement the `/icaoAddr/msgId`
-//! MQTT topic namimg conventions.
+//! MQTT topic namimg conventions.  Other conventions may be described by topic
+//! templates with `{icao}`, `{msgid}`, `{msgname}`, `{sysid}`, `{compid}` and
+//! `{pubtopic}` placeholders:  `get_topic()` expands the publish template and
+//! `parse_topic()` matches a topic against the subscribe template.
+//!
+//! The `set_auth_cli()`, `set_tls_cli()`, `set_qos_cli()`, `set_retain_cli()`
+//! and `set_will_cli()` functions parse the parameters for brokers that are
+//! less easy going than a local mosquitto:  credentials, TLS, quality of
+//! service by topic, retained publishing and a last-will message.
 //!
 //! Concurrency-wise, the main routine creates a separate thread that handles
 //! the publication of all messages and calls the `subscribe()` function below.
-//! The `subscribe()` function, I believe creates a thread context to handle
-//! callback when messages are received.  I believe there is one such thread
-//! per subscription.
+//! The `subscribe()` function runs the network loop:  it handles callbacks
+//! when messages are received and, when the connection is lost, it reconnects
+//! with exponential backoff and subscribes again.  The state of the connection
+//! is shared by all clones of a `Client` so `publish()`, in the other thread,
+//! knows whether to send a message or to buffer (or drop) it.
 //!
 extern crate mosquitto_client;
 
-use std::io::Error;
+use std::collections::VecDeque;
+use std::io::{Error, ErrorKind};
+use std::{thread, time};
+
+use std::sync::{mpsc, Arc, Mutex};
+
+use broker;
+use mavlink::{Link, MavMessage};
+
+// ---------------------------------------------------------------------------
+
+// The libmosquitto error codes that mean there is no connection (to be retried)
+const MOSQ_ERR_NO_CONN: i32 = 4;
+const MOSQ_ERR_CONN_LOST: i32 = 7;
+
+// ---------------------------------------------------------------------------
 
-use std::sync::mpsc;
+#[derive(Clone, Copy, Debug, PartialEq)]
+/// The State enumeration is the state of the connection to the MQTT broker
+pub enum State {
+    Disconnected,
+    Connecting,
+    Connected,
+}
+
+// ---------------------------------------------------------------------------
+
+#[derive(Clone, Debug)]
+/// The Backoff structure holds the delays between attempts to reconnect
+pub struct Backoff {
+    min:        time::Duration,
+    max:        time::Duration,
+    delay:      time::Duration,
+}
+
+/// The implementation of methods for the Backoff type
+impl Backoff {
+    // new() creates a Backoff structure that doubles the delay from min up to max
+    pub fn new(min: time::Duration, max: time::Duration) -> Backoff {
+        Backoff {
+            min,
+            max,
+            delay:      min,
+        }
+    }
+
+    // next_delay() returns the delay before the next attempt and doubles the one after (up to the maximum)
+    pub fn next_delay(&mut self) -> time::Duration {
+        let delay = self.delay;
+
+        self.delay = if delay * 2 < self.max { delay * 2 } else { self.max };
+
+        delay
+    }
+
+    // reset() restores the minimum delay (on success)
+    pub fn reset(&mut self) {
+        self.delay = self.min;
+    }
+}
+
+// ---------------------------------------------------------------------------
+
+/// The placeholders that may appear in topic templates
+const PLACEHOLDERS: &[&str] = &["{pubtopic}", "{icao}", "{msgid}", "{msgname}", "{sysid}", "{compid}"];
+
+// ---------------------------------------------------------------------------
+
+#[derive(Clone, Debug, Default, PartialEq)]
+/// The Fields structure holds what a topic says about a message (so far as the subscribe template knows)
+pub struct Fields {
+    pub icao:       Option<u32>,
+    pub msgid:      Option<u32>,
+    pub sysid:      Option<u8>,
+    pub compid:     Option<u8>,
+}
 
 // ---------------------------------------------------------------------------
 
@@ -35,6 +118,42 @@ pub struct Client {
     pub pubtopic:   String,
     pub subtopic:   String,
 
+    // Topic templates for publishing and for interpreting subscriptions
+    pubtemplate: String,
+    subtemplate: String,
+
+    // Topic template for the JSON rendering of messages (empty for the publish template + /json)
+    jsontemplate: Option<String>,
+
+    // The ICAO address and MAVLink ids of the simulated device (for topic templates)
+    icao:       Option<u32>,
+    source:     (u8, u8),
+
+    // Credentials and TLS certificate files (CA, client certificate, client key)
+    username:   Option<String>,
+    password:   Option<String>,
+    tls:        Option<(String, Option<String>, Option<String>)>,
+
+    // Default QoS for publishing and subscribing, exceptions by topic (filter) and topics (filters) to retain
+    pubqos:     u32,
+    subqos:     u32,
+    qos:        Vec<(String, u32)>,
+    retain:     Vec<String>,
+
+    // Last-will topic and message (the topic defaults to pubtopic/status)
+    will:       Option<(String, String)>,
+
+    // The delays between attempts to reconnect
+    backoff:    Backoff,
+
+    // Messages (topic, payload, qos, retain) kept while disconnected, how many to keep and how many were lost
+    queue:      VecDeque<(String, Vec<u8>, u32, bool)>,
+    queuesize:  usize,
+    dropped:    u64,
+
+    // The state of the connection (shared by all clones)
+    state:      Arc<Mutex<State>>,
+
     handle:     mosquitto_client::Mosquitto,
 }
 
@@ -50,6 +169,24 @@ impl Client {
             port:       1883,
             pubtopic:   String::new(),
             subtopic:   String::new(),
+            pubtemplate: "{pubtopic}/{msgid}".to_string(),
+            subtemplate: "/{icao}/{msgid}".to_string(),
+            jsontemplate: None,
+            icao:       None,
+            source:     (Link::new().get_sysid(), Link::new().get_compid()),
+            username:   None,
+            password:   None,
+            tls:        None,
+            pubqos:     0,
+            subqos:     1,
+            qos:        Vec::new(),
+            retain:     Vec::new(),
+            will:       None,
+            backoff:    Backoff::new(time::Duration::new(1, 0), time::Duration::new(30, 0)),
+            queue:      VecDeque::new(),
+            queuesize:  0,
+            dropped:    0,
+            state:      Arc::new(Mutex::new(State::Disconnected)),
             handle:     mosquitto_client::Mosquitto::new("")
         }
     }
@@ -62,29 +199,44 @@ impl Client {
         println!("port:     {}", self.port);
         println!("pubtopic: {}", self.pubtopic);
         println!("subtopic: {}", self.subtopic);
+        println!("template: {} {}", self.pubtemplate, self.subtemplate);
+        println!("json:     {}", self.jsontemplate.as_ref().map_or("", |s| s.as_str()));
+        println!("icao:     {:?}", self.get_icao());
+        println!("username: {}", self.username.as_ref().map_or("", |s| s.as_str()));
+        println!("tls:      {}", self.tls.as_ref().map_or("", |t| t.0.as_str()));
+        println!("qos:      {}/{} {:?}", self.pubqos, self.subqos, self.qos);
+        println!("retain:   {:?}", self.retain);
+        println!("will:     {}", self.get_will().map_or(String::new(), |(t, m)| format!("{} {}", t, m)));
+        println!("backoff:  {:?}", self.backoff);
+        println!("buffer:   {}", self.queuesize);
+        println!("state:    {:?}", self.get_state());
 
         self
     }
 
     // set_cli() extract MQTT parameters from a CLI parameter string
-    pub fn set_cli(&mut self, cli: &str) -> &mut Self {
+    pub fn set_cli(&mut self, cli: &str) -> Result<&mut Self, String> {
         let fission: Vec<&str> = cli.split(',').collect();
 
-        if fission[0].len() != 0 {
+        if ! fission[0].is_empty() {
             self.clientid = fission[0].to_string();
         }
 
-        if fission.len() > 1 && fission[1].len() != 0 {
+        if fission.len() > 1 && ! fission[1].is_empty() {
             let pair: Vec<&str> = fission[1].split(':').collect();
 
             self.host = pair[0].to_string();
 
+            if pair.len() > 2 {
+                return Err(format!("'{}' is not of the form 'host:port'", fission[1]));
+            }
+
             if pair.len() > 1 {
-                self.port = pair[1].parse().unwrap();
+                self.port = pair[1].parse().map_err(|_| format!("'{}' is not a port number", pair[1]))?;
             }
         }
 
-        if fission.len() > 2 && fission[2].len() != 0 {
+        if fission.len() > 2 && ! fission[2].is_empty() {
             let pair: Vec<&str> = fission[2].split(':').collect();
 
             self.pubtopic = pair[0].to_string();
@@ -94,34 +246,331 @@ impl Client {
             }
         }
 
+        if fission.len() > 3 {
+            return Err(format!("'{}' is one parameter too many", fission[3..].join(",")));
+        }
+
+        Ok(self)
+    }
+
+    // set_auth_cli() extracts the MQTT user name and password from a CLI parameter string
+    pub fn set_auth_cli(&mut self, cli: &str) -> Result<&mut Self, String> {
+        let pair: Vec<&str> = cli.splitn(2, ':').collect();
+
+        if pair[0].is_empty() {
+            return Err(format!("'{}' is not of the form 'username:password'", cli));
+        }
+
+        self.username = Some(pair[0].to_string());
+        self.password = if pair.len() > 1 { Some(pair[1].to_string()) } else { None };
+
+        Ok(self)
+    }
+
+    // set_tls_cli() extracts the TLS CA file and, optionally, client certificate and key files from a CLI parameter string
+    pub fn set_tls_cli(&mut self, cli: &str) -> Result<&mut Self, String> {
+        let files: Vec<&str> = cli.split(',').collect();
+
+        match files.len() {
+            1 if ! files[0].is_empty() => {
+                self.tls = Some((files[0].to_string(), None, None));
+            }
+            3 if files.iter().all(|file| ! file.is_empty()) => {
+                self.tls = Some((files[0].to_string(), Some(files[1].to_string()), Some(files[2].to_string())));
+            }
+            _ => {
+                return Err(format!("'{}' is not of the form 'cafile' or 'cafile,certfile,keyfile'", cli));
+            }
+        }
+
+        Ok(self)
+    }
+
+    // set_qos_cli() extracts the default QoS and/or the QoS of individual topics from a CLI parameter string
+    pub fn set_qos_cli(&mut self, cli: &str) -> Result<&mut Self, String> {
+        fn qos(value: &str) -> Result<u32, String> {
+            match value.parse() {
+                Ok(qos) if qos <= 2 => Ok(qos),
+                _ => Err(format!("'{}' is not a QoS (0, 1 or 2)", value)),
+            }
+        }
+
+        for spec in cli.split(',') {
+            let pair: Vec<&str> = spec.rsplitn(2, ':').collect();
+
+            if pair.len() == 1 {
+                self.pubqos = qos(pair[0])?;
+                self.subqos = self.pubqos;
+            } else if pair[1].is_empty() {
+                return Err(format!("'{}' is not of the form 'topic:qos'", spec));
+            } else {
+                self.qos.push((pair[1].to_string(), qos(pair[0])?));
+            }
+        }
+
+        Ok(self)
+    }
+
+    // set_retain_cli() extracts the topics (or topic filters) of messages to be published retained from a CLI parameter string
+    pub fn set_retain_cli(&mut self, cli: &str) -> Result<&mut Self, String> {
+        for topic in cli.split(',') {
+            if topic.is_empty() {
+                return Err(format!("'{}' is not a list of topics", cli));
+            }
+
+            self.retain.push(topic.to_string());
+        }
+
+        Ok(self)
+    }
+
+    // set_will_cli() extracts the last-will topic and message from a CLI parameter string
+    pub fn set_will_cli(&mut self, cli: &str) -> Result<&mut Self, String> {
+        let pair: Vec<&str> = cli.splitn(2, ':').collect();
+
+        let message = if pair.len() > 1 && ! pair[1].is_empty() { pair[1] } else { "offline" };
+
+        self.will = Some((pair[0].to_string(), message.to_string()));
+
+        Ok(self)
+    }
+
+    // set_retry_cli() extracts the minimum and maximum delays (in seconds) between attempts to reconnect from a CLI parameter string
+    pub fn set_retry_cli(&mut self, cli: &str) -> Result<&mut Self, String> {
+        let pair: Vec<&str> = cli.split(':').collect();
+
+        if pair.len() != 2 {
+            return Err(format!("'{}' is not of the form 'delay:maxdelay'", cli));
+        }
+
+        let min: u64 = pair[0].parse().map_err(|_| format!("'{}' is not a number of seconds", pair[0]))?;
+        let max: u64 = pair[1].parse().map_err(|_| format!("'{}' is not a number of seconds", pair[1]))?;
+
+        if min == 0 || max < min {
+            return Err(format!("'{}' is not a delay of at least 1 s and a maximum no less than that", cli));
+        }
+
+        self.backoff = Backoff::new(time::Duration::new(min, 0), time::Duration::new(max, 0));
+
+        Ok(self)
+    }
+
+    // set_buffer_cli() extracts the number of messages to keep while disconnected from a CLI parameter string
+    pub fn set_buffer_cli(&mut self, cli: &str) -> Result<&mut Self, String> {
+        self.queuesize = cli.parse().map_err(|_| format!("'{}' is not a number of messages", cli))?;
+
+        Ok(self)
+    }
+
+    // set_template_cli() extracts the publish and subscribe topic templates from a CLI parameter string
+    pub fn set_template_cli(&mut self, cli: &str) -> Result<&mut Self, String> {
+        let pair: Vec<&str> = cli.split(';').collect();
+
+        if pair.len() > 2 {
+            return Err(format!("'{}' is not of the form 'pubtemplate;subtemplate'", cli));
+        }
+
+        let pubtemplate = if ! pair[0].is_empty() { pair[0] } else { self.pubtemplate.as_str() }.to_string();
+        let subtemplate = if pair.len() > 1 && ! pair[1].is_empty() { pair[1] } else { pubtemplate.as_str() }.to_string();
+
+        for template in [&pubtemplate, &subtemplate].iter() {
+            check_template(template)?;
+        }
+
+        // to be matched, the subscribe template placeholders must be whole topic levels
+        let levels = subtemplate.replace("{pubtopic}", "");
+
+        if levels.split('/').any(|level| level.contains('{') && ! PLACEHOLDERS.contains(&level)) {
+            return Err(format!("'{}' has a placeholder that is not a whole topic level", subtemplate));
+        }
+
+        self.pubtemplate = pubtemplate;
+        self.subtemplate = subtemplate;
+
+        Ok(self)
+    }
+
+    // set_json_cli() extracts the topic template for the JSON rendering of messages from a CLI parameter string
+    pub fn set_json_cli(&mut self, cli: &str) -> Result<&mut Self, String> {
+        check_template(cli)?;
+
+        self.jsontemplate = Some(cli.to_string());
+
+        Ok(self)
+    }
+
+    // set_icao_cli() extracts the ICAO address of the simulated device from a CLI parameter string
+    pub fn set_icao_cli(&mut self, cli: &str) -> Result<&mut Self, String> {
+        match u32::from_str_radix(cli, 16) {
+            Ok(icao) if icao <= 0xffffff => self.icao = Some(icao),
+            _ => return Err(format!("'{}' is not an ICAO address (6 hex digits)", cli)),
+        }
+
+        Ok(self)
+    }
+
+    // set_source() sets the MAVLink system and component ids of the simulated device
+    pub fn set_source(&mut self, sysid: u8, compid: u8) -> &mut Self {
+        self.source = (sysid, compid);
+
         self
     }
 
-    // get_202_subtopic() extract the subscription topic for MAVLink 202 messages
-    pub fn get_202_subtopic(&self) -> &str {
-        for topic in self.subtopic.split(';') {
-            let bits: Vec<&str> = topic.split('/').collect();
+    // is_publisher() returns whether there is somewhere to publish to:  a publish topic or a template that does without one
+    pub fn is_publisher(&self) -> bool {
+        ! self.pubtopic.is_empty() || ! self.pubtemplate.contains("{pubtopic}")
+    }
+
+    // get_templates() returns the publish and subscribe topic templates
+    pub fn get_templates(&self) -> (&str, &str) {
+        (&self.pubtemplate, &self.subtemplate)
+    }
+
+    // get_icao() returns the ICAO address of the simulated device:  as set or else the publish topic, if that is one
+    pub fn get_icao(&self) -> Option<u32> {
+        self.icao.or_else(|| u32::from_str_radix(self.pubtopic.trim_start_matches('/'), 16).ok())
+    }
+
+    // get_topic() expands the publish template for a message
+    pub fn get_topic(&self, msgid: u32, sysid: u8, compid: u8) -> String {
+        let msgname = MavMessage::name(msgid).map_or(msgid.to_string(), |name| name.to_string());
+
+        self.expand(&self.pubtemplate, &msgid.to_string(), &msgname, sysid, compid)
+    }
+
+    // get_json_topic() expands the JSON template for a message, if messages are to be rendered as JSON
+    pub fn get_json_topic(&self, msgid: u32, sysid: u8, compid: u8) -> Option<String> {
+        let template = match self.jsontemplate {
+            Some(ref template) if ! template.is_empty() => template.clone(),
+            Some(_)                                     => format!("{}/json", self.pubtemplate),
+            None                                        => return None,
+        };
+
+        let msgname = MavMessage::name(msgid).map_or(msgid.to_string(), |name| name.to_string());
+
+        Some(self.expand(&template, &msgid.to_string(), &msgname, sysid, compid))
+    }
+
+    // parse_topic() matches a topic (or subscription) against the subscribe template and returns what it says
+    pub fn parse_topic(&self, topic: &str) -> Option<Fields> {
+        let template = self.subtemplate.replace("{pubtopic}", &self.pubtopic);
+
+        let patterns: Vec<&str> = template.split('/').collect();
+        let levels: Vec<&str> = topic.split('/').collect();
+
+        if patterns.len() != levels.len() {
+            return None;
+        }
+
+        let mut fields = Fields::default();
+
+        for (pattern, level) in patterns.iter().zip(levels.iter()) {
+            // a single level wildcard says nothing about the field
+            if *level == "+" && pattern.starts_with('{') {
+                continue;
+            }
 
-            if bits[bits.len() - 1] == "202" {
-                return bits[1]
+            match *pattern {
+                "{icao}"    => fields.icao = Some(u32::from_str_radix(level, 16).ok()?),
+                "{msgid}"   => fields.msgid = Some(level.parse().ok()?),
+                "{msgname}" => fields.msgid = Some(MavMessage::id(level)?),
+                "{sysid}"   => fields.sysid = Some(level.parse().ok()?),
+                "{compid}"  => fields.compid = Some(level.parse().ok()?),
+                literal     => if literal != *level { return None; },
             }
         }
 
-        &self.pubtopic[..]       // avoid embarassment
+        Some(fields)
+    }
+
+    // get_ufo_icao() returns the ICAO address of the craft whose 202 messages are subscribed to, if there is one
+    pub fn get_ufo_icao(&self) -> Option<u32> {
+        self.subtopic.split(';')
+            .filter_map(|topic| self.parse_topic(topic))
+            .find(|fields| fields.msgid == Some(202) && fields.icao.is_some())
+            .and_then(|fields| fields.icao)
+    }
+
+    // expand() expands a topic template for the given message id and name
+    fn expand(&self, template: &str, msgid: &str, msgname: &str, sysid: u8, compid: u8) -> String {
+        let icao = format!("{:06x}", self.get_icao().unwrap_or(0));
+
+        template
+            .replace("{pubtopic}", &self.pubtopic)
+            .replace("{icao}", &icao)
+            .replace("{msgid}", msgid)
+            .replace("{msgname}", msgname)
+            .replace("{sysid}", &sysid.to_string())
+            .replace("{compid}", &compid.to_string())
+    }
+
+    // get_state() returns the state of the connection to the MQTT broker
+    pub fn get_state(&self) -> State {
+        *self.state.lock().unwrap()
+    }
+
+    // get_dropped() returns the number of messages not published for want of a connection
+    pub fn get_dropped(&self) -> u64 {
+        self.dropped
+    }
+
+    // get_qos() returns the QoS for a topic:  that of the first topic filter it matches or the default
+    pub fn get_qos(&self, topic: &str, default: u32) -> u32 {
+        self.qos.iter().find(|&(filter, _)| broker::matches(filter, topic)).map_or(default, |&(_, qos)| qos)
+    }
+
+    // get_retain() returns whether messages published to a topic are to be retained
+    pub fn get_retain(&self, topic: &str) -> bool {
+        self.retain.iter().any(|filter| broker::matches(filter, topic))
+    }
+
+    // get_will() returns the last-will topic and message, if there is one
+    pub fn get_will(&self) -> Option<(String, &str)> {
+        self.will.as_ref().map(|(topic, message)| {
+            let topic = if ! topic.is_empty() { topic.clone() } else { self.expand(&self.pubtemplate, "status", "status", self.source.0, self.source.1) };
+
+            (topic, message.as_str())
+        })
     }
 
     // connect() connects to the MQTT broker
     pub fn connect(&mut self) -> &mut Self {
         self.handle = mosquitto_client::Mosquitto::new_session(&self.clientid, false);
 
-        match self.handle.connect(&self.host, self.port) {
-            Ok(_)  => println!("MQTT connection successful"),
-            Err(e) => println!("MQTT connection error: {}", e),
+        if let Some(ref username) = self.username {
+            match self.set_username_pw(username, self.password.as_deref()) {
+                Ok(_)  => println!("MQTT set credentials successful"),
+                Err(e) => println!("MQTT set credentials error: {}", e),
+            }
         }
 
-        match self.handle.reconnect_delay_set(0, 0, false) {
-            Ok(_)  => println!("MQTT set delay successful"),
-            Err(e) => println!("MQTT set delay error: {}", e),
+        if let Some((ref cafile, ref certfile, ref keyfile)) = self.tls {
+            match self.set_tls(cafile, certfile.as_ref().map(|s| s.as_str()), keyfile.as_ref().map(|s| s.as_str())) {
+                Ok(_)  => println!("MQTT set TLS successful"),
+                Err(e) => println!("MQTT set TLS error: {}", e),
+            }
+        }
+
+        // tell the world when the simulated device goes missing (and, below, when it is there)
+        let will = self.get_will().map(|(topic, message)| (topic, message.to_string()));
+
+        if let Some((ref topic, ref message)) = will {
+            match self.handle.will_set(topic, message.as_bytes(), self.get_qos(topic, self.pubqos), true) {
+                Ok(_)  => println!("MQTT set will successful {}", topic),
+                Err(e) => println!("MQTT set will error: {}", e),
+            }
+        }
+
+        // the connection is established (or not) in the network loop, see subscribe()
+        match self.handle.connect(&self.host, self.port) {
+            Ok(_)  => {
+                println!("MQTT connecting to {}:{}", self.host, self.port);
+                self.set_state(State::Connecting);
+            },
+            Err(e) => {
+                println!("MQTT connection error: {} (will retry)", e);
+                self.set_state(State::Disconnected);
+            },
         }
 
         self
@@ -142,47 +591,202 @@ impl Client {
         self
     }
 
-    // publish() publishes a message to an MQTT broker subtopic
+    // publish() publishes a message to an MQTT broker subtopic (or keeps it until reconnected)
     pub fn publish(&mut self, payload: &[u8], tail: &str) -> Result<usize, Error> {
         let mut fussy = self.pubtopic.clone();
         fussy.push_str(tail);
 
-        match self.handle.publish(&fussy, payload, 0, false) {
-            Ok(_)  => (), // println!("MQTT publish successful"),
-            Err(e) => println!("MQTT publish error: {}", e),
-        };
+        self.publish_to(&fussy, payload)
+    }
+
+    // publish_to() publishes a message to an MQTT broker topic (or keeps it until reconnected)
+    pub fn publish_to(&mut self, topic: &str, payload: &[u8]) -> Result<usize, Error> {
+        let fussy = topic.to_string();
+
+        let qos = self.get_qos(&fussy, self.pubqos);
+        let retain = self.get_retain(&fussy);
+
+        if self.get_state() == State::Connected {
+            self.flush()?;
+
+            if self.queue.is_empty() {
+                match self.handle.publish(&fussy, payload, qos, retain) {
+                    Ok(_)  => return Ok(payload.len()),
+                    Err(ref e) if e.error() == MOSQ_ERR_NO_CONN || e.error() == MOSQ_ERR_CONN_LOST => (),
+                    Err(e) => return Err(Error::other(e)),
+                }
+            }
+        }
+
+        // no connection:  keep the message for later or drop it
+        if self.queuesize == 0 {
+            self.dropped += 1;
+
+            return Err(Error::new(ErrorKind::NotConnected, format!("MQTT not connected: message to {} dropped", fussy)));
+        }
+
+        if self.queue.len() == self.queuesize {
+            self.queue.pop_front();
+            self.dropped += 1;
+        }
+
+        self.queue.push_back((fussy, payload.to_vec(), qos, retain));
 
         Ok(0)
     }
 
-    // subscribe() subscribes to a list MQTT broker topics and/or subtopics
-    pub fn subscribe<F>(&mut self, channel: &mpsc::Sender<Vec<u8>>, callback: F) -> &mut Self
-                where F: Fn(&mpsc::Sender<Vec<u8>>, &[u8]) -> () {
+    // flush() publishes the messages kept while disconnected (oldest first)
+    fn flush(&mut self) -> Result<(), Error> {
+        if ! self.queue.is_empty() {
+            println!("MQTT publishing {} messages kept while disconnected ({} dropped)", self.queue.len(), self.dropped);
+        }
 
-        for topic in self.subtopic.split(';') {
-            match self.handle.subscribe(topic, 1) {
-                Ok(_)  => println!("MQTT subscribe successful {}", topic),
-                Err(e) => println!("MQTT subscribe error: {}", e),
+        while let Some((topic, payload, qos, retain)) = self.queue.pop_front() {
+            match self.handle.publish(&topic, &payload, qos, retain) {
+                Ok(_)  => (),
+                Err(ref e) if e.error() == MOSQ_ERR_NO_CONN || e.error() == MOSQ_ERR_CONN_LOST => {
+                    self.queue.push_front((topic, payload, qos, retain));
+                    break;
+                },
+                Err(e) => return Err(Error::other(e)),
             }
         }
 
+        Ok(())
+    }
+
+    // subscribe() subscribes to a list MQTT broker topics and/or subtopics and runs the network loop
+    pub fn subscribe<F>(&mut self, channel: &mpsc::Sender<Vec<u8>>, callback: F) -> &mut Self
+                where F: Fn(&mpsc::Sender<Vec<u8>>, &[u8]) {
+
+        let topics: Vec<(&str, u32)> =
+            self.subtopic.split(';').filter(|topic| ! topic.is_empty()).map(|topic| (topic, self.get_qos(topic, self.subqos))).collect();
+
+        let will = self.get_will().map(|(topic, _)| (topic.clone(), self.get_qos(&topic, self.pubqos)));
+
+        let handle = &self.handle;
+        let state = &self.state;
+
         {
-            let mut mc = self.handle.callbacks(0);
+            // the data are the number of messages received and whether to stop
+            let mut mc = self.handle.callbacks((0, false));
 
             mc.on_message(move |data, msg| {
-                *data += 1;
+                data.0 += 1;
                 callback(channel, msg.payload());
             });
 
-            match self.handle.loop_until_disconnect(200) {
-                Ok(_)  => println!("MQTT disconnect successful"),
-                Err(e) => println!("MQTT disconnect error: {}", e),
+            // (re)subscribe on each (re)connection:  the broker may have forgotten all about us
+            mc.on_connect(|_, rc| {
+                if rc != 0 {
+                    println!("MQTT connection refused: {}", rc);
+                    return;
+                }
+
+                println!("MQTT connection successful");
+                *state.lock().unwrap() = State::Connected;
+
+                for &(topic, qos) in topics.iter() {
+                    match handle.subscribe(topic, qos) {
+                        Ok(_)  => println!("MQTT subscribe successful {}", topic),
+                        Err(e) => println!("MQTT subscribe error: {}", e),
+                    }
+                }
+
+                if let Some((ref topic, qos)) = will {
+                    match handle.publish(topic, b"online", qos, true) {
+                        Ok(_)  => (),
+                        Err(e) => println!("MQTT publish error: {}", e),
+                    }
+                }
+            });
+
+            // a disconnection asked for (rc 0) ends the loop, any other is retried
+            mc.on_disconnect(|data, rc| {
+                println!("MQTT disconnected: {}", rc);
+                *state.lock().unwrap() = State::Disconnected;
+                data.1 = rc == 0;
+            });
+
+            let mut backoff = self.backoff.clone();
+
+            // the callbacks that do_loop() calls change the data behind clippy's back
+            #[allow(clippy::while_immutable_condition)]
+            while ! mc.data.1 {
+                match self.handle.do_loop(200) {
+                    Ok(_)  => {
+                        if self.get_state() == State::Connected {
+                            backoff.reset();
+                        }
+                    },
+                    Err(e) => {
+                        self.set_state(State::Disconnected);
+
+                        let delay = backoff.next_delay();
+
+                        println!("MQTT not connected: {} (retry in {} s)", e, delay.as_secs());
+
+                        thread::sleep(delay);
+
+                        match self.handle.reconnect() {
+                            Ok(_)  => self.set_state(State::Connecting),
+                            Err(e) => println!("MQTT reconnection error: {}", e),
+                        }
+                    },
+                }
             }
-            println!("received {} messages",mc.data);
+
+            println!("received {} messages", mc.data.0);
         }
 
         self
     }
+
+    // set_state() records the state of the connection (for all clones)
+    fn set_state(&self, state: State) {
+        *self.state.lock().unwrap() = state;
+    }
+
+    // set_username_pw() sets the credentials presented when connecting
+    fn set_username_pw(&self, username: &str, password: Option<&str>) -> Result<(), String> {
+        if username.contains('\0') || password.is_some_and(|password| password.contains('\0')) {
+            return Err("credentials contain a null".to_string());
+        }
+
+        self.handle.username_pw_set(username, password).map_err(|e| e.to_string())
+    }
+
+    // set_tls() sets the CA file and, optionally, the client certificate and key files for TLS
+    fn set_tls(&self, cafile: &str, certfile: Option<&str>, keyfile: Option<&str>) -> Result<(), String> {
+        match (certfile, keyfile) {
+            (Some(certfile), Some(keyfile)) => self.handle.tls_set(cafile, certfile, keyfile, None),
+            _                               => self.handle.tls_ca_set(cafile),
+        }.map_err(|e| e.to_string())
+    }
+}
+
+/// The implementation of the Default trait for the Client type
+impl Default for Client {
+    fn default() -> Client {
+        Client::new()
+    }
+}
+
+// ---------------------------------------------------------------------------
+
+// check_template() returns an error if a topic template has a placeholder that is not known
+fn check_template(template: &str) -> Result<(), String> {
+    let mut residue = template.to_string();
+
+    for placeholder in PLACEHOLDERS {
+        residue = residue.replace(placeholder, "");
+    }
+
+    if residue.contains('{') || residue.contains('}') {
+        return Err(format!("'{}' has a placeholder other than {}", template, PLACEHOLDERS.join(", ")));
+    }
+
+    Ok(())
 }
 
 // EOF
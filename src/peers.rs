@@ -0,0 +1,110 @@
+//! ADS-B Simulator - see README.md
+//
+// © NewForester, 2018.  Available under MIT licence terms.
+//
+//! The peers module keeps track of the MAVLink components the ADS-B Simulator
+//! hears heartbeats from so it can report when one of them goes silent.
+//!
+//! Components are identified by the system and component ids in the header
+//! of their heartbeat messages.  A component is reported silent once when no
+//! heartbeat has been heard for the timeout period and reported again when
+//! its heartbeats resume.
+//!
+//! The methods return what there is to report and leave the reporting to the
+//! simulator.
+//!
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+// ---------------------------------------------------------------------------
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+/// The Heard enumeration lists the heartbeats worth reporting
+pub enum Heard {
+    First,
+    Resumed,
+}
+
+/// The Peer structure holds what is known about one component
+struct Peer {
+    mavtype:    u8,
+    lastheard:  Instant,
+    silent:     bool,
+}
+
+// ---------------------------------------------------------------------------
+
+/// The Peers structure holds all components heard from (keyed by system and component id)
+pub struct Peers {
+    timeout:    Duration,
+    peers:      HashMap<(u8, u8), Peer>,
+}
+
+// ---------------------------------------------------------------------------
+
+/// The implementation of methods for the Peers type
+impl Peers {
+    // new() creates and initialises a Peers structure
+    pub fn new() -> Peers {
+        Peers {
+            timeout:    Duration::new(3, 0),
+            peers:      HashMap::new(),
+        }
+    }
+
+    // heard() records the receipt of a heartbeat from a component and returns whether it is the first or resumes
+    pub fn heard(&mut self, sysid: u8, compid: u8, mavtype: u8, now: Instant) -> Option<Heard> {
+        let mut heard = None;
+
+        let peer = self.peers.entry((sysid, compid)).or_insert_with(|| {
+            heard = Some(Heard::First);
+
+            Peer {
+                mavtype,
+                lastheard:  now,
+                silent:     false,
+            }
+        });
+
+        if peer.silent {
+            heard = Some(Heard::Resumed);
+        }
+
+        peer.mavtype = mavtype;
+        peer.lastheard = now;
+        peer.silent = false;
+
+        heard
+    }
+
+    // check() returns the system id, component id and type of components that have (just) gone silent
+    pub fn check(&mut self, now: Instant) -> Vec<(u8, u8, u8)> {
+        let mut silent: Vec<(u8, u8, u8)> = Vec::new();
+
+        for (&(sysid, compid), peer) in self.peers.iter_mut() {
+            if ! peer.silent && now.duration_since(peer.lastheard) > self.timeout {
+                silent.push((sysid, compid, peer.mavtype));
+
+                peer.silent = true;
+            }
+        }
+
+        silent.sort();
+
+        silent
+    }
+
+    // get_timeout() returns how long a component may go without a heartbeat before it is silent
+    pub fn get_timeout(&self) -> Duration {
+        self.timeout
+    }
+}
+
+/// The implementation of the Default trait for the Peers type
+impl Default for Peers {
+    fn default() -> Peers {
+        Peers::new()
+    }
+}
+
+// EOF
@@ -0,0 +1,121 @@
+//! ADS-B Simulator - see README.md
+//
+// © NewForester, 2018.  Available under MIT licence terms.
+//
+//! The rates module keeps track of how often the ADS-B Simulator is to send
+//! each of its MAVLink messages and when each was last sent.
+//!
+//! Every message is sent once a second unless a REQUEST_DATA_STREAM (66)
+//! message or a MAV_CMD_SET_MESSAGE_INTERVAL command says otherwise.  A
+//! message may be sent more often, less often or not at all.
+//!
+//! The simulator ticks as often as the most frequent message needs so
+//! `period()` is never more than a second.  A message is due less than half a
+//! period early and the next is due an interval after it was due, not after
+//! it was sent, so a message keeps its rate when the period does not divide
+//! its interval.  The `schedule()` function does the sums for `due()` and for
+//! anything else sent at a fixed interval.
+//!
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+// ---------------------------------------------------------------------------
+
+/// The interval between messages unless told otherwise and the shortest allowed (100 Hz)
+const DEFAULT_INTERVAL: Duration = Duration::from_secs(1);
+const MINIMUM_INTERVAL: Duration = Duration::from_millis(10);
+
+// ---------------------------------------------------------------------------
+
+/// The Rates structure holds the intervals that are not the default (None if stopped) and when each message was last sent
+pub struct Rates {
+    intervals:  HashMap<u32, Option<Duration>>,
+    lastsent:   HashMap<u32, Instant>,
+}
+
+// ---------------------------------------------------------------------------
+
+/// The implementation of methods for the Rates type
+impl Rates {
+    // new() creates and initialises a Rates structure (every message once a second)
+    pub fn new() -> Rates {
+        Rates {
+            intervals:  HashMap::new(),
+            lastsent:   HashMap::new(),
+        }
+    }
+
+    // set_interval() sets the interval between messages with the given id (None to stop them)
+    pub fn set_interval(&mut self, msgid: u32, interval: Option<Duration>) -> &mut Self {
+        let interval = interval.map(|interval| if interval < MINIMUM_INTERVAL { MINIMUM_INTERVAL } else { interval });
+
+        self.intervals.insert(msgid, interval);
+
+        self
+    }
+
+    // set_rate() sets the number of messages with the given id a second (0 to stop them)
+    pub fn set_rate(&mut self, msgid: u32, rate: u16) -> &mut Self {
+        let interval = if rate == 0 { None } else { Some(DEFAULT_INTERVAL / rate as u32) };
+
+        self.set_interval(msgid, interval)
+    }
+
+    // set_default() restores the interval between messages with the given id to the default
+    pub fn set_default(&mut self, msgid: u32) -> &mut Self {
+        self.set_interval(msgid, Some(DEFAULT_INTERVAL))
+    }
+
+    // get_interval() returns the interval between messages with the given id (None if stopped)
+    pub fn get_interval(&self, msgid: u32) -> Option<Duration> {
+        self.intervals.get(&msgid).cloned().unwrap_or(Some(DEFAULT_INTERVAL))
+    }
+
+    // period() returns how often the simulator must tick:  the shortest interval (but at least once a second)
+    pub fn period(&self) -> Duration {
+        self.intervals.values().filter_map(|&interval| interval).fold(DEFAULT_INTERVAL, |period, interval| period.min(interval))
+    }
+
+    // due() returns whether a message with the given id is due and, if it is, notes when it was due
+    pub fn due(&mut self, msgid: u32, now: Instant) -> bool {
+        let interval = match self.get_interval(msgid) {
+            Some(interval) => interval,
+            None           => return false,
+        };
+
+        match schedule(self.lastsent.get(&msgid).cloned(), interval, self.period() / 2, now) {
+            Some(sent) => {
+                self.lastsent.insert(msgid, sent);
+                true
+            },
+            None => false,
+        }
+    }
+}
+
+/// The implementation of the Default trait for the Rates type
+impl Default for Rates {
+    fn default() -> Rates {
+        Rates::new()
+    }
+}
+
+// ---------------------------------------------------------------------------
+
+// schedule() returns when something sent at an interval was due, if it is due now (or less than the tolerance from now)
+//
+// It resynchronises with now if it has fallen a whole interval behind.
+pub fn schedule(lastsent: Option<Instant>, interval: Duration, tolerance: Duration, now: Instant) -> Option<Instant> {
+    let next = match lastsent {
+        Some(lastsent) => lastsent + interval,
+        None           => return Some(now),
+    };
+
+    if now + tolerance <= next {
+        return None;
+    }
+
+    Some(if now < next + interval { next } else { now })
+}
+
+// EOF
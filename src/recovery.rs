@@ -0,0 +1,152 @@
+//! ADS-B Simulator - see README.md
+//
+// © NewForester, 2018.  Available under MIT licence terms.
+//
+//! The recovery module decides how the UAV gets back to its route once 84
+//! messages (avoidance manoeuvres) stop.
+//!
+//! After the last 84 message, the UAV holds the course it was given for the
+//! hold time (a second unless told otherwise) and then recovers in one of
+//! three ways:
+//!
+//!   * `resume` - resume the original velocity, on a track parallel to the
+//!     original line, or, flying a mission, head for the waypoint it was
+//!     heading for (the default);
+//!   * `track` - steer back onto the original line, or the leg of the mission
+//!     being flown, at 45 degrees and then follow it;
+//!   * `next` - head straight for the next planned point:  the waypoint being
+//!     flown to or, without a mission, the point of the original line the UAV
+//!     would have reached by the end of the hold had it not been diverted.
+//!
+//! The UAV is back on its route once within 5 m of the line or point.
+//!
+//! The `set_cli()` function provides the implementation of the parsing of
+//! certain command line parameters as described in README.md.
+//!
+use std::time::Duration;
+
+use coords::CwithV;
+
+// ---------------------------------------------------------------------------
+
+/// How close (m) to the line or point recovered to the UAV must come
+const RADIUS: f32 = 5.0;
+
+// ---------------------------------------------------------------------------
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+/// The Strategy enumeration lists the ways the UAV may get back to its route
+pub enum Strategy {
+    Resume,
+    Track,
+    Next,
+}
+
+/// The Recovery structure holds the strategy, the hold time and, while recovering, the point headed for
+pub struct Recovery {
+    strategy:   Strategy,
+    hold:       Duration,
+
+    target:     Option<CwithV>,
+}
+
+// ---------------------------------------------------------------------------
+
+/// The implementation of methods for the Recovery type
+impl Recovery {
+    // new() creates and initialises a Recovery structure (resume after a second)
+    pub fn new() -> Recovery {
+        Recovery {
+            strategy:   Strategy::Resume,
+            hold:       Duration::from_secs(1),
+            target:     None,
+        }
+    }
+
+    // set_cli() extract the strategy and hold time (in seconds) from a CLI parameter string
+    pub fn set_cli(&mut self, cli: &str) -> Result<&mut Self, String> {
+        let pair: Vec<&str> = cli.split(':').collect();
+
+        if pair.len() > 2 {
+            return Err(format!("'{}' is not of the form 'strategy:hold'", cli));
+        }
+
+        self.strategy = match pair[0] {
+            "resume"    => Strategy::Resume,
+            "track"     => Strategy::Track,
+            "next"      => Strategy::Next,
+            ""          => self.strategy,
+            strategy    => return Err(format!("'{}' is not a strategy (resume, track or next)", strategy)),
+        };
+
+        if pair.len() > 1 && ! pair[1].is_empty() {
+            let seconds: f32 = pair[1].parse().map_err(|_| format!("'{}' is not a number of seconds", pair[1]))?;
+
+            if ! (seconds >= 0.0 && seconds.is_finite()) {
+                return Err(format!("'{}' is not a number of seconds", pair[1]));
+            }
+
+            self.hold = Duration::from_millis((seconds * 1000.0).round() as u64);
+        }
+
+        Ok(self)
+    }
+
+    // get_strategy() returns the strategy
+    pub fn get_strategy(&self) -> Strategy {
+        self.strategy
+    }
+
+    // get_hold() returns how long the UAV holds its course after the last 84 message
+    pub fn get_hold(&self) -> Duration {
+        self.hold
+    }
+
+    // diverted() forgets the point headed for (another 84 message has arrived)
+    pub fn diverted(&mut self) -> &mut Self {
+        self.target = None;
+
+        self
+    }
+
+    // steer() sets the velocity of the UAV to get back to its route, the line from one point to another, and returns whether it is back
+    pub fn steer(&mut self, uav: &mut CwithV, from: &CwithV, to: &CwithV, next: &CwithV, speed: f32, seconds: f32) -> bool {
+        match self.strategy {
+            Strategy::Resume => true,
+            Strategy::Track => {
+                let length = from.distance(to);
+                let along = uav.along(from, to);
+                let offtrack = uav.distance(&from.towards(to, along));
+
+                if length == 0.0 || offtrack <= RADIUS {
+                    return true;
+                }
+
+                // head for the point as far along the line as the UAV is off it (but no further than its end)
+                uav.head_for(&from.towards(to, (along + offtrack).min(length)), speed, seconds);
+
+                false
+            },
+            Strategy::Next => {
+                let target = self.target.get_or_insert_with(|| next.clone()).clone();
+
+                if uav.distance(&target) <= RADIUS {
+                    return true;
+                }
+
+                uav.head_for(&target, speed, seconds);
+
+                false
+            },
+        }
+    }
+}
+
+/// The implementation of the Default trait for the Recovery type
+impl Default for Recovery {
+    fn default() -> Recovery {
+        Recovery::new()
+    }
+}
+
+// EOF
@@ -0,0 +1,1124 @@
+//! ADS-B Simulator - see README.md
+//
+// © NewForester, 2018.  Available under MIT licence terms.
+//
+//! The simulator module is the heart of the ADS-B Simulator.
+//!
+//! The `Simulator` structure holds the state of the simulation and its `tick()`
+//! method does everything that has to be done once a second (or more often if
+//! some message is wanted more often):  it handles the messages received since
+//! the last tick, moves the craft and returns the burst of messages due.  It
+//! does no I/O and does not look at the time of day so a test harness can
+//! drive it with a simulated clock.
+//!
+//! The UAV flies a straight line unless an 84 message says otherwise, or goes
+//! wherever a SITL autopilot says it is.  A ground station may upload a
+//! mission for it to fly instead (see the mission module).  Once 84 messages
+//! stop, the UAV gets back to its route as the recovery module says.
+//!
+//! Every message received is counted in the link statistics (see the
+//! linkstats module).  When messages are signed, those whose signatures do
+//! not check out are dropped.  Messages addressed to some other system or
+//! component, 84 messages included, are ignored.
+//!
+//! An autopilot may configure the ICAO address, callsign, emitter type and
+//! squawk of the UAV as it would a uAvionix transponder (10001 and 10002).  A
+//! ground station may read and set the same and the receiver range, position
+//! noise and message rates as parameters (20, 21 and 23):  changes take
+//! effect at once.
+//!
+//! REQUEST_DATA_STREAM (66) messages and MAV_CMD_SET_MESSAGE_INTERVAL commands
+//! change how often each message is sent (see the rates module).
+//!
+//! The `producer()` function is the simulator's main routine.  It calls
+//! `tick()` once a second, or as often as the most frequent message needs,
+//! and sends what it returns over all the transports configured (see the
+//! transport module).
+//!
+//! In terms of message passing, the callback routine called for each MQTT
+//! message received simply sends the message via a Rust channel to the
+//! producer() thread.  The producer does all things message to avoid the need
+//! for mutual exclusion mechanisms.
+//!
+use std::{iter, thread, time};
+use std::sync::mpsc;
+
+use cli::Options;
+use coords::CwithV;
+use gdl90;
+use linkstats::LinkStats;
+use mavlink;
+use mavlink::{Link, Message, MavMessage};
+use mavlink::msg76::MAV_CMD_SET_MESSAGE_INTERVAL;
+use mavlink::msg77::{MAV_RESULT_ACCEPTED, MAV_RESULT_DENIED, MAV_RESULT_UNSUPPORTED};
+use mavlink::msg22::{MAV_PARAM_TYPE_UINT8, MAV_PARAM_TYPE_UINT16, MAV_PARAM_TYPE_UINT32, MAV_PARAM_TYPE_REAL32};
+use mavlink::msg47::MAV_MISSION_ACCEPTED;
+use mavlink::signing::Signing;
+use mission::Mission;
+use peers::{Heard, Peers};
+use rates;
+use rates::Rates;
+use recovery::Recovery;
+use snapshot;
+use transport::{FanOut, Transport};
+
+// ---------------------------------------------------------------------------
+
+/// The ICAO address used in 246 messages when no other is known
+pub const UFO_ICAO: u32 = 0x00300159;
+
+/// The ids of the MAVLink messages in each burst in the order they are sent
+pub const BURST: [u32; 5] = [0, 66, 203, 202, 246];
+
+/// The ids of the MAVLink messages added to each burst once an autopilot has configured the transponder
+pub const CONFIGURED: [u32; 1] = [10003];
+
+/// The ids of the MAVLink messages added to each burst once a ground station has uploaded a mission
+pub const MISSION: [u32; 1] = [42];
+
+/// The autopilot (system and component ids) the simulated device asks for its position once a second by way of its 66 messages
+pub const AUTOPILOT: (u8, u8) = (1, 1);
+
+/// The ids of the messages in each data stream (MAV_DATA_STREAM) a 66 message may start or stop
+pub const STREAMS: [(u8, &[u32]); 3] = [
+    (0, &[203, 202, 246, 10003, 42]),       // MAV_DATA_STREAM_ALL
+    (2, &[203, 10003, 42]),                 // MAV_DATA_STREAM_EXTENDED_STATUS
+    (6, &[202, 246]),                       // MAV_DATA_STREAM_POSITION
+];
+
+/// The parameters a ground station may read and set (and their MAV_PARAM_TYPE) in the order they are listed
+///
+/// Integer values are cast to a float.  The callsign is packed three
+/// characters to a value (the first in the top byte, null padded) as a float
+/// holds no more than 24 bits exactly.  The rates are in messages a second
+/// (0 if stopped).
+pub const PARAMETERS: [(&str, u8); 15] = [
+    ("ADSB_ICAO",       MAV_PARAM_TYPE_UINT32), // ICAO address of the UAV (24 bits)
+    ("ADSB_CALLSIGN1",  MAV_PARAM_TYPE_UINT32), // characters 1-3 of the callsign of the UAV
+    ("ADSB_CALLSIGN2",  MAV_PARAM_TYPE_UINT32), // characters 4-6 of the callsign of the UAV
+    ("ADSB_CALLSIGN3",  MAV_PARAM_TYPE_UINT32), // characters 7-8 of the callsign of the UAV
+    ("ADSB_EMIT_TYPE",  MAV_PARAM_TYPE_UINT8),  // emitter type of the UAV
+    ("ADSB_SQUAWK",     MAV_PARAM_TYPE_UINT16), // squawk of the UAV
+    ("ADSB_RANGE",      MAV_PARAM_TYPE_REAL32), // receiver range in m (0 for unlimited)
+    ("ADSB_NOISE",      MAV_PARAM_TYPE_REAL32), // noise in m added to traffic positions
+    ("RATE_0",          MAV_PARAM_TYPE_REAL32),
+    ("RATE_66",         MAV_PARAM_TYPE_REAL32),
+    ("RATE_203",        MAV_PARAM_TYPE_REAL32),
+    ("RATE_202",        MAV_PARAM_TYPE_REAL32),
+    ("RATE_246",        MAV_PARAM_TYPE_REAL32),
+    ("RATE_10003",      MAV_PARAM_TYPE_REAL32),
+    ("RATE_42",         MAV_PARAM_TYPE_REAL32),
+];
+
+/// How far ahead (in seconds) to look along the original line of the UAV for recovery
+const LINE_AHEAD: f32 = 3600.0;
+
+/// The interval between GDL 90 bursts and between snapshots
+const ONE_SECOND: time::Duration = time::Duration::from_secs(1);
+
+// ---------------------------------------------------------------------------
+
+/// The Burst structure holds the messages to be sent after one tick
+pub struct Burst {
+    pub mavlink:    Vec<(u32, Vec<u8>)>,
+    pub gdl90:      Vec<Vec<u8>>,
+}
+
+// ---------------------------------------------------------------------------
+
+/// The Simulator structure holds the state of the simulation
+pub struct Simulator {
+    // Position and velocity of the UAV, its original course, where it would be on that course and its nemesis
+    uav:                CwithV,
+    uav_orig:           CwithV,
+    planned:            CwithV,
+    ufo:                CwithV,
+
+    // Whether the UAV has been diverted by 84 messages (and not yet recovered) and how it is to recover
+    diverted:           bool,
+    recovery:           Recovery,
+
+    // Can't beat a good old fashions Booleaed flag (otherwise I surrender with a white one)
+    ufoinitialised:     bool,
+
+    // Whether the UAV position comes from a SITL autopilot and, if so, whether from 33 (rather than 24) messages
+    sitl:               bool,
+    sitlglobal:         bool,
+
+    // The ICAO address, callsign and emitter type of the UAV itself (only GDL 90 and snapshots need them)
+    ownicao:            u32,
+    owncallsign:        String,
+    ownemitter:         u8,
+
+    // Whether an autopilot has configured the transponder (by way of a 10001 message)
+    configured:         bool,
+
+    // The mission flown (if any), the results of uploads to be acknowledged (to whom) and the waypoints reached
+    mission:            Mission,
+    missionacks:        Vec<((u8, u8), u8)>,
+    reached:            Vec<u16>,
+
+    // The receiver range and the noise added to traffic positions in m (and the state of the noise generator)
+    range:              f32,
+    noise:              f32,
+    random:             u32,
+
+    // The UFO as last reported in a 246 message (noise and all)
+    uforeported:        Option<CwithV>,
+
+    // When the simulator last ticked, the last 84 message arrived and the last GDL 90 burst was sent
+    lasttick:           Option<time::Instant>,
+    commanded:          Option<time::Instant>,
+    gdl90sent:          Option<time::Instant>,
+
+    // The number of 84 messages ignored because they were addressed to another vehicle
+    misaddressed:       u64,
+
+    // The number of ADS-B (202 and 246) messages received and sent (as dump1090 counts messages)
+    messages:           u64,
+
+    // How often each message is sent and the commands to be acknowledged (command and result)
+    rates:              Rates,
+    acks:               Vec<(u16, u8)>,
+
+    // The parameters whose values are to be sent (by index)
+    paramsdue:          Vec<usize>,
+
+    // The MAVLink messages generated every second - avoid calling new each second
+    heartbeat:          mavlink::msg0::Message,
+    datastreamrequest:  mavlink::msg66::Message,
+    status:             mavlink::msg203::Message,
+    ownship:            mavlink::msg202::Message,
+    trafficreport:      mavlink::msg246::Message,
+    transponderhealth:  mavlink::msg10003::Message,
+    commandack:         mavlink::msg77::Message,
+    paramvalue:         mavlink::msg22::Message,
+    missioncurrent:     mavlink::msg42::Message,
+    missionrequest:     mavlink::msg51::Message,
+    missionack:         mavlink::msg47::Message,
+    itemreached:        mavlink::msg46::Message,
+
+    // The components heard from (by way of heartbeat messages)
+    peers:              Peers,
+
+    // The sequence number and ids in the header of the messages generated (each transport has a link of its own too)
+    link:               Link,
+
+    // The key with which to verify the signatures of messages received, if any (the transports sign those sent)
+    signing:            Option<Signing>,
+
+    // The link quality statistics of the messages received
+    linkstats:          LinkStats,
+}
+
+// ---------------------------------------------------------------------------
+
+/// The implementation of methods for the Simulator type
+impl Simulator {
+    // new() creates and initialises a Simulator structure for a UAV and, optionally, a UFO
+    pub fn new(uav: CwithV, ufo: Option<CwithV>, link: Link, linkstats: LinkStats) -> Simulator {
+        let mut trafficreport = mavlink::msg246::Message::new();
+
+        trafficreport.icao_address = UFO_ICAO;
+
+        // MAV_DATA_STREAM_POSITION at 1 Hz (all zeros would tell every listener to stop everything)
+        let mut datastreamrequest = mavlink::msg66::Message::new();
+
+        datastreamrequest.target_system = AUTOPILOT.0;
+        datastreamrequest.target_component = AUTOPILOT.1;
+        datastreamrequest.req_stream_id = 6;
+        datastreamrequest.req_message_rate = 1;
+        datastreamrequest.start_stop = 1;
+
+        Simulator {
+            mission:            Mission::new(uav.get_altitude()),
+            missionacks:        Vec::new(),
+            reached:            Vec::new(),
+            uav_orig:           uav.clone(),
+            planned:            uav.clone(),
+            uav,
+            diverted:           false,
+            recovery:           Recovery::new(),
+            ufoinitialised:     ufo.is_some(),
+            sitl:               false,
+            sitlglobal:         false,
+            ufo:                ufo.unwrap_or_default(),
+            ownicao:            0,
+            owncallsign:        String::new(),
+            ownemitter:         gdl90::EMITTER_UAV,
+            configured:         false,
+            range:              0.0,
+            noise:              0.0,
+            random:             0x2545_f491,
+            uforeported:        None,
+            lasttick:           None,
+            commanded:          None,
+            gdl90sent:          None,
+            misaddressed:       0,
+            messages:           0,
+            rates:              Rates::new(),
+            acks:               Vec::new(),
+            paramsdue:          Vec::new(),
+            heartbeat:          mavlink::msg0::Message::new(),
+            datastreamrequest,
+            status:             mavlink::msg203::Message::new(),
+            ownship:            mavlink::msg202::Message::new(),
+            trafficreport,
+            transponderhealth:  mavlink::msg10003::Message::new(),
+            commandack:         mavlink::msg77::Message::new(),
+            paramvalue:         mavlink::msg22::Message::new(),
+            missioncurrent:     mavlink::msg42::Message::new(),
+            missionrequest:     mavlink::msg51::Message::new(),
+            missionack:         mavlink::msg47::Message::new(),
+            itemreached:        mavlink::msg46::Message::new(),
+            peers:              Peers::new(),
+            link,
+            signing:            None,
+            linkstats,
+        }
+    }
+
+    // set_icao() sets the ICAO addresses of the UFO (for 246 messages) and of the UAV itself
+    pub fn set_icao(&mut self, ufoicao: u32, ownicao: u32) -> &mut Self {
+        self.trafficreport.icao_address = ufoicao;
+        self.ownicao = ownicao;
+
+        self
+    }
+
+    // set_signing() sets the key with which to verify messages received (and insist 84 and 202 messages are signed)
+    pub fn set_signing(&mut self, signing: Signing) -> &mut Self {
+        self.signing = Some(signing);
+
+        self
+    }
+
+    // get_signing() returns the signing context, if messages received must be signed
+    pub fn get_signing(&self) -> Option<&Signing> {
+        self.signing.as_ref()
+    }
+
+    // get_linkstats() returns the link quality statistics of the messages received
+    pub fn get_linkstats(&self) -> &LinkStats {
+        &self.linkstats
+    }
+
+    // set_recovery() sets how the UAV gets back to its route once 84 messages stop
+    pub fn set_recovery(&mut self, recovery: Recovery) -> &mut Self {
+        self.recovery = recovery;
+
+        self
+    }
+
+    // set_sitl() sets whether the position of the UAV comes from a SITL autopilot (rather than its velocity)
+    pub fn set_sitl(&mut self, sitl: bool) -> &mut Self {
+        self.sitl = sitl;
+
+        self
+    }
+
+    // get_uav() returns the position and velocity of the UAV
+    pub fn get_uav(&self) -> &CwithV {
+        &self.uav
+    }
+
+    // get_ufo() returns the position and velocity of the UFO, if there is one yet
+    pub fn get_ufo(&self) -> Option<&CwithV> {
+        if self.ufoinitialised { Some(&self.ufo) } else { None }
+    }
+
+    // get_transponder() returns the ICAO address, callsign and emitter type of the UAV itself
+    pub fn get_transponder(&self) -> (u32, &str, u8) {
+        (self.ownicao, &self.owncallsign, self.ownemitter)
+    }
+
+    // get_mission() returns the mission flown (if any) and any upload in progress
+    pub fn get_mission(&self) -> &Mission {
+        &self.mission
+    }
+
+    // get_misaddressed() returns the number of 84 messages ignored because they were addressed to another vehicle
+    pub fn get_misaddressed(&self) -> u64 {
+        self.misaddressed
+    }
+
+    // get_messages() returns the number of ADS-B (202 and 246) messages received and sent
+    pub fn get_messages(&self) -> u64 {
+        self.messages
+    }
+
+    // get_rates() returns how often each message is sent
+    pub fn get_rates(&self) -> &Rates {
+        &self.rates
+    }
+
+    // get_period() returns how often the simulator must tick
+    pub fn get_period(&self) -> time::Duration {
+        self.rates.period()
+    }
+
+    // get_param() returns the value of the parameter with the given index (integers cast to a float)
+    pub fn get_param(&self, index: usize) -> Option<f32> {
+        let name = PARAMETERS.get(index)?.0;
+
+        let value = match name {
+            "ADSB_ICAO"      => self.ownicao as f32,
+            "ADSB_EMIT_TYPE" => self.ownemitter as f32,
+            "ADSB_SQUAWK"    => self.ownship.squawk as f32,
+            "ADSB_RANGE"     => self.range,
+            "ADSB_NOISE"     => self.noise,
+            "ADSB_CALLSIGN1" | "ADSB_CALLSIGN2" | "ADSB_CALLSIGN3" => {
+                let first = 3 * Self::callsign_part(name)?;
+                let chars = self.owncallsign.bytes().chain(iter::repeat(0)).skip(first).take(3);
+
+                chars.fold(0, |value, cc| value << 8 | cc as u32) as f32
+            },
+            name             => self.rates.get_interval(Self::rate_msgid(name)?).map_or(0.0, |interval| 1.0 / interval.as_secs_f32()),
+        };
+
+        Some(value)
+    }
+
+    // set_param() sets the parameter with the given index and returns whether the value is acceptable
+    pub fn set_param(&mut self, index: usize, value: f32) -> bool {
+        let name = match PARAMETERS.get(index) {
+            Some(&(name, _)) => name,
+            None             => return false,
+        };
+
+        // integer values must be whole numbers in range
+        let integer = |maximum: u32| if value >= 0.0 && value <= maximum as f32 && value.fract() == 0.0 { Some(value as u32) } else { None };
+
+        match name {
+            "ADSB_ICAO" => match integer(0x00ff_ffff) {
+                Some(icao) => {
+                    if icao != self.ownicao {
+                        println!("ICAO: {:08x} ({})", icao, self.owncallsign);
+                    }
+                    self.ownicao = icao;
+                },
+                None => return false,
+            },
+            "ADSB_EMIT_TYPE" => match integer(0xff) {
+                Some(emitter) => self.ownemitter = emitter as u8,
+                None          => return false,
+            },
+            "ADSB_SQUAWK" => match integer(0xffff) {
+                Some(squawk) => self.ownship.squawk = squawk as u16,
+                None         => return false,
+            },
+            "ADSB_CALLSIGN1" | "ADSB_CALLSIGN2" | "ADSB_CALLSIGN3" => match integer(0x00ff_ffff).and_then(|chars| self.callsign_with(name, chars)) {
+                Some(callsign) => {
+                    if callsign != self.owncallsign {
+                        println!("ICAO: {:08x} ({})", self.ownicao, callsign);
+                    }
+                    self.owncallsign = callsign;
+                },
+                None => return false,
+            },
+            "ADSB_RANGE" | "ADSB_NOISE" => {
+                if ! (value >= 0.0 && value.is_finite()) {
+                    return false;
+                }
+
+                if name == "ADSB_RANGE" { self.range = value } else { self.noise = value }
+            },
+            name => {
+                if ! (value >= 0.0 && value.is_finite()) {
+                    return false;
+                }
+
+                let msgid = match Self::rate_msgid(name) {
+                    Some(msgid) => msgid,
+                    None        => return false,
+                };
+
+                let interval = if value == 0.0 { None } else { Some(time::Duration::from_micros((1.0e6 / value as f64).round() as u64)) };
+
+                let before = self.rates.get_interval(msgid);
+
+                self.rates.set_interval(msgid, interval);
+                self.retimed(msgid, before);
+            },
+        }
+
+        true
+    }
+
+    // callsign_part() returns which three characters of the callsign a parameter is, if it is one
+    fn callsign_part(name: &str) -> Option<usize> {
+        ["ADSB_CALLSIGN1", "ADSB_CALLSIGN2", "ADSB_CALLSIGN3"].iter().position(|&part| part == name)
+    }
+
+    // callsign_with() returns the callsign with the characters of a callsign parameter replaced, if the result is valid
+    fn callsign_with(&self, name: &str, chars: u32) -> Option<String> {
+        let first = 3 * Self::callsign_part(name)?;
+        let mut callsign: Vec<u8> = self.owncallsign.bytes().chain(iter::repeat(0)).take(9).collect();
+
+        callsign[first]     = (chars >> 16) as u8;
+        callsign[first + 1] = (chars >> 8) as u8;
+        callsign[first + 2] = chars as u8;
+
+        let callsign: Vec<u8> = callsign.into_iter().take_while(|&cc| cc != 0).collect();
+
+        if callsign.len() > 8 || callsign.iter().any(|cc| ! (b' ' ..= b'~').contains(cc)) {
+            return None;
+        }
+
+        Some(String::from_utf8_lossy(&callsign).trim_end().to_string())
+    }
+
+    // rate_msgid() returns the id of the message whose rate a parameter is, if it is one
+    fn rate_msgid(name: &str) -> Option<u32> {
+        name.strip_prefix("RATE_")?.parse().ok()
+    }
+
+    // get_link() returns the link context of the messages generated
+    pub fn get_link(&self) -> &Link {
+        &self.link
+    }
+
+    // tick() handles the messages received since the last tick and returns the next burst of messages
+    pub fn tick<I>(&mut self, now: time::Instant, received: I) -> Burst
+                where I: IntoIterator<Item = Vec<u8>> {
+        // the time since the last tick (a second for the first)
+        let elapsed = self.lasttick.map_or(1.0, |lasttick| now.duration_since(lasttick).as_secs_f32());
+
+        self.lasttick = Some(now);
+
+        // once the last 84 is old enough get back to the route and then follow it (unless another 84 says otherwise)
+        if ! self.sitl && self.commanded.is_none_or(|commanded| now.duration_since(commanded) >= self.recovery.get_hold()) {
+            if self.diverted {
+                self.diverted = ! self.recover(elapsed);
+            }
+
+            if ! self.diverted {
+                self.follow(elapsed);
+            }
+        }
+
+        // update the ufo's position (possibly to be overridden by arrival of 202 message) and where the uav would be
+        self.ufo.advance_position(elapsed);
+        self.planned.advance_position(elapsed);
+
+        // process any message that have arrived since last time
+        for mavmsg in received {
+            self.receive(&mavmsg, now);
+        }
+
+        // report any component that has stopped sending heartbeats
+        for (sysid, compid, mavtype) in self.peers.check(now) {
+            println!("no heartbeat from {}/{} (type {}) for {} seconds", sysid, compid, mavtype, self.peers.get_timeout().as_secs());
+        }
+
+        // report link quality statistics now and again
+        self.linkstats.report(now);
+
+        // update the uav's position (by the time since the last tick) unless the autopilot gives it
+        if ! self.sitl {
+            self.uav.advance_position(elapsed);
+        }
+
+        // generate a burst of the messages due as would the real ADS-B device
+        let mut burst = Burst {
+            mavlink:    Vec::with_capacity(BURST.len() + CONFIGURED.len() + MISSION.len()),
+            gdl90:      Vec::new(),
+        };
+
+        let configured: &[u32] = if self.configured { &CONFIGURED } else { &[] };
+        let mission: &[u32] = if self.mission.get_count() != 0 { &MISSION } else { &[] };
+
+        for msgid in BURST.iter().chain(configured.iter()).chain(mission.iter()) {
+            if ! self.rates.due(*msgid, now) {
+                continue;
+            }
+
+            let message = match *msgid {
+                0 =>  {
+                    self.heartbeat.serialise(&mut self.link)
+                },
+                66 =>  {
+                    self.datastreamrequest.serialise(&mut self.link)
+                },
+                203 =>  {
+                    self.status.serialise(&mut self.link)
+                },
+                202 =>  {
+                    self.ownship.set_cwithv(&self.uav).serialise(&mut self.link)
+                },
+                246 =>  {
+                    if ! self.ufoinitialised || ! self.in_range() {continue;}
+
+                    let reported = self.reported();
+
+                    self.trafficreport.set_cwithv(&reported);
+                    self.uforeported = Some(reported);
+
+                    self.trafficreport.serialise(&mut self.link)
+                },
+                10003 =>  {
+                    self.transponderhealth.serialise(&mut self.link)
+                },
+                42 =>  {
+                    self.missioncurrent.seq = self.mission.get_current();
+
+                    self.missioncurrent.serialise(&mut self.link)
+                },
+                _  =>  {
+                    panic!("WTF: msgid = {}", msgid);
+                },
+            }.to_vec();
+
+            self.queue(&mut burst, *msgid, &message);
+        }
+
+        // acknowledge the commands received (whatever the rates)
+        let acks: Vec<(u16, u8)> = self.acks.drain(..).collect();
+
+        for (command, result) in acks {
+            let message = self.commandack.set_result(command, result).serialise(&mut self.link).to_vec();
+
+            self.queue(&mut burst, 77, &message);
+        }
+
+        // send the values of the parameters asked for or set (whatever the rates)
+        let paramsdue: Vec<usize> = self.paramsdue.drain(..).collect();
+
+        for index in paramsdue {
+            let (name, type_) = PARAMETERS[index];
+            let value = self.get_param(index).unwrap_or(0.0);
+
+            let message = self.paramvalue.set_value(name, value, type_, index, PARAMETERS.len()).serialise(&mut self.link).to_vec();
+
+            self.queue(&mut burst, 22, &message);
+        }
+
+        // acknowledge the mission uploads over, ask for the next item of the one in progress and report waypoints reached
+        let missionacks: Vec<((u8, u8), u8)> = self.missionacks.drain(..).collect();
+
+        for (target, result) in missionacks {
+            let message = self.missionack.set_result(target, result).serialise(&mut self.link).to_vec();
+
+            self.queue(&mut burst, 47, &message);
+        }
+
+        if self.mission.abandon_quiet(now) {
+            println!("mission upload abandoned");
+        }
+
+        if let Some((target, seq)) = self.mission.get_request() {
+            let message = self.missionrequest.set_request(target, seq).serialise(&mut self.link).to_vec();
+
+            self.queue(&mut burst, 51, &message);
+        }
+
+        let reached: Vec<u16> = self.reached.drain(..).collect();
+
+        for seq in reached {
+            self.itemreached.seq = seq;
+
+            let message = self.itemreached.serialise(&mut self.link).to_vec();
+
+            self.queue(&mut burst, 46, &message);
+        }
+
+        // the same burst in GDL 90 form for EFBs and the like (once a second)
+        if let Some(due) = rates::schedule(self.gdl90sent, ONE_SECOND, self.rates.period() / 2, now) {
+            self.gdl90sent = Some(due);
+
+            burst.gdl90.push(gdl90::heartbeat(self.ownship.fixtype > 1));
+            burst.gdl90.push(gdl90::ownship_report(&self.ownship, self.ownicao, &self.owncallsign, self.ownemitter));
+            burst.gdl90.push(gdl90::ownship_geometric_altitude(&self.ownship));
+
+            if self.ufoinitialised && self.in_range() {
+                burst.gdl90.push(gdl90::traffic_report(&self.trafficreport));
+            }
+        }
+
+        burst
+    }
+
+    // follow() sets the velocity of the UAV, about to move for the given seconds, to follow its route:  the mission, if any, or else its original course
+    fn follow(&mut self, seconds: f32) {
+        if self.mission.get_count() != 0 {
+            let cruise = self.uav_orig.get_groundspeed();
+
+            let reached = self.mission.steer(&mut self.uav, cruise, seconds);
+
+            for seq in reached.iter() {
+                println!("mission item {} reached", seq);
+            }
+
+            self.reached.extend(reached);
+        } else {
+            self.uav.set_course(&self.uav_orig);
+        }
+    }
+
+    // recover() sets the velocity of the UAV, about to move for the given seconds, to get back to its route and returns whether it is back
+    fn recover(&mut self, seconds: f32) -> bool {
+        let speed = self.mission.get_speed(self.uav_orig.get_groundspeed());
+
+        // the leg of the mission or else the original line from where the uav would be now
+        let (from, to, next) = match self.mission.get_leg() {
+            Some((from, to)) => {
+                let next = to.clone();
+                (from, to, next)
+            },
+            None => {
+                let mut ahead = self.planned.clone();
+
+                ahead.advance_position(LINE_AHEAD);
+
+                (self.planned.clone(), ahead, self.planned.clone())
+            },
+        };
+
+        let recovered = self.recovery.steer(&mut self.uav, &from, &to, &next, speed, seconds);
+
+        if recovered {
+            println!("back on route");
+        }
+
+        recovered
+    }
+
+    // in_range() returns whether the UFO is within range of the receiver
+    fn in_range(&self) -> bool {
+        self.range == 0.0 || self.uav.distance(&self.ufo) <= self.range
+    }
+
+    // reported() returns the position and velocity of the UFO as reported (with noise added to the position)
+    fn reported(&mut self) -> CwithV {
+        let mut reported = self.ufo.clone();
+
+        if self.noise > 0.0 {
+            let (north, east, up) = (self.jitter(), self.jitter(), self.jitter());
+
+            reported.displace(north, east, up);
+        }
+
+        reported
+    }
+
+    // jitter() returns a pseudo-random distance between plus and minus the noise level (xorshift32)
+    fn jitter(&mut self) -> f32 {
+        self.random ^= self.random << 13;
+        self.random ^= self.random >> 17;
+        self.random ^= self.random << 5;
+
+        self.noise * (2.0 * (self.random as f32 / u32::MAX as f32) - 1.0)
+    }
+
+    // queue() adds a message to a burst (the transports frame it afresh, and sign it, for their own links)
+    fn queue(&mut self, burst: &mut Burst, msgid: u32, message: &[u8]) {
+        if Self::adsb(msgid) {
+            self.messages += 1;
+        }
+
+        burst.mavlink.push((msgid, message.to_vec()));
+    }
+
+    // adsb() returns whether a message is an ADS-B message (ownship or traffic report)
+    fn adsb(msgid: u32) -> bool {
+        msgid == 202 || msgid == 246
+    }
+
+    // aircraft() returns all craft for a snapshot:  the UAV if it has an ICAO address and the UFO as last reported, if it is being reported
+    pub fn aircraft(&self) -> Vec<snapshot::Craft<'_>> {
+        let mut aircraft = Vec::new();
+
+        if self.ownicao != 0 {
+            aircraft.push(snapshot::Craft {icao: self.ownicao, callsign: self.owncallsign.clone(), cwithv: &self.uav});
+        }
+
+        let reporting = self.ufoinitialised && self.in_range() && self.rates.get_interval(246).is_some();
+
+        if let (true, Some(reported)) = (reporting, self.uforeported.as_ref()) {
+            let callsign = self.trafficreport.callsign.iter().cloned().take_while(|&c| c != 0).collect();
+
+            aircraft.push(snapshot::Craft {
+                icao:       self.trafficreport.icao_address,
+                callsign:   String::from_utf8(callsign).unwrap_or_default(),
+                cwithv:     reported,
+            });
+        }
+
+        aircraft
+    }
+
+    // receive() handles one message received
+    fn receive(&mut self, mavmsg: &[u8], now: time::Instant) {
+        // the statistics see every frame as it arrived (MAVLink 1 or 2, signed or not)
+        if ! self.linkstats.received(mavmsg) {
+            println!("bad message ({}) discarded", mavlink::seqno(mavmsg).unwrap_or(0));
+            return;
+        }
+
+        // MAVLink 2 messages are converted to MAVLink 1 (once their signatures have been checked)
+        let converted =
+            match self.signing {
+                Some(ref mut signing) => {
+                    let verified = signing.verify(mavmsg, now);
+
+                    if verified.is_err() {
+                        self.linkstats.rejected(mavmsg);
+                    }
+
+                    verified
+                },
+                None => mavlink::to_v1(mavmsg),
+            };
+
+        let mavmsg = match converted {
+            Ok(ref frame) => &frame[..],
+            Err(e) => {
+                println!("message rejected: {}", e);
+                return;
+            }
+        };
+
+        let parsed = MavMessage::parse(mavmsg);
+
+        if let Ok(ref message) = parsed {
+            if Self::adsb(message.msgid()) {
+                self.messages += 1;
+            }
+        }
+
+        match parsed {
+            Ok(MavMessage::Heartbeat(heartbeat)) => {
+                match self.peers.heard(mavmsg[3], mavmsg[4], heartbeat.type_, now) {
+                    Some(Heard::First)   => println!("heartbeat from {}/{} (type {})", mavmsg[3], mavmsg[4], heartbeat.type_),
+                    Some(Heard::Resumed) => println!("heartbeat from {}/{} (type {}) resumed", mavmsg[3], mavmsg[4], heartbeat.type_),
+                    None                 => (),
+                }
+            }
+            Ok(MavMessage::GlobalPosition(ref position)) if self.sitl => {
+                if ! self.sitlglobal {
+                    println!("received first 33 message");
+                }
+                self.sitlglobal = true;
+
+                position.get_cwithv(&mut self.uav);
+            }
+            Ok(MavMessage::GpsRaw(ref gps)) if self.sitl => {
+                self.ownship.fixtype = gps.fix_type;
+                self.ownship.numsats = gps.satellites_visible;
+
+                // the position estimate is better than the raw GPS position
+                if gps.has_fix() && ! self.sitlglobal {
+                    gps.get_cwithv(&mut self.uav);
+                }
+            }
+            Ok(MavMessage::SetTargetPosition(ref settargetposition)) if ! self.addressed(settargetposition.target_system, settargetposition.target_component) => {
+                self.misaddressed += 1;
+
+                println!("84 message from {}/{} for {}/{} ignored ({} so far)",
+                    mavmsg[3], mavmsg[4], settargetposition.target_system, settargetposition.target_component, self.misaddressed);
+            }
+            Ok(MavMessage::SetTargetPosition(settargetposition)) => {
+                println!("received 84 message");
+
+                self.commanded = Some(now);
+                self.diverted = true;
+                self.recovery.diverted();
+
+                self.uav.set_velocity(settargetposition.vx, settargetposition.vy, settargetposition.vz);
+            }
+            Ok(MavMessage::DataStreamRequest(ref request)) => {
+                // 66 messages must name the system:  others' bursts on a shared topic have 66 messages too
+                if request.target_system != 0 && self.addressed(request.target_system, request.target_component) {
+                    self.request_data_stream(request.req_stream_id, request.req_message_rate, request.start_stop);
+                }
+            }
+            Ok(MavMessage::CommandLong(ref command)) => {
+                if self.addressed(command.target_system, command.target_component) {
+                    let result = match command.command {
+                        MAV_CMD_SET_MESSAGE_INTERVAL => self.set_message_interval(command.param1, command.param2),
+                        _ => MAV_RESULT_UNSUPPORTED,
+                    };
+
+                    self.acks.push((command.command, result));
+                }
+            }
+            Ok(MavMessage::ParamRequestList(ref request)) => {
+                if self.addressed(request.target_system, request.target_component) {
+                    self.paramsdue.extend(0 .. PARAMETERS.len());
+                }
+            }
+            Ok(MavMessage::ParamRequestRead(ref request)) => {
+                if self.addressed(request.target_system, request.target_component) {
+                    let index =
+                        if request.param_index < 0 {
+                            Self::param_index(&request.get_param_id())
+                        } else {
+                            Some(request.param_index as usize).filter(|&index| index < PARAMETERS.len())
+                        };
+
+                    match index {
+                        Some(index) => self.paramsdue.push(index),
+                        None        => println!("unknown parameter {} ({})", request.get_param_id(), request.param_index),
+                    }
+                }
+            }
+            Ok(MavMessage::ParamSet(ref set)) => {
+                if self.addressed(set.target_system, set.target_component) {
+                    match Self::param_index(&set.get_param_id()) {
+                        Some(index) => {
+                            if ! self.set_param(index, set.param_value) {
+                                println!("parameter {} not set to {}", set.get_param_id(), set.param_value);
+                            }
+
+                            // the current value whether set or not
+                            self.paramsdue.push(index);
+                        }
+                        None => println!("unknown parameter {}", set.get_param_id()),
+                    }
+                }
+            }
+            Ok(MavMessage::MissionCount(ref count)) => {
+                if self.addressed(count.target_system, count.target_component) {
+                    let source = (mavmsg[3], mavmsg[4]);
+
+                    println!("mission upload of {} items", count.count);
+
+                    // an empty mission is uploaded at once
+                    if let Some(result) = self.mission.start_upload(source, count.count, now) {
+                        println!("mission of {} items accepted", self.mission.get_count());
+                        self.missionacks.push((source, result));
+                    }
+                }
+            }
+            Ok(MavMessage::MissionItem(ref item)) => {
+                if self.addressed(item.target_system, item.target_component) {
+                    let source = (mavmsg[3], mavmsg[4]);
+
+                    match self.mission.add_item(item, now) {
+                        Ok(Some(result)) => {
+                            if result == MAV_MISSION_ACCEPTED {
+                                println!("mission of {} items accepted", self.mission.get_count());
+                            } else {
+                                println!("mission item {} rejected ({})", item.seq, result);
+                            }
+                            self.missionacks.push((source, result));
+                        },
+                        Ok(None) => (),
+                        Err(e)   => println!("{}", e),
+                    }
+                }
+            }
+            Ok(MavMessage::TransponderConfig(ref config)) => {
+                if ! self.configured {
+                    println!("received first 10001 message");
+                }
+                self.configured = true;
+
+                if config.get_icao() != self.ownicao || config.get_callsign() != self.owncallsign {
+                    println!("ICAO: {:08x} ({})", config.get_icao(), config.get_callsign());
+                }
+
+                self.ownicao = config.get_icao();
+                self.owncallsign = config.get_callsign();
+                self.ownemitter = config.emitter_type;
+            }
+            Ok(MavMessage::TransponderDynamic(ref dynamic)) => {
+                self.ownship.squawk = dynamic.squawk;
+            }
+            Ok(MavMessage::Ownship(mut ownship)) => {
+                ownship.get_cwithv(&mut self.ufo);
+                if ! self.ufoinitialised {
+                    println!("received first 202 message");
+                }
+                self.ufoinitialised = true;
+            }
+            Ok(message) => {
+                println!("unexpected message {} ({})", message.msgid(), mavlink::seqno(mavmsg).unwrap_or(0));
+            }
+            Err(e) => {
+                println!("unusable message: {}", e);
+            }
+        }
+    }
+
+    // addressed() returns whether a message with the given target is for the simulator (0 means any)
+    fn addressed(&self, sysid: u8, compid: u8) -> bool {
+        (sysid == 0 || sysid == self.link.get_sysid()) && (compid == 0 || compid == self.link.get_compid())
+    }
+
+    // param_index() returns the index of the parameter with the given id, if there is one
+    fn param_index(param_id: &str) -> Option<usize> {
+        PARAMETERS.iter().position(|&(name, _)| name == param_id)
+    }
+
+    // request_data_stream() starts (at the given rate) or stops the messages of a data stream
+    fn request_data_stream(&mut self, stream: u8, rate: u16, start: u8) {
+        let msgids = match STREAMS.iter().find(|&&(id, _)| id == stream) {
+            Some(&(_, msgids)) => msgids,
+            None => {
+                println!("unsupported data stream {}", stream);
+                return;
+            }
+        };
+
+        for &msgid in msgids {
+            let before = self.rates.get_interval(msgid);
+
+            self.rates.set_rate(msgid, if start != 0 { rate } else { 0 });
+            self.retimed(msgid, before);
+        }
+    }
+
+    // set_message_interval() sets the interval between messages in us (-1 to stop them, 0 for the default)
+    fn set_message_interval(&mut self, msgid: f32, interval: f32) -> u8 {
+        // the parameters are floats:  NaN, negative, fractional (message id) and out of range values are refused
+        if ! msgid.is_finite() || msgid < 0.0 || msgid.fract() != 0.0 || msgid > u32::MAX as f32 {
+            return MAV_RESULT_DENIED;
+        }
+
+        if ! interval.is_finite() || (interval < 0.0 && interval != -1.0) {
+            return MAV_RESULT_DENIED;
+        }
+
+        let msgid = msgid as u32;
+
+        if ! BURST.contains(&msgid) && ! CONFIGURED.contains(&msgid) && ! MISSION.contains(&msgid) {
+            return MAV_RESULT_DENIED;
+        }
+
+        let before = self.rates.get_interval(msgid);
+
+        if interval < 0.0 {
+            self.rates.set_interval(msgid, None);
+        } else if interval == 0.0 {
+            self.rates.set_default(msgid);
+        } else {
+            self.rates.set_interval(msgid, Some(time::Duration::from_micros(interval.round() as u64)));
+        }
+
+        self.retimed(msgid, before);
+
+        MAV_RESULT_ACCEPTED
+    }
+
+    // retimed() reports a change to the interval between messages with the given id (given the interval before)
+    fn retimed(&self, msgid: u32, before: Option<time::Duration>) {
+        let after = self.rates.get_interval(msgid);
+
+        if after != before {
+            match after {
+                Some(interval) => println!("message {} every {} ms", msgid, interval.as_millis()),
+                None           => println!("message {} stopped", msgid),
+            }
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+
+/// The producer() thread publishes all messages and handles messages received
+pub fn producer(channel: &mpsc::Receiver<Vec<u8>>, options: Options) {
+    let Options {mqtt, inet, serial, tlog, stdout, uav, ufo, sitl, recovery, gdl90, snapshot, link, signing, linkstats, ..} = options;
+
+    let mut simulator = Simulator::new(uav, ufo, link, linkstats);
+
+    simulator.set_sitl(sitl.is_some());
+    simulator.set_recovery(recovery);
+
+    // every message goes over every transport configured, each a link of its own, with the simulator's ids
+    let mut transports = FanOut::new();
+
+    transports.set_source(simulator.get_link().get_sysid(), simulator.get_link().get_compid());
+
+    // the transports sign the messages sent and the simulator verifies those received
+    if let Some(signing) = signing {
+        transports.set_signing(signing.clone());
+        simulator.set_signing(signing);
+    }
+
+    // deduce the ICAO addresses to be used when generating 246 messages and of the UAV itself
+    let ufoicao =
+        match inet {
+            Some(_) => UFO_ICAO,
+            None    => mqtt.get_ufo_icao().unwrap_or(UFO_ICAO),
+        };
+
+    let ownicao = mqtt.get_icao().unwrap_or(0);
+
+    simulator.set_icao(ufoicao, ownicao);
+
+    // MQTT is a transport if it has somewhere to publish (or there is nothing else)
+    if let Some(inet) = inet {
+        transports.add(Box::new(inet));
+    }
+
+    if let Some(serial) = serial {
+        transports.add(Box::new(serial));
+    }
+
+    if let Some(tlog) = tlog {
+        transports.add(Box::new(tlog));
+    }
+
+    if let Some(stdout) = stdout {
+        transports.add(Box::new(stdout));
+    }
+
+    if mqtt.is_publisher() || transports.is_empty() {
+        transports.add(Box::new(mqtt));
+    }
+
+    // just to be clear where generated messages are going
+    println!("Producer {}", transports.name());
+
+    println!("ICAO: {:08x}", ufoicao);
+
+    println!("MAVLink: {}/{}", simulator.get_link().get_sysid(), simulator.get_link().get_compid());
+
+    if let Some(signing) = simulator.get_signing() {
+        println!("Signing: link {}", signing.get_linkid());
+    }
+
+    let mut snapshotwritten: Option<time::Instant> = None;
+
+    loop {
+        // record when this loop (iteration) starts
+        let start = time::Instant::now();
+
+        let burst = simulator.tick(start, channel.try_iter());
+
+        // the fan out reports failures itself
+        for &(msgid, ref message) in burst.mavlink.iter() {
+            let _ = transports.send(msgid, message);
+        }
+
+        // repeat the burst in GDL 90 form for EFBs and the like
+        if let Some(ref output) = gdl90 {
+            for message in burst.gdl90.iter() {
+                match output.send(message) {
+                    Ok(_) => (),
+                    Err(e) => println!("GDL 90 error: {}", e),
+                };
+            }
+        }
+
+        // write a snapshot of all craft for dashboards and the like (once a second)
+        if let Some(ref output) = snapshot {
+            if let Some(due) = rates::schedule(snapshotwritten, ONE_SECOND, simulator.get_period() / 2, start) {
+                snapshotwritten = Some(due);
+
+                match output.write(&simulator.aircraft(), simulator.get_messages()) {
+                    Ok(_) => (),
+                    Err(e) => println!("Snapshot error: {}", e),
+                };
+            }
+        }
+
+        // sleep until the next tick (a second unless some message is wanted more often) and then start again
+        if let Some(rest) = simulator.get_period().checked_sub(start.elapsed()) {
+            thread::sleep(rest);
+        }
+    }
+}
+
+// EOF
@@ -0,0 +1,149 @@
+//! ADS-B Simulator - see README.md
+//
+// © NewForester, 2018.  Available under MIT licence terms.
+//
+//! The sitl module provides the ADS-B Simulator with the means to take the
+//! position of the UAV from a software-in-the-loop (SITL) autopilot instead
+//! of flying the straight line of the `-uav` parameter.
+//!
+//! The autopilot is asked to send its MAVLink messages to a UDP port the
+//! simulator listens on.  Of these, only the GLOBAL_POSITION_INT (33),
+//! GPS_RAW_INT (24) and HEARTBEAT (0) messages and the transponder's own
+//! UAVIONIX_ADSB_OUT_CFG (10001) and UAVIONIX_ADSB_OUT_DYNAMIC (10002)
+//! messages are of interest:  they are passed on to the `producer()` thread
+//! in the same way as messages received by MQTT and the rest are dropped.
+//! A datagram may hold several messages.
+//!
+//! The `set_cli()` function provides the implementation of the parsing of
+//! certain command line parameters as described in README.md.
+//!
+use std::io::{Error, ErrorKind};
+use std::net::UdpSocket;
+use std::sync::mpsc;
+use std::thread;
+
+use mavlink;
+
+// ---------------------------------------------------------------------------
+
+/// The ids of the messages passed on
+const WANTED: [u32; 5] = [0, 24, 33, 10001, 10002];
+
+// ---------------------------------------------------------------------------
+
+/// The Sitl structure holds the UDP socket the autopilot's messages are received on
+pub struct Sitl {
+    address:    String,
+
+    socket:     Option<UdpSocket>,
+}
+
+// ---------------------------------------------------------------------------
+
+/// The implementation of methods for the Sitl type
+impl Sitl {
+    // new() creates and initialises a Sitl structure
+    pub fn new() -> Sitl {
+        Sitl {
+            address:    "127.0.0.1:14551".to_string(),
+            socket:     None,
+        }
+    }
+
+    // set_cli() extract the port (and host) to listen on from a CLI parameter string
+    pub fn set_cli(&mut self, cli: &str) -> Result<&mut Self, String> {
+        let pair: Vec<&str> = cli.split(':').collect();
+
+        if pair.len() > 2 {
+            return Err(format!("'{}' is not of the form 'port:host'", cli));
+        }
+
+        let port: u16 = pair[0].parse().map_err(|_| format!("'{}' is not a port number", pair[0]))?;
+
+        let host = if pair.len() == 1 || pair[1].is_empty() { "127.0.0.1" } else { pair[1] };
+
+        self.address = format!("{}:{}", host, port);
+
+        Ok(self)
+    }
+
+    // get_address() returns the socket address messages are received on
+    pub fn get_address(&self) -> &str {
+        &self.address
+    }
+
+    // get_local_address() returns the socket address messages are received on once bound (port 0 is any port)
+    pub fn get_local_address(&self) -> Option<String> {
+        self.socket.as_ref().and_then(|socket| socket.local_addr().ok()).map(|address| address.to_string())
+    }
+
+    // open() binds the UDP socket messages are received on
+    pub fn open(&mut self) -> Result<&mut Self, Error> {
+        self.socket = Some(UdpSocket::bind(&self.address)?);
+
+        Ok(self)
+    }
+
+    // listen() starts a thread that passes the messages of interest on down the channel
+    pub fn listen(&self, channel: mpsc::Sender<Vec<u8>>) -> Result<&Self, Error> {
+        let socket = match self.socket {
+            Some(ref socket) => socket.try_clone()?,
+            None             => return Err(Error::new(ErrorKind::NotConnected, "SITL socket not open")),
+        };
+
+        thread::spawn(move || {
+            let mut buffy = [0u8; 65536];
+
+            loop {
+                let length = match socket.recv_from(&mut buffy) {
+                    Ok((length, _)) => length,
+                    Err(e) => {
+                        println!("SITL error: {}", e);
+                        return;
+                    }
+                };
+
+                for message in messages(&buffy[.. length]) {
+                    if channel.send(message.to_vec()).is_err() {
+                        return;
+                    }
+                }
+            }
+        });
+
+        Ok(self)
+    }
+}
+
+/// The implementation of the Default trait for the Sitl type
+impl Default for Sitl {
+    fn default() -> Sitl {
+        Sitl::new()
+    }
+}
+
+// ---------------------------------------------------------------------------
+
+// messages() returns the messages of interest in a datagram (skipping anything that is not a message)
+pub fn messages(datagram: &[u8]) -> Vec<&[u8]> {
+    let mut messages = Vec::new();
+    let mut rest = datagram;
+
+    while ! rest.is_empty() {
+        match mavlink::framelen(rest) {
+            Some(length) => {
+                if mavlink::msgid(rest).is_some_and(|msgid| WANTED.contains(&msgid)) {
+                    messages.push(&rest[.. length]);
+                }
+                rest = &rest[length ..];
+            }
+            None => {
+                rest = &rest[1 ..];
+            }
+        }
+    }
+
+    messages
+}
+
+// EOF
@@ -0,0 +1,139 @@
+//! ADS-B Simulator - see README.md
+//
+// © NewForester, 2018.  Available under MIT licence terms.
+//
+//! The snapshot module provides the ADS-B Simulator with a dump1090 style
+//! `aircraft.json` file for the benefit of quick dashboards.
+//!
+//! The file lists the craft a receiver would see with its ICAO address (hex),
+//! callsign (flight), position, altitude, track, ground speed and vertical
+//! rate in the units dump1090/readsb use (degrees, ft, knots and ft/min):
+//! the UAV if it has an ICAO address and the UFO as reported in the last
+//! traffic report (noise and all) if it is being reported (in range).
+//! Its messages count is, as for dump1090, the number of ADS-B messages
+//! (202 and 246) received and sent so far.
+//!
+//! The `set_cli()` function provides the implementation of the parsing of
+//! certain command line parameters as described in README.md.
+//!
+//! The file is written anew each second:  it is written to a temporary file
+//! first and then renamed so readers never see a partial snapshot.
+//!
+extern crate chrono;
+
+use self::chrono::Utc;
+
+use std::fs;
+use std::io::{Error, Write};
+use std::path::PathBuf;
+
+use coords::CwithV;
+
+// ---------------------------------------------------------------------------
+
+/// The Craft structure holds what a snapshot needs to know about one craft
+pub struct Craft<'a> {
+    pub icao:       u32,
+    pub callsign:   String,
+    pub cwithv:     &'a CwithV,
+}
+
+// ---------------------------------------------------------------------------
+
+/// The Snapshot structure holds where snapshots are written
+pub struct Snapshot {
+    directory:  String,
+}
+
+// ---------------------------------------------------------------------------
+
+/// The implementation of methods for the Snapshot type
+impl Snapshot {
+    // new() creates and initialises a Snapshot structure
+    pub fn new() -> Snapshot {
+        Snapshot {
+            directory:  ".".to_string(),
+        }
+    }
+
+    // set_cli() extract snapshot parameters from a CLI parameter string
+    pub fn set_cli(&mut self, cli: &str) -> &mut Self {
+        if ! cli.is_empty() {
+            self.directory = cli.to_string();
+        }
+
+        self
+    }
+
+    // get_path() returns the path of the aircraft.json file
+    pub fn get_path(&self) -> PathBuf {
+        PathBuf::from(&self.directory).join("aircraft.json")
+    }
+
+    // write() (re)writes the aircraft.json file for the given craft and number of messages received and sent
+    pub fn write(&self, aircraft: &[Craft], messages: u64) -> Result<(), Error> {
+        let path = self.get_path();
+        let temporary = path.with_extension("json.tmp");
+
+        {
+            let mut file = fs::File::create(&temporary)?;
+
+            file.write_all(Self::render(aircraft, messages).as_bytes())?;
+        }
+
+        fs::rename(&temporary, &path)
+    }
+
+    // render() returns the JSON text of a snapshot of the given craft and number of messages received and sent
+    pub fn render(aircraft: &[Craft], messages: u64) -> String {
+        let now = Utc::now();
+
+        let mut json = String::new();
+
+        json.push_str(&format!("{{ \"now\" : {}.{:01},\n", now.timestamp(), now.timestamp_subsec_millis() / 100));
+        json.push_str(&format!("  \"messages\" : {},\n", messages));
+        json.push_str("  \"aircraft\" : [");
+
+        for (ii, craft) in aircraft.iter().enumerate() {
+            if ii != 0 {
+                json.push(',');
+            }
+            json.push_str("\n    ");
+            json.push_str(&Self::render_craft(craft));
+        }
+
+        json.push_str("\n  ]\n}\n");
+
+        json
+    }
+
+    // render_craft() returns the JSON object of one craft
+    fn render_craft(craft: &Craft) -> String {
+        let cwithv = craft.cwithv;
+
+        let callsign: String = craft.callsign.chars()
+            .filter(|c| c.is_ascii_graphic() && *c != '"' && *c != '\\')
+            .collect();
+
+        format!("{{\"hex\":\"{:06x}\",\"flight\":\"{:8}\",\"lat\":{:.6},\"lon\":{:.6},\"altitude\":{},\"alt_geom\":{},\"track\":{:.1},\"gs\":{:.1},\"vert_rate\":{},\"seen_pos\":0.0,\"seen\":0.0}}",
+            craft.icao & 0xffffff,
+            callsign,
+            cwithv.get_latitude(),
+            cwithv.get_longitude(),
+            (cwithv.get_altitude() / 0.3048).round() as i32,
+            (cwithv.get_altitude() / 0.3048).round() as i32,
+            cwithv.get_heading(),
+            cwithv.get_groundspeed() * 1.943844,
+            (cwithv.get_rateofclimb() / 0.3048 * 60.0).round() as i32,
+        )
+    }
+}
+
+/// The implementation of the Default trait for the Snapshot type
+impl Default for Snapshot {
+    fn default() -> Snapshot {
+        Snapshot::new()
+    }
+}
+
+// EOF
@@ -0,0 +1,431 @@
+//! ADS-B Simulator - see README.md
+//
+// © NewForester, 2018.  Available under MIT licence terms.
+//
+//! The transport module provides the ADS-B Simulator with a common interface
+//! to everything it sends its MAVLink messages over.
+//!
+//! The `Transport` trait is implemented by the UDP (`inet::Inet`) and MQTT
+//! (`mqtt::Client`) back ends and by the serial, telemetry log file and
+//! stdout back ends below.  The `FanOut` structure is a transport too:  it
+//! sends each message over all the transports added to it so the same stream
+//! of messages may go, for example, to the UI by MQTT and to an autopilot
+//! by UDP at the same time.
+//!
+//! Each transport is a MAVLink link of its own:  the fan out serialises each
+//! message afresh for each transport, with the sequence number of that
+//! transport's link (and a signature if messages are signed), so a transport
+//! that sends only some messages leaves no gaps in the sequence numbers of
+//! the others.
+//!
+//! The `set_cli()` functions provide the implementation of the parsing of
+//! certain command line parameters as described in README.md.
+//!
+use std::fs;
+use std::io::{Error, Write};
+use std::process::Command;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use inet::Inet;
+use mavlink;
+use mavlink::{Link, MavMessage};
+use mavlink::json;
+use mavlink::signing::Signing;
+use mqtt::Client;
+
+// ---------------------------------------------------------------------------
+
+/// The Transport trait is implemented by everything MAVLink messages can be sent over
+pub trait Transport {
+    // name() returns a description of the transport (for diagnostics)
+    fn name(&self) -> String;
+
+    // send() sends one MAVLink message with the given id
+    fn send(&mut self, msgid: u32, message: &[u8]) -> Result<usize, Error>;
+
+    // wants() returns whether messages with the given id are sent at all (so use up a sequence number)
+    fn wants(&self, _msgid: u32) -> bool {
+        true
+    }
+}
+
+// ---------------------------------------------------------------------------
+
+/// The implementation of the Transport trait for UDP
+impl Transport for Inet {
+    fn name(&self) -> String {
+        format!("UDP {}", self.get_address())
+    }
+
+    fn send(&mut self, _msgid: u32, message: &[u8]) -> Result<usize, Error> {
+        Inet::send(self, message)
+    }
+}
+
+/// The implementation of the Transport trait for MQTT (the topic is given by the publish template)
+/// with, optionally, a JSON rendering of each message published to a parallel topic
+impl Transport for Client {
+    fn name(&self) -> String {
+        format!("MQTT {}", self.get_templates().0.replace("{pubtopic}", &self.pubtopic))
+    }
+
+    fn send(&mut self, msgid: u32, message: &[u8]) -> Result<usize, Error> {
+        let (sysid, compid) = mavlink::source(message).unwrap_or((0, 0));
+
+        let topic = self.get_topic(msgid, sysid, compid);
+
+        let sent = self.publish_to(&topic, message)?;
+
+        // the JSON rendering follows the message it mirrors (and shares its fate if not connected)
+        if let Some(topic) = self.get_json_topic(msgid, sysid, compid) {
+            self.publish_to(&topic, json::render(message)?.as_bytes())?;
+        }
+
+        Ok(sent)
+    }
+}
+
+// ---------------------------------------------------------------------------
+
+/// The Serial structure holds the serial device messages are written to and its baud rate
+pub struct Serial {
+    device:     String,
+    baud:       u32,
+
+    file:       Option<fs::File>,
+}
+
+/// The implementation of methods for the Serial type
+impl Serial {
+    // new() creates and initialises a Serial structure
+    pub fn new() -> Serial {
+        Serial {
+            device:     "/dev/ttyUSB0".to_string(),
+            baud:       57600,
+            file:       None,
+        }
+    }
+
+    // set_cli() extract serial parameters from a CLI parameter string
+    pub fn set_cli(&mut self, cli: &str) -> Result<&mut Self, String> {
+        let pair: Vec<&str> = cli.split(':').collect();
+
+        if pair.len() > 2 {
+            return Err(format!("'{}' is not of the form 'device:baud'", cli));
+        }
+
+        if ! pair[0].is_empty() {
+            self.device = pair[0].to_string();
+        }
+
+        if pair.len() > 1 && ! pair[1].is_empty() {
+            self.baud = pair[1].parse().map_err(|_| format!("'{}' is not a baud rate", pair[1]))?;
+        }
+
+        Ok(self)
+    }
+
+    // get_device() returns the path of the serial device
+    pub fn get_device(&self) -> &str {
+        &self.device
+    }
+
+    // open() sets the line speed and raw mode (std has no termios so ask stty) and opens the device
+    pub fn open(&mut self) -> Result<&mut Self, Error> {
+        let status = Command::new("stty")
+                        .args(["-F", &self.device, &self.baud.to_string(), "raw", "-echo"])
+                        .status()?;
+
+        if ! status.success() {
+            return Err(Error::other(format!("stty could not set {} to {} baud", self.device, self.baud)));
+        }
+
+        self.file = Some(fs::OpenOptions::new().write(true).open(&self.device)?);
+
+        Ok(self)
+    }
+}
+
+/// The implementation of the Default trait for the Serial type
+impl Default for Serial {
+    fn default() -> Serial {
+        Serial::new()
+    }
+}
+
+/// The implementation of the Transport trait for a serial device
+impl Transport for Serial {
+    fn name(&self) -> String {
+        format!("serial {} ({} baud)", self.device, self.baud)
+    }
+
+    fn send(&mut self, _msgid: u32, message: &[u8]) -> Result<usize, Error> {
+        match self.file {
+            Some(ref mut file) => file.write_all(message).map(|_| message.len()),
+            None               => Ok(0),
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+
+/// The Tlog structure holds the telemetry log file messages are written to
+pub struct Tlog {
+    path:       String,
+
+    file:       Option<fs::File>,
+}
+
+/// The implementation of methods for the Tlog type
+impl Tlog {
+    // new() creates and initialises a Tlog structure
+    pub fn new() -> Tlog {
+        Tlog {
+            path:       "adsbsim.tlog".to_string(),
+            file:       None,
+        }
+    }
+
+    // set_cli() extract telemetry log parameters from a CLI parameter string
+    pub fn set_cli(&mut self, cli: &str) -> &mut Self {
+        if ! cli.is_empty() {
+            self.path = cli.to_string();
+        }
+
+        self
+    }
+
+    // get_path() returns the path of the telemetry log file
+    pub fn get_path(&self) -> &str {
+        &self.path
+    }
+
+    // open() creates (or truncates) the telemetry log file
+    pub fn open(&mut self) -> Result<&mut Self, Error> {
+        self.file = Some(fs::File::create(&self.path)?);
+
+        Ok(self)
+    }
+}
+
+/// The implementation of the Default trait for the Tlog type
+impl Default for Tlog {
+    fn default() -> Tlog {
+        Tlog::new()
+    }
+}
+
+/// The implementation of the Transport trait for a telemetry log file
+impl Transport for Tlog {
+    fn name(&self) -> String {
+        format!("tlog {}", self.path)
+    }
+
+    // send() writes the message preceded by the time in microseconds (big-endian) as do ground stations
+    fn send(&mut self, _msgid: u32, message: &[u8]) -> Result<usize, Error> {
+        let file = match self.file {
+            Some(ref mut file) => file,
+            None               => return Ok(0),
+        };
+
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).map_err(Error::other)?;
+        let usec = now.as_secs() * 1_000_000 + now.subsec_micros() as u64;
+
+        let mut record = Vec::with_capacity(8 + message.len());
+
+        for ii in (0 .. 8).rev() {
+            record.push((usec >> (8 * ii)) as u8);
+        }
+
+        record.extend_from_slice(message);
+
+        file.write_all(&record).map(|_| message.len())
+    }
+}
+
+// ---------------------------------------------------------------------------
+
+/// The Stdout structure holds which messages are to be printed (all if none are listed)
+pub struct Stdout {
+    msgids:     Vec<u32>,
+}
+
+/// The implementation of methods for the Stdout type
+impl Stdout {
+    // new() creates and initialises a Stdout structure
+    pub fn new() -> Stdout {
+        Stdout {
+            msgids:     Vec::new(),
+        }
+    }
+
+    // set_cli() extract the ids of the messages to be printed from a CLI parameter string
+    pub fn set_cli(&mut self, cli: &str) -> Result<&mut Self, String> {
+        for msgid in cli.split(',').filter(|msgid| ! msgid.is_empty()) {
+            self.msgids.push(msgid.parse().map_err(|_| format!("'{}' is not a message id", msgid))?);
+        }
+
+        Ok(self)
+    }
+}
+
+/// The implementation of the Default trait for the Stdout type
+impl Default for Stdout {
+    fn default() -> Stdout {
+        Stdout::new()
+    }
+}
+
+/// The implementation of the Transport trait for stdout (in hex)
+impl Transport for Stdout {
+    fn name(&self) -> String {
+        "stdout".to_string()
+    }
+
+    fn send(&mut self, msgid: u32, message: &[u8]) -> Result<usize, Error> {
+        if ! self.wants(msgid) {
+            return Ok(0);
+        }
+
+        let hex: Vec<String> = message.iter().map(|byte| format!("{:02x}", byte)).collect();
+
+        println!("{:3}: {}", msgid, hex.join(" "));
+
+        Ok(message.len())
+    }
+
+    fn wants(&self, msgid: u32) -> bool {
+        self.msgids.is_empty() || self.msgids.contains(&msgid)
+    }
+}
+
+// ---------------------------------------------------------------------------
+
+/// The Output structure holds a transport, the link of the messages sent over it and whether it is failing
+struct Output {
+    transport:  Box<dyn Transport + Send>,
+    link:       Link,
+    failing:    bool,
+}
+
+/// The FanOut structure holds the transports every message is sent over, the ids of the messages and the signing context, if any
+pub struct FanOut {
+    transports: Vec<Output>,
+    source:     (u8, u8),
+    signing:    Option<Signing>,
+}
+
+/// The implementation of methods for the FanOut type
+impl FanOut {
+    // new() creates and initialises a FanOut structure (with no transports)
+    pub fn new() -> FanOut {
+        FanOut {
+            transports: Vec::new(),
+            source:     (Link::new().get_sysid(), Link::new().get_compid()),
+            signing:    None,
+        }
+    }
+
+    // set_source() sets the system and component ids of the messages sent over every transport
+    pub fn set_source(&mut self, sysid: u8, compid: u8) -> &mut Self {
+        self.source = (sysid, compid);
+
+        for output in self.transports.iter_mut() {
+            output.link.set_source(sysid, compid);
+        }
+
+        self
+    }
+
+    // set_signing() sets the key and link id with which to sign messages sent
+    pub fn set_signing(&mut self, signing: Signing) -> &mut Self {
+        self.signing = Some(signing);
+
+        self
+    }
+
+    // add() adds a transport (with a link of its own)
+    pub fn add(&mut self, transport: Box<dyn Transport + Send>) -> &mut Self {
+        let mut link = Link::new();
+
+        link.set_source(self.source.0, self.source.1);
+
+        self.transports.push(Output {transport, link, failing: false});
+
+        self
+    }
+
+    // len() returns the number of transports
+    pub fn len(&self) -> usize {
+        self.transports.len()
+    }
+
+    // is_empty() returns whether there are no transports
+    pub fn is_empty(&self) -> bool {
+        self.transports.is_empty()
+    }
+}
+
+/// The implementation of the Default trait for the FanOut type
+impl Default for FanOut {
+    fn default() -> FanOut {
+        FanOut::new()
+    }
+}
+
+/// The implementation of the Transport trait for a fan out
+impl Transport for FanOut {
+    fn name(&self) -> String {
+        let names: Vec<String> = self.transports.iter().map(|output| output.transport.name()).collect();
+
+        names.join(", ")
+    }
+
+    // send() sends a message over every transport that wants it, framed for the transport's link,
+    // reports the first of each run of failures and returns the number sent
+    fn send(&mut self, msgid: u32, message: &[u8]) -> Result<usize, Error> {
+        // a message that cannot be parsed cannot be framed afresh so it goes as it is
+        let mut parsed = mavlink::to_v1(message).and_then(|frame| MavMessage::parse(&frame)).ok();
+
+        let now = Instant::now();
+        let mut sent = 0;
+
+        for output in self.transports.iter_mut() {
+            if ! output.transport.wants(msgid) {
+                continue;
+            }
+
+            let mut frame = match parsed {
+                Some(ref mut parsed) => parsed.serialise(&mut output.link).to_vec(),
+                None                 => message.to_vec(),
+            };
+
+            if let Some(ref mut signing) = self.signing {
+                frame = match signing.sign(&frame, now) {
+                    Ok(signed) => signed,
+                    Err(e) => {
+                        println!("message {} not signed: {}", msgid, e);
+                        continue;
+                    }
+                };
+            }
+
+            match output.transport.send(msgid, &frame) {
+                Ok(_) => {
+                    sent += 1;
+                    output.failing = false;
+                },
+                Err(e) => {
+                    if ! output.failing {
+                        println!("Error: {}: {}", output.transport.name(), e);
+                    }
+                    output.failing = true;
+                },
+            }
+        }
+
+        Ok(sent)
+    }
+}
+
+// EOF
@@ -0,0 +1,358 @@
+//! ADS-B Simulator - see README.md
+//
+// © NewForester, 2018.  Available under MIT licence terms.
+//
+//! Tests of the embedded MQTT broker using hand-made MQTT packets over TCP
+//! and over WebSockets (as the UI would).
+//!
+extern crate adsbsim;
+
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::time::Duration;
+
+use adsbsim::broker::{self, Broker};
+
+// ---------------------------------------------------------------------------
+
+// free_port() returns a port nothing is listening on (probably)
+fn free_port() -> u16 {
+    TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port()
+}
+
+fn start(websocket: bool) -> Broker {
+    let mut broker = Broker::new();
+
+    broker.set_host("127.0.0.1");
+
+    if websocket {
+        broker.set_cli(&format!("0:{}", free_port())).unwrap();
+    } else {
+        broker.set_cli("0:0").unwrap();
+    }
+
+    broker.start().unwrap();
+
+    broker
+}
+
+fn string(text: &str) -> Vec<u8> {
+    let mut bytes = vec![(text.len() >> 8) as u8, text.len() as u8];
+
+    bytes.extend_from_slice(text.as_bytes());
+
+    bytes
+}
+
+fn packet(header: u8, body: &[u8]) -> Vec<u8> {
+    assert!(body.len() < 128);
+
+    let mut packet = vec![header, body.len() as u8];
+
+    packet.extend_from_slice(body);
+
+    packet
+}
+
+fn connect_packet(clientid: &str, will: Option<(&str, &str)>) -> Vec<u8> {
+    let mut body = string("MQTT");
+
+    body.push(4);
+    body.push(if will.is_some() { 0x06 } else { 0x02 });
+    body.extend_from_slice(&[0, 60]);
+    body.extend(string(clientid));
+
+    if let Some((topic, message)) = will {
+        body.extend(string(topic));
+        body.extend(string(message));
+    }
+
+    packet(0x10, &body)
+}
+
+fn subscribe_packet(filter: &str) -> Vec<u8> {
+    let mut body = vec![0, 1];
+
+    body.extend(string(filter));
+    body.push(1);
+
+    packet(0x82, &body)
+}
+
+fn publish_packet(topic: &str, payload: &[u8], retain: bool) -> Vec<u8> {
+    let mut body = string(topic);
+
+    body.extend_from_slice(payload);
+
+    packet(if retain { 0x31 } else { 0x30 }, &body)
+}
+
+// read_packet() reads one (small) MQTT packet
+fn read_packet<R: Read>(stream: &mut R) -> (u8, Vec<u8>) {
+    let mut header = [0u8; 2];
+
+    stream.read_exact(&mut header).unwrap();
+
+    assert!(header[1] < 128);
+
+    let mut body = vec![0u8; header[1] as usize];
+
+    stream.read_exact(&mut body).unwrap();
+
+    (header[0], body)
+}
+
+// read_publish() reads a PUBLISH packet and returns its topic and payload
+fn read_publish<R: Read>(stream: &mut R) -> (String, Vec<u8>) {
+    let (header, body) = read_packet(stream);
+
+    assert_eq!(header & 0xf0, 0x30);
+
+    let length = (body[0] as usize) << 8 | body[1] as usize;
+
+    (String::from_utf8(body[2 .. 2 + length].to_vec()).unwrap(), body[2 + length ..].to_vec())
+}
+
+fn client(broker: &Broker, clientid: &str, will: Option<(&str, &str)>) -> TcpStream {
+    let mut stream = TcpStream::connect(("127.0.0.1", broker.get_port())).unwrap();
+
+    stream.set_read_timeout(Some(Duration::new(5, 0))).unwrap();
+
+    stream.write_all(&connect_packet(clientid, will)).unwrap();
+
+    assert_eq!(read_packet(&mut stream), (0x20, vec![0, 0]));
+
+    stream
+}
+
+fn subscribe(stream: &mut TcpStream, filter: &str) {
+    stream.write_all(&subscribe_packet(filter)).unwrap();
+
+    assert_eq!(read_packet(stream), (0x90, vec![0, 1, 0]));
+}
+
+// websocket() opens a WebSocket connection to the broker and returns it with the handshake response
+fn websocket(broker: &Broker) -> (TcpStream, String) {
+    let mut stream = TcpStream::connect(("127.0.0.1", broker.get_wsport())).unwrap();
+
+    stream.set_read_timeout(Some(Duration::new(5, 0))).unwrap();
+
+    stream.write_all(b"GET /mqtt HTTP/1.1\r\nHost: localhost\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\
+                       Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\nSec-WebSocket-Protocol: mqtt\r\n\
+                       Sec-WebSocket-Version: 13\r\n\r\n").unwrap();
+
+    let mut response = Vec::new();
+
+    while ! response.ends_with(b"\r\n\r\n") {
+        let mut byte = [0u8; 1];
+
+        stream.read_exact(&mut byte).unwrap();
+
+        response.push(byte[0]);
+    }
+
+    (stream, String::from_utf8(response).unwrap())
+}
+
+// masked() returns a (small) client WebSocket frame with the given opcode
+fn masked(opcode: u8, payload: &[u8]) -> Vec<u8> {
+    assert!(payload.len() < 126);
+
+    let mask = [0x12, 0x34, 0x56, 0x78];
+    let mut frame = vec![0x80 | opcode, 0x80 | payload.len() as u8];
+
+    frame.extend_from_slice(&mask);
+    frame.extend(payload.iter().enumerate().map(|(ii, byte)| byte ^ mask[ii % 4]));
+
+    frame
+}
+
+// ---------------------------------------------------------------------------
+
+#[test]
+fn websocket_accept_key() {
+    // the example in RFC 6455
+    assert_eq!(broker::accept("dGhlIHNhbXBsZSBub25jZQ=="), "s3pPLMBiTxaQ9kYGzzhZRbK+xOo=");
+}
+
+#[test]
+fn topic_filters() {
+    assert!(broker::matches("/300159/202", "/300159/202"));
+    assert!(! broker::matches("/300159/202", "/300159/246"));
+    assert!(broker::matches("/300159/+", "/300159/246"));
+    assert!(! broker::matches("/300159/+", "/300159/246/x"));
+    assert!(broker::matches("/300159/#", "/300159/246/x"));
+    assert!(broker::matches("/300159/#", "/300159"));
+    assert!(broker::matches("/+/202", "/151060/202"));
+    assert!(broker::matches("#", "/151060/202"));
+    assert!(! broker::matches("/151060", "/151060/202"));
+}
+
+#[test]
+fn publish_and_subscribe() {
+    let broker = start(false);
+
+    let mut subscriber = client(&broker, "subscriber", None);
+    let mut publisher = client(&broker, "publisher", None);
+
+    subscribe(&mut subscriber, "/300159/+");
+    subscribe(&mut subscriber, "/300159/202");
+
+    publisher.write_all(&publish_packet("/151060/202", b"not this one", false)).unwrap();
+    publisher.write_all(&publish_packet("/300159/202", b"\xfe\x01", false)).unwrap();
+
+    // once only even though two subscriptions match
+    assert_eq!(read_publish(&mut subscriber), ("/300159/202".to_string(), b"\xfe\x01".to_vec()));
+
+    publisher.write_all(&[0xc0, 0]).unwrap();
+
+    assert_eq!(read_packet(&mut publisher), (0xd0, vec![]));
+
+    publisher.write_all(&publish_packet("/300159/246", b"next", false)).unwrap();
+
+    assert_eq!(read_publish(&mut subscriber), ("/300159/246".to_string(), b"next".to_vec()));
+}
+
+#[test]
+fn retained_and_will_messages() {
+    let broker = start(false);
+
+    let mut publisher = client(&broker, "publisher", Some(("/300159/status", "offline")));
+
+    publisher.write_all(&publish_packet("/300159/status", b"online", true)).unwrap();
+    publisher.write_all(&[0xc0, 0]).unwrap();
+    read_packet(&mut publisher);
+
+    let mut subscriber = client(&broker, "subscriber", None);
+
+    subscribe(&mut subscriber, "/300159/status");
+
+    assert_eq!(read_publish(&mut subscriber), ("/300159/status".to_string(), b"online".to_vec()));
+
+    drop(publisher);
+
+    assert_eq!(read_publish(&mut subscriber), ("/300159/status".to_string(), b"offline".to_vec()));
+}
+
+#[test]
+fn websocket_client() {
+    let broker = start(true);
+
+    let mut publisher = client(&broker, "publisher", None);
+
+    let (mut stream, response) = websocket(&broker);
+
+    assert!(response.starts_with("HTTP/1.1 101"));
+    assert!(response.contains("Sec-WebSocket-Accept: s3pPLMBiTxaQ9kYGzzhZRbK+xOo="));
+    assert!(response.contains("Sec-WebSocket-Protocol: mqtt"));
+
+    // client frames are masked:  send CONNECT and SUBSCRIBE in one frame each
+    for packet in [connect_packet("ui", None), subscribe_packet("/+/202")].iter() {
+        stream.write_all(&masked(0x2, packet)).unwrap();
+    }
+
+    // server frames are not masked
+    let mut frames = Vec::new();
+
+    for _ in 0 .. 2 {
+        let mut header = [0u8; 2];
+
+        stream.read_exact(&mut header).unwrap();
+
+        assert_eq!(header[0], 0x82);
+
+        let mut payload = vec![0u8; header[1] as usize];
+
+        stream.read_exact(&mut payload).unwrap();
+
+        frames.push(payload);
+    }
+
+    assert_eq!(frames[0], vec![0x20, 2, 0, 0]);
+    assert_eq!(frames[1], vec![0x90, 3, 0, 1, 0]);
+
+    publisher.write_all(&publish_packet("/151060/202", b"ownship", false)).unwrap();
+
+    let mut header = [0u8; 2];
+
+    stream.read_exact(&mut header).unwrap();
+
+    let mut payload = vec![0u8; header[1] as usize];
+
+    stream.read_exact(&mut payload).unwrap();
+
+    assert_eq!(read_publish(&mut &payload[..]), ("/151060/202".to_string(), b"ownship".to_vec()));
+}
+
+#[test]
+fn websocket_pings_answered() {
+    let broker = start(true);
+
+    let (mut stream, _) = websocket(&broker);
+
+    stream.write_all(&masked(0x9, b"hello")).unwrap();
+
+    let mut pong = [0u8; 7];
+
+    stream.read_exact(&mut pong).unwrap();
+
+    assert_eq!(&pong, b"\x8a\x05hello");
+}
+
+#[test]
+fn websocket_frame_lengths_not_believed() {
+    let broker = start(true);
+
+    // a text frame that claims to be 2^62 bytes long and then goes away
+    {
+        let (mut stream, _) = websocket(&broker);
+
+        stream.write_all(&[0x81, 0xff, 0x40, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, b'x']).unwrap();
+    }
+
+    // and so does a ping that is far too long
+    {
+        let (mut stream, _) = websocket(&broker);
+
+        stream.write_all(&[0x89, 0xff, 0x40, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]).unwrap();
+
+        let mut byte = [0u8; 1];
+
+        assert!(stream.read(&mut byte).map(|count| count == 0).unwrap_or(true));
+    }
+
+    // the broker lives on
+    let mut stream = client(&broker, "survivor", None);
+
+    subscribe(&mut stream, "/+/202");
+}
+
+#[test]
+fn suback_remaining_length_encoded() {
+    let broker = start(false);
+
+    let mut stream = client(&broker, "greedy", None);
+
+    // 130 filters so that the SUBACK has more than 127 bytes after its fixed header
+    let mut body = vec![0, 1];
+
+    for _ in 0 .. 130 {
+        body.extend(string("a"));
+        body.push(0);
+    }
+
+    let mut subscribe = vec![0x82, (body.len() % 128) as u8 | 0x80, (body.len() / 128) as u8];
+
+    subscribe.extend(body);
+
+    stream.write_all(&subscribe).unwrap();
+
+    let mut suback = vec![0u8; 3 + 2 + 130];
+
+    stream.read_exact(&mut suback).unwrap();
+
+    assert_eq!(suback[.. 5], [0x90, (132 % 128) | 0x80, 132 / 128, 0, 1]);
+    assert!(suback[5 ..].iter().all(|&granted| granted == 0));
+}
+
+// EOF
@@ -0,0 +1,148 @@
+//! ADS-B Simulator - see README.md
+//
+// © NewForester, 2018.  Available under MIT licence terms.
+//
+//! Golden vector tests of the GDL 90 encoders.
+//!
+extern crate adsbsim;
+
+use adsbsim::gdl90;
+use adsbsim::mavlink::{msg202, msg246};
+
+// ---------------------------------------------------------------------------
+
+// unframe() returns the message id and data of a GDL 90 frame having checked its flags and CRC
+fn unframe(frame: &[u8]) -> Vec<u8> {
+    assert_eq!((frame[0], frame[frame.len() - 1]), (0x7e, 0x7e));
+
+    let mut message: Vec<u8> = Vec::new();
+    let mut escaped = false;
+
+    for &byte in &frame[1 .. frame.len() - 1] {
+        assert_ne!(byte, 0x7e);
+
+        if escaped {
+            message.push(byte ^ 0x20);
+            escaped = false;
+        } else if byte == 0x7d {
+            escaped = true;
+        } else {
+            message.push(byte);
+        }
+    }
+
+    let crc = message.split_off(message.len() - 2);
+
+    assert_eq!(gdl90::crc(&message), crc[0] as u16 | (crc[1] as u16) << 8);
+
+    message
+}
+
+// traffic() returns a traffic report for a craft with the given position and vertical speed
+fn traffic(latitude: f32, longitude: f32, altitude: f32, rateofclimb: f32) -> Vec<u8> {
+    let mut traffic = msg246::Message::new();
+
+    traffic.set_gps(latitude, longitude).set_altitude(altitude).set_rateofclimb(rateofclimb);
+
+    unframe(&gdl90::traffic_report(&traffic))
+}
+
+// ---------------------------------------------------------------------------
+
+#[test]
+fn crc_of_spec_heartbeat_example() {
+    // the example heartbeat of the GDL 90 Data Interface Specification (560-1058-00), section 2.2.3
+    let spec = [0x7e, 0x00, 0x81, 0x41, 0xdb, 0xd0, 0x08, 0x02, 0xb3, 0x8b, 0x7e];
+
+    assert_eq!(gdl90::crc(&spec[1 .. 8]), 0x8bb3);
+    assert_eq!(gdl90::frame(0, &spec[2 .. 8]), spec);
+}
+
+#[test]
+fn flag_and_escape_bytes_stuffed() {
+    // with message id 2 the CRC of a single data byte 0x7e (0x7d) is 0x027e (0x027d)
+    assert_eq!(gdl90::frame(2, &[0x7e]), [0x7e, 0x02, 0x7d, 0x5e, 0x7d, 0x5e, 0x02, 0x7e]);
+    assert_eq!(gdl90::frame(2, &[0x7d]), [0x7e, 0x02, 0x7d, 0x5d, 0x7d, 0x5d, 0x02, 0x7e]);
+
+    // and a neighbour that needs no stuffing
+    assert_eq!(gdl90::frame(2, &[0x7c]), [0x7e, 0x02, 0x7c, 0x7c, 0x02, 0x7e]);
+}
+
+#[test]
+fn positions_in_24_bit_semicircles() {
+    let report = traffic(45.0, -90.0, 0.0, 0.0);
+
+    assert_eq!(report[0], 20);
+    assert_eq!(report[5 .. 11], [0x20, 0x00, 0x00, 0xc0, 0x00, 0x00]);
+
+    let report = traffic(-45.0, 180.0, 0.0, 0.0);
+
+    assert_eq!(report[5 .. 11], [0xe0, 0x00, 0x00, 0x80, 0x00, 0x00]);
+
+    let report = traffic(0.0, -0.0000215, 0.0, 0.0);
+
+    assert_eq!(report[5 .. 11], [0x00, 0x00, 0x00, 0xff, 0xff, 0xff]);
+}
+
+#[test]
+fn altitudes_offset_by_1000_feet() {
+    // 25 ft increments in the top 12 bits, airborne with true track in the bottom 4 bits
+    assert_eq!(traffic(0.0, 0.0, 0.0, 0.0)[11 .. 13], [0x02, 0x89]);
+    assert_eq!(traffic(0.0, 0.0, 3048.0, 0.0)[11 .. 13], [0x1b, 0x89]);
+    assert_eq!(traffic(0.0, 0.0, -152.4, 0.0)[11 .. 13], [0x01, 0x49]);
+
+    // out of range is 'invalid or not available'
+    assert_eq!(traffic(0.0, 0.0, 40000.0, 0.0)[11 .. 13], [0xff, 0xf9]);
+    assert_eq!(traffic(0.0, 0.0, -1000.0, 0.0)[11 .. 13], [0xff, 0xf9]);
+}
+
+#[test]
+fn vertical_rates_limited() {
+    let vertical = |report: Vec<u8>| (report[15] as u16 & 0x0f) << 8 | report[16] as u16;
+
+    // 64 ft/min units (0.33 m/s rounds to 1) as 12 bit two's complement
+    assert_eq!(vertical(traffic(0.0, 0.0, 0.0, 0.0)), 0x000);
+    assert_eq!(vertical(traffic(0.0, 0.0, 0.0, 0.33)), 0x001);
+    assert_eq!(vertical(traffic(0.0, 0.0, 0.0, -0.33)), 0xfff);
+
+    // beyond +/- 32,640 ft/min the limits
+    assert_eq!(vertical(traffic(0.0, 0.0, 0.0, 300.0)), 0x1fe);
+    assert_eq!(vertical(traffic(0.0, 0.0, 0.0, -300.0)), 0xe02);
+
+    // no vertical velocity, no data
+    let mut report = msg246::Message::new();
+
+    report.set_gps(0.0, 0.0).set_altitude(0.0);
+
+    assert_eq!(vertical(unframe(&gdl90::traffic_report(&report))), 0x800);
+}
+
+#[test]
+fn callsigns_space_padded() {
+    let mut report = msg246::Message::new();
+
+    report.set_callsign("N12");
+    assert_eq!(&unframe(&gdl90::traffic_report(&report))[19 .. 27], b"N12     ");
+
+    report.set_callsign("ab-1");
+    assert_eq!(&unframe(&gdl90::traffic_report(&report))[19 .. 27], b"AB 1    ");
+
+    let ownship = unframe(&gdl90::ownship_report(&msg202::Message::new(), 0xabcdef, "UAV1", gdl90::EMITTER_UAV));
+
+    assert_eq!(ownship[0], 10);
+    assert_eq!(ownship[2 .. 5], [0xab, 0xcd, 0xef]);
+    assert_eq!(&ownship[19 .. 27], b"UAV1    ");
+}
+
+#[test]
+fn geometric_altitude_from_gnss_altitude() {
+    let mut ownship = msg202::Message::new();
+
+    ownship.altpres = 0;
+    ownship.altgnss = 1524000;
+
+    // 5 ft units:  5,000 ft is 1,000
+    assert_eq!(unframe(&gdl90::ownship_geometric_altitude(&ownship)), [11, 0x03, 0xe8, 0x7f, 0xff]);
+}
+
+// EOF
@@ -0,0 +1,59 @@
+//! ADS-B Simulator - see README.md
+//
+// © NewForester, 2018.  Available under MIT licence terms.
+//
+//! Tests of the link quality statistics kept for the messages received.
+//!
+extern crate adsbsim;
+
+use adsbsim::linkstats::LinkStats;
+use adsbsim::mavlink::{Link, Message};
+use adsbsim::mavlink::{msg0, msg202};
+
+// ---------------------------------------------------------------------------
+
+fn linkstats(cli: &str) -> LinkStats {
+    let mut linkstats = LinkStats::new();
+
+    linkstats.set_cli(cli).unwrap();
+
+    linkstats
+}
+
+// ownship_only() returns the 202 messages of a link that sends a heartbeat between each
+fn ownship_only(count: usize) -> Vec<Vec<u8>> {
+    let mut link = Link::new();
+
+    link.set_source(2, 1);
+
+    (0 .. count).map(|_| {
+        msg0::Message::new().serialise(&mut link);
+        msg202::Message::new().serialise(&mut link).to_vec()
+    }).collect()
+}
+
+// ---------------------------------------------------------------------------
+
+#[test]
+fn gaps_lost_only_when_whole_link_received() {
+    let mut some = linkstats("0");
+    let mut all = linkstats("0:all");
+
+    for frame in ownship_only(5) {
+        assert!(some.received(&frame));
+        assert!(all.received(&frame));
+    }
+
+    assert_eq!(some.get_lost(2, 1), 0);
+    assert_eq!(all.get_lost(2, 1), 4);
+}
+
+#[test]
+fn bad_parameters_rejected() {
+    assert!(LinkStats::new().set_cli("often").is_err());
+    assert!(LinkStats::new().set_cli("10:some").is_err());
+    assert!(LinkStats::new().set_cli("10:all:all").is_err());
+    assert!(LinkStats::new().set_cli("10:all").is_ok());
+}
+
+// EOF
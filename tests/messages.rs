@@ -0,0 +1,1017 @@
+//! ADS-B Simulator - see README.md
+//
+// © NewForester, 2018.  Available under MIT licence terms.
+//
+//! Tests of the MAVLink message implementations.
+//!
+//! The golden vectors are complete MAVLink v1 frames (v2 for the uAvionix
+//! messages, whose ids do not fit in a byte).  They were produced
+//! independently of this crate by packing the fields as the MAVLink reference
+//! implementation does (largest types first, little endian, X.25 checksum
+//! seeded with the CRC_EXTRA of the official definition) so they catch a wrong
+//! field order or CRC_EXTRA.  Messages 202 and 203 are not in the official
+//! definitions:  their vectors use the CRC_EXTRA the receivers expect.
+//!
+//! The round-trip tests are property-based:  each serialises many messages
+//! with pseudo-random field values, parses them back and checks nothing has
+//! changed.  They also check that corrupting any byte covered by the checksum
+//! gets the message rejected.  The pseudo-random generator is seeded so any
+//! failure is repeatable.
+//!
+extern crate adsbsim;
+
+use adsbsim::coords::CwithV;
+use adsbsim::mavlink;
+use adsbsim::mavlink::{json, Link, Message, MavMessage};
+use adsbsim::mavlink::{msg0, msg20, msg21, msg22, msg23, msg24, msg33, msg44, msg47, msg51, msg66, msg73, msg76, msg77, msg84, msg202, msg203, msg246};
+use adsbsim::mavlink::{msg10001, msg10002, msg10003};
+
+// ---------------------------------------------------------------------------
+
+const HEARTBEAT: [u8; 17] = [
+    0xfe, 0x09, 0x00, 0x01, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x1b, 0x08, 0x00, 0x04, 0x03, 0xff,
+    0x34,
+];
+
+const PARAM_REQUEST_READ: [u8; 28] = [
+    0xfe, 0x14, 0x00, 0x01, 0x01, 0x14, 0xff, 0xff, 0x19, 0x59, 0x41, 0x44, 0x53, 0x42, 0x5f, 0x49,
+    0x43, 0x41, 0x4f, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x8b, 0x3e,
+];
+
+const PARAM_REQUEST_LIST: [u8; 10] = [
+    0xfe, 0x02, 0x00, 0x01, 0x01, 0x15, 0x19, 0x59, 0x60, 0xa4,
+];
+
+const PARAM_VALUE: [u8; 33] = [
+    0xfe, 0x19, 0x00, 0x01, 0x01, 0x16, 0x00, 0x40, 0x9c, 0x45, 0x0b, 0x00, 0x03, 0x00, 0x41, 0x44,
+    0x53, 0x42, 0x5f, 0x52, 0x41, 0x4e, 0x47, 0x45, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x09, 0x4a,
+    0xb5,
+];
+
+const PARAM_SET: [u8; 31] = [
+    0xfe, 0x17, 0x00, 0x01, 0x01, 0x17, 0xb0, 0xa2, 0x91, 0x49, 0x19, 0x59, 0x41, 0x44, 0x53, 0x42,
+    0x5f, 0x49, 0x43, 0x41, 0x4f, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x05, 0x05, 0x98,
+];
+
+const GPS_RAW_INT: [u8; 38] = [
+    0xfe, 0x1e, 0x00, 0x01, 0x01, 0x18, 0x00, 0xc0, 0x29, 0xf7, 0x3d, 0x54, 0x05, 0x00, 0x80, 0xfb,
+    0x65, 0x1e, 0x80, 0x69, 0x67, 0xff, 0xf0, 0x49, 0x02, 0x00, 0x78, 0x00, 0xc8, 0x00, 0xe8, 0x03,
+    0x28, 0x23, 0x03, 0x0a, 0xc6, 0xdd,
+];
+
+const GLOBAL_POSITION_INT: [u8; 36] = [
+    0xfe, 0x1c, 0x00, 0x01, 0x01, 0x21, 0xe8, 0x03, 0x00, 0x00, 0x80, 0xfb, 0x65, 0x1e, 0x80, 0x69,
+    0x67, 0xff, 0xf0, 0x49, 0x02, 0x00, 0x50, 0xc3, 0x00, 0x00, 0xe8, 0x03, 0x06, 0xff, 0x32, 0x00,
+    0xc4, 0x86, 0xa5, 0xb5,
+];
+
+const MISSION_COUNT: [u8; 12] = [
+    0xfe, 0x04, 0x00, 0x01, 0x01, 0x2c, 0x03, 0x00, 0x19, 0x59, 0x25, 0x8b,
+];
+
+const MISSION_ACK: [u8; 11] = [
+    0xfe, 0x03, 0x00, 0x01, 0x01, 0x2f, 0xff, 0xbe, 0x00, 0x4f, 0x5f,
+];
+
+const MISSION_REQUEST_INT: [u8; 12] = [
+    0xfe, 0x04, 0x00, 0x01, 0x01, 0x33, 0x02, 0x00, 0xff, 0xbe, 0xf1, 0x09,
+];
+
+const REQUEST_DATA_STREAM: [u8; 14] = [
+    0xfe, 0x06, 0x00, 0x01, 0x01, 0x42, 0x04, 0x00, 0x01, 0x01, 0x00, 0x01, 0x54, 0x24,
+];
+
+const MISSION_ITEM_INT: [u8; 45] = [
+    0xfe, 0x25, 0x00, 0x01, 0x01, 0x49, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xc0, 0x46, 0xb2, 0x1e, 0x80, 0x69, 0x67, 0xff, 0x00, 0x00,
+    0xf0, 0x42, 0x01, 0x00, 0x10, 0x00, 0x19, 0x59, 0x06, 0x00, 0x01, 0x28, 0x09,
+];
+
+const COMMAND_LONG: [u8; 41] = [
+    0xfe, 0x21, 0x00, 0x01, 0x01, 0x4c, 0x00, 0x00, 0x76, 0x43, 0x00, 0x50, 0x43, 0x48, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0xff, 0x01, 0x19, 0x59, 0x00, 0x64, 0x2b,
+];
+
+const COMMAND_ACK: [u8; 11] = [
+    0xfe, 0x03, 0x00, 0x01, 0x01, 0x4d, 0xff, 0x01, 0x00, 0x46, 0x5d,
+];
+
+const SET_POSITION_TARGET_LOCAL_NED: [u8; 61] = [
+    0xfe, 0x35, 0x00, 0x01, 0x01, 0x54, 0xe8, 0x03, 0x00, 0x00, 0x00, 0x00, 0xc0, 0x3f, 0x00, 0x00,
+    0x20, 0xc0, 0x00, 0x00, 0x20, 0xc1, 0x00, 0x00, 0x80, 0x3f, 0x00, 0x00, 0x00, 0x40, 0x00, 0x00,
+    0x00, 0xbf, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x80, 0x3e, 0x00, 0x00, 0x00, 0x00, 0xc7, 0x0d, 0x19, 0x59, 0x01, 0x16, 0xda,
+];
+
+const OWNSHIP: [u8; 50] = [
+    0xfe, 0x2a, 0x00, 0x01, 0x01, 0xca, 0x00, 0x2f, 0x68, 0x59, 0x80, 0xfb, 0x65, 0x1e, 0x80, 0x69,
+    0x67, 0xff, 0xf0, 0x49, 0x02, 0x00, 0xc0, 0x51, 0x02, 0x00, 0x0a, 0x00, 0x00, 0x00, 0x03, 0x00,
+    0x01, 0x00, 0xce, 0xff, 0xe8, 0x03, 0x06, 0xff, 0x00, 0x00, 0xb0, 0x04, 0x04, 0x07, 0x00, 0x03,
+    0x20, 0x68,
+];
+
+const STATUS: [u8; 9] = [
+    0xfe, 0x01, 0x00, 0x01, 0x01, 0xcb, 0x01, 0x69, 0xc4,
+];
+
+const ADSB_VEHICLE: [u8; 46] = [
+    0xfe, 0x26, 0x00, 0x01, 0x01, 0xf6, 0x59, 0x01, 0x30, 0x00, 0x80, 0xfb, 0x65, 0x1e, 0x80, 0x69,
+    0x67, 0xff, 0xf0, 0x49, 0x02, 0x00, 0x28, 0x23, 0xe8, 0x03, 0xce, 0xff, 0x1f, 0x00, 0xb0, 0x04,
+    0x01, 0x44, 0x2d, 0x52, 0x69, 0x73, 0x51, 0x00, 0x00, 0x00, 0x0e, 0x01, 0x04, 0x42,
+];
+
+const UAVIONIX_ADSB_OUT_CFG: [u8; 32] = [
+    0xfd, 0x14, 0x00, 0x00, 0x00, 0x01, 0x01, 0x11, 0x27, 0x00, 0x23, 0xc1, 0xab, 0x00, 0xc4, 0x09,
+    0x44, 0x52, 0x49, 0x53, 0x51, 0x31, 0x00, 0x00, 0x00, 0x0e, 0x01, 0x02, 0x03, 0x01, 0xb4, 0xfc,
+];
+
+const UAVIONIX_ADSB_OUT_DYNAMIC: [u8; 53] = [
+    0xfd, 0x29, 0x00, 0x00, 0x00, 0x01, 0x01, 0x12, 0x27, 0x00, 0x00, 0x8c, 0x86, 0x47, 0x80, 0xfb,
+    0x65, 0x1e, 0x80, 0x69, 0x67, 0xff, 0xf0, 0x49, 0x02, 0x00, 0xf5, 0x03, 0x00, 0x00, 0x88, 0x13,
+    0x00, 0x00, 0x2c, 0x01, 0x64, 0x00, 0xce, 0xff, 0xe8, 0x03, 0x06, 0xff, 0x03, 0x00, 0x34, 0x12,
+    0x03, 0x0a, 0x00, 0xaa, 0xa3,
+];
+
+// the same with the trailing zero of the payload dropped as MAVLink 2 senders do
+const UAVIONIX_ADSB_OUT_DYNAMIC_TRUNCATED: [u8; 52] = [
+    0xfd, 0x28, 0x00, 0x00, 0x00, 0x01, 0x01, 0x12, 0x27, 0x00, 0x00, 0x8c, 0x86, 0x47, 0x80, 0xfb,
+    0x65, 0x1e, 0x80, 0x69, 0x67, 0xff, 0xf0, 0x49, 0x02, 0x00, 0xf5, 0x03, 0x00, 0x00, 0x88, 0x13,
+    0x00, 0x00, 0x2c, 0x01, 0x64, 0x00, 0xce, 0xff, 0xe8, 0x03, 0x06, 0xff, 0x03, 0x00, 0x34, 0x12,
+    0x03, 0x0a, 0xf8, 0x1e,
+];
+
+const UAVIONIX_ADSB_TRANSCEIVER_HEALTH_REPORT: [u8; 13] = [
+    0xfd, 0x01, 0x00, 0x00, 0x00, 0x01, 0x01, 0x13, 0x27, 0x00, 0x01, 0x46, 0x10,
+];
+
+// ---------------------------------------------------------------------------
+
+/// The Random structure holds the state of a xorshift pseudo-random number generator
+struct Random {
+    state: u64,
+}
+
+impl Random {
+    fn new(seed: u64) -> Random {
+        Random { state: seed | 1 }
+    }
+
+    fn next(&mut self) -> u64 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+
+        self.state
+    }
+
+    fn u8(&mut self) -> u8 { self.next() as u8 }
+    fn u16(&mut self) -> u16 { self.next() as u16 }
+    fn u32(&mut self) -> u32 { self.next() as u32 }
+    fn i16(&mut self) -> i16 { self.next() as i16 }
+    fn i32(&mut self) -> i32 { self.next() as i32 }
+
+    // f32() returns any bit pattern at all (NaNs included)
+    fn f32(&mut self) -> f32 { f32::from_bits(self.u32()) }
+}
+
+const ROUNDS: usize = 1000;
+
+// ---------------------------------------------------------------------------
+
+fn link() -> Link {
+    let mut link = Link::new();
+
+    link.set_source(1, 1);
+
+    link
+}
+
+// parse() parses a frame that must be good
+fn parse(frame: &[u8]) -> MavMessage {
+    match MavMessage::parse(frame) {
+        Ok(message) => message,
+        Err(e) => panic!("frame {:02x?} rejected: {}", frame, e),
+    }
+}
+
+// corrupt() checks that flipping bits in any byte covered by the checksum gets a frame rejected
+fn corrupt(frame: &[u8], random: &mut Random) {
+    let mut bad = frame.to_vec();
+    let index = 1 + random.next() as usize % (frame.len() - 1);
+
+    bad[index] ^= 1 << (random.next() % 8);
+
+    assert!(MavMessage::parse(&bad).is_err(), "corrupt byte {} of {:02x?} accepted", index, frame);
+}
+
+// ---------------------------------------------------------------------------
+
+#[test]
+fn golden_heartbeat() {
+    let mut message = msg0::Message::new();
+
+    assert_eq!(message.serialise(&mut link()), &HEARTBEAT[..]);
+
+    match parse(&HEARTBEAT) {
+        MavMessage::Heartbeat(message) => {
+            assert_eq!(message.custom_mode, 0);
+            assert_eq!(message.type_, 27);
+            assert_eq!(message.autopilot, 8);
+            assert_eq!(message.base_mode, 0);
+            assert_eq!(message.system_status, 4);
+            assert_eq!(message.mavlink_version, 3);
+        }
+        _ => panic!("not a heartbeat"),
+    }
+}
+
+#[test]
+fn golden_param_requests() {
+    let mut read = msg20::Message::new();
+
+    read.target_system = 0x19;
+    read.target_component = 0x59;
+    read.set_param_id("ADSB_ICAO");
+
+    assert_eq!(read.serialise(&mut link()), &PARAM_REQUEST_READ[..]);
+
+    match parse(&PARAM_REQUEST_READ) {
+        MavMessage::ParamRequestRead(message) => {
+            assert_eq!(message.param_index, -1);
+            assert_eq!(message.get_param_id(), "ADSB_ICAO");
+        },
+        _ => panic!("not a parameter read request"),
+    }
+
+    let mut list = msg21::Message::new();
+
+    list.target_system = 0x19;
+    list.target_component = 0x59;
+
+    assert_eq!(list.serialise(&mut link()), &PARAM_REQUEST_LIST[..]);
+
+    match parse(&PARAM_REQUEST_LIST) {
+        MavMessage::ParamRequestList(message) => assert_eq!((message.target_system, message.target_component), (0x19, 0x59)),
+        _ => panic!("not a parameter list request"),
+    }
+}
+
+#[test]
+fn golden_param_value() {
+    let mut message = msg22::Message::new();
+
+    message.set_value("ADSB_RANGE", 5000.0, msg22::MAV_PARAM_TYPE_REAL32, 3, 11);
+
+    assert_eq!(message.serialise(&mut link()), &PARAM_VALUE[..]);
+
+    match parse(&PARAM_VALUE) {
+        MavMessage::ParamValue(message) => {
+            assert_eq!(message.get_param_id(), "ADSB_RANGE");
+            assert_eq!((message.param_value, message.param_index, message.param_count), (5000.0, 3, 11));
+        },
+        _ => panic!("not a parameter value"),
+    }
+
+    // a 16 character id has no null
+    message.set_param_id("ABCDEFGHIJKLMNOPQ");
+
+    assert_eq!(&message.param_id, b"ABCDEFGHIJKLMNOP");
+    assert_eq!(message.get_param_id(), "ABCDEFGHIJKLMNOP");
+}
+
+#[test]
+fn golden_param_set() {
+    let mut message = msg23::Message::new();
+
+    message.target_system = 0x19;
+    message.target_component = 0x59;
+    message.param_value = 0x123456 as f32;
+    message.param_type = msg22::MAV_PARAM_TYPE_UINT32;
+    message.set_param_id("ADSB_ICAO");
+
+    assert_eq!(message.serialise(&mut link()), &PARAM_SET[..]);
+
+    match parse(&PARAM_SET) {
+        MavMessage::ParamSet(message) => {
+            assert_eq!(message.get_param_id(), "ADSB_ICAO");
+            assert_eq!(message.param_value as u32, 0x123456);
+        },
+        _ => panic!("not a parameter set"),
+    }
+}
+
+#[test]
+fn golden_gps_raw_int() {
+    let mut message = msg24::Message::new();
+
+    message.time_usec = 1500000000000000;
+    message.fix_type = 3;
+    message.lat = 510000000;
+    message.lon = -10000000;
+    message.alt = 150000;
+    message.eph = 120;
+    message.epv = 200;
+    message.vel = 1000;
+    message.cog = 9000;
+    message.satellites_visible = 10;
+
+    assert_eq!(message.serialise(&mut link()), &GPS_RAW_INT[..]);
+
+    match parse(&GPS_RAW_INT) {
+        MavMessage::GpsRaw(message) => {
+            assert_eq!(message.time_usec, 1500000000000000);
+            assert_eq!((message.fix_type, message.satellites_visible), (3, 10));
+            assert_eq!((message.lat, message.lon, message.alt), (510000000, -10000000, 150000));
+            assert_eq!((message.eph, message.epv, message.vel, message.cog), (120, 200, 1000, 9000));
+        }
+        _ => panic!("not a raw GPS"),
+    }
+}
+
+#[test]
+fn golden_global_position_int() {
+    let mut message = msg33::Message::new();
+
+    message.time_boot_ms = 1000;
+    message.lat = 510000000;
+    message.lon = -10000000;
+    message.alt = 150000;
+    message.relative_alt = 50000;
+    message.vx = 1000;
+    message.vy = -250;
+    message.vz = 50;
+    message.hdg = 34500;
+
+    assert_eq!(message.serialise(&mut link()), &GLOBAL_POSITION_INT[..]);
+
+    match parse(&GLOBAL_POSITION_INT) {
+        MavMessage::GlobalPosition(message) => {
+            assert_eq!(message.time_boot_ms, 1000);
+            assert_eq!((message.lat, message.lon), (510000000, -10000000));
+            assert_eq!((message.alt, message.relative_alt), (150000, 50000));
+            assert_eq!((message.vx, message.vy, message.vz, message.hdg), (1000, -250, 50, 34500));
+        }
+        _ => panic!("not a global position"),
+    }
+}
+
+#[test]
+fn golden_mission_upload() {
+    let mut count = msg44::Message::new();
+
+    count.target_system = 0x19;
+    count.target_component = 0x59;
+    count.count = 3;
+
+    assert_eq!(count.serialise(&mut link()), &MISSION_COUNT[..]);
+
+    match parse(&MISSION_COUNT) {
+        MavMessage::MissionCount(message) => assert_eq!(message.count, 3),
+        _ => panic!("not a mission count"),
+    }
+
+    assert_eq!(msg51::Message::new().set_request((0xff, 0xbe), 2).serialise(&mut link()), &MISSION_REQUEST_INT[..]);
+
+    match parse(&MISSION_REQUEST_INT) {
+        MavMessage::MissionRequest(message) => assert_eq!((message.target_system, message.target_component, message.seq), (0xff, 0xbe, 2)),
+        _ => panic!("not a mission request"),
+    }
+
+    assert_eq!(msg47::Message::new().set_result((0xff, 0xbe), msg47::MAV_MISSION_ACCEPTED).serialise(&mut link()), &MISSION_ACK[..]);
+
+    match parse(&MISSION_ACK) {
+        MavMessage::MissionAck(message) => assert_eq!(message.type_, msg47::MAV_MISSION_ACCEPTED),
+        _ => panic!("not a mission acknowledgement"),
+    }
+}
+
+#[test]
+fn golden_request_data_stream() {
+    let mut message = msg66::Message::new();
+
+    message.req_message_rate = 4;
+    message.target_system = 1;
+    message.target_component = 1;
+    message.req_stream_id = 0;
+    message.start_stop = 1;
+
+    assert_eq!(message.serialise(&mut link()), &REQUEST_DATA_STREAM[..]);
+
+    match parse(&REQUEST_DATA_STREAM) {
+        MavMessage::DataStreamRequest(message) => {
+            assert_eq!(message.req_message_rate, 4);
+            assert_eq!(message.target_system, 1);
+            assert_eq!(message.target_component, 1);
+            assert_eq!(message.req_stream_id, 0);
+            assert_eq!(message.start_stop, 1);
+        }
+        _ => panic!("not a data stream request"),
+    }
+}
+
+#[test]
+fn golden_mission_item_int() {
+    let mut message = msg73::Message::new();
+
+    message.target_system = 0x19;
+    message.target_component = 0x59;
+    message.seq = 1;
+    message.frame = msg73::MAV_FRAME_GLOBAL_RELATIVE_ALT_INT;
+    message.command = msg73::MAV_CMD_NAV_WAYPOINT;
+    message.autocontinue = 1;
+    message.x = 515000000;
+    message.y = -10000000;
+    message.z = 120.0;
+
+    assert_eq!(message.serialise(&mut link()), &MISSION_ITEM_INT[..]);
+
+    match parse(&MISSION_ITEM_INT) {
+        MavMessage::MissionItem(message) => {
+            let mut cwithv = CwithV::new();
+
+            message.get_cwithv(&mut cwithv);
+
+            assert!(message.is_global() && message.is_relative());
+            assert_eq!((message.seq, message.command), (1, 16));
+            assert!((cwithv.get_latitude() - 51.5).abs() < 1.0e-5 && (cwithv.get_longitude() + 1.0).abs() < 1.0e-5);
+            assert_eq!(cwithv.get_altitude(), 120.0);
+        },
+        _ => panic!("not a mission item"),
+    }
+}
+
+#[test]
+fn golden_command_long() {
+    let mut message = msg76::Message::new();
+
+    message.target_system = 0x19;
+    message.target_component = 0x59;
+    message.command = msg76::MAV_CMD_SET_MESSAGE_INTERVAL;
+    message.param1 = 246.0;
+    message.param2 = 200000.0;
+
+    assert_eq!(message.serialise(&mut link()), &COMMAND_LONG[..]);
+
+    match parse(&COMMAND_LONG) {
+        MavMessage::CommandLong(message) => {
+            assert_eq!((message.target_system, message.target_component), (0x19, 0x59));
+            assert_eq!((message.command, message.confirmation), (511, 0));
+            assert_eq!((message.param1, message.param2, message.param7), (246.0, 200000.0, 0.0));
+        }
+        _ => panic!("not a command"),
+    }
+}
+
+#[test]
+fn golden_command_ack() {
+    let mut message = msg77::Message::new();
+
+    message.set_result(msg76::MAV_CMD_SET_MESSAGE_INTERVAL, msg77::MAV_RESULT_ACCEPTED);
+
+    assert_eq!(message.serialise(&mut link()), &COMMAND_ACK[..]);
+
+    match parse(&COMMAND_ACK) {
+        MavMessage::CommandAck(message) => assert_eq!((message.command, message.result), (511, 0)),
+        _ => panic!("not a command acknowledgement"),
+    }
+}
+
+#[test]
+fn golden_set_position_target_local_ned() {
+    let mut message = msg84::Message::new();
+
+    message.time_boot_ms = 1000;
+    message.x = 1.5;
+    message.y = -2.5;
+    message.z = -10.0;
+    message.vx = 1.0;
+    message.vy = 2.0;
+    message.vz = -0.5;
+    message.yaw = 0.25;
+    message.type_mask = 0x0dc7;
+    message.target_system = 25;
+    message.target_component = 89;
+    message.coordinate_frame = 1;
+
+    assert_eq!(message.serialise(&mut link()), &SET_POSITION_TARGET_LOCAL_NED[..]);
+
+    match parse(&SET_POSITION_TARGET_LOCAL_NED) {
+        MavMessage::SetTargetPosition(message) => {
+            assert_eq!(message.time_boot_ms, 1000);
+            assert_eq!((message.x, message.y, message.z), (1.5, -2.5, -10.0));
+            assert_eq!((message.vx, message.vy, message.vz), (1.0, 2.0, -0.5));
+            assert_eq!((message.afx, message.afy, message.afz), (0.0, 0.0, 0.0));
+            assert_eq!((message.yaw, message.yaw_rate), (0.25, 0.0));
+            assert_eq!(message.type_mask, 0x0dc7);
+            assert_eq!(message.target_system, 25);
+            assert_eq!(message.target_component, 89);
+            assert_eq!(message.coordinate_frame, 1);
+        }
+        _ => panic!("not a set target position"),
+    }
+}
+
+#[test]
+fn golden_ownship() {
+    let mut message = msg202::Message::new();
+
+    message.utctime = 1500000000;
+    message.latitude = 510000000;
+    message.longitude = -10000000;
+    message.altpres = 150000;
+    message.altgnss = 152000;
+    message.acchoriz = 10;
+    message.accvert = 3;
+    message.accvel = 1;
+    message.velvert = -50;
+    message.nsvog = 1000;
+    message.ewvog = -250;
+    message.state = 0;
+    message.squawk = 1200;
+
+    assert_eq!(message.serialise(&mut link()), &OWNSHIP[..]);
+
+    match parse(&OWNSHIP) {
+        MavMessage::Ownship(message) => {
+            assert_eq!(message.utctime, 1500000000);
+            assert_eq!((message.latitude, message.longitude), (510000000, -10000000));
+            assert_eq!((message.altpres, message.altgnss), (150000, 152000));
+            assert_eq!((message.acchoriz, message.accvert, message.accvel), (10, 3, 1));
+            assert_eq!((message.velvert, message.nsvog, message.ewvog), (-50, 1000, -250));
+            assert_eq!((message.state, message.squawk), (0, 1200));
+            assert_eq!((message.fixtype, message.numsats, message.emstatus, message.control), (4, 7, 0, 3));
+        }
+        _ => panic!("not an ownship"),
+    }
+}
+
+#[test]
+fn golden_status() {
+    let mut message = msg203::Message::new();
+
+    assert_eq!(message.serialise(&mut link()), &STATUS[..]);
+
+    match parse(&STATUS) {
+        MavMessage::Status(message) => assert_eq!(message.status, 1),
+        _ => panic!("not a status"),
+    }
+}
+
+#[test]
+fn golden_adsb_vehicle() {
+    let mut message = msg246::Message::new();
+
+    message.icao_address = 0x00300159;
+    message.lat = 510000000;
+    message.lon = -10000000;
+    message.altitude = 150000;
+    message.heading = 9000;
+    message.hor_velocity = 1000;
+    message.ver_velocity = -50;
+    message.flags = 0x001f;
+    message.squawk = 1200;
+    message.altitude_type = 1;
+    message.emitter_type = 14;
+    message.tslc = 1;
+
+    assert_eq!(message.serialise(&mut link()), &ADSB_VEHICLE[..]);
+
+    match parse(&ADSB_VEHICLE) {
+        MavMessage::TrafficReport(message) => {
+            assert_eq!(message.icao_address, 0x00300159);
+            assert_eq!((message.lat, message.lon, message.altitude), (510000000, -10000000, 150000));
+            assert_eq!((message.heading, message.hor_velocity, message.ver_velocity), (9000, 1000, -50));
+            assert_eq!((message.flags, message.squawk), (0x001f, 1200));
+            assert_eq!(message.altitude_type, 1);
+            assert_eq!(&message.callsign, b"D-RisQ\0\0\0");
+            assert_eq!((message.emitter_type, message.tslc), (14, 1));
+        }
+        _ => panic!("not an ADS-B vehicle"),
+    }
+}
+
+#[test]
+fn golden_uavionix_adsb_out_cfg() {
+    let mut message = msg10001::Message::new();
+
+    message.icao = 0xabc123;
+    message.stall_speed = 2500;
+    message.set_callsign("DRISQ1");
+    message.emitter_type = 14;
+    message.aircraft_size = 1;
+    message.gps_offset_lat = 2;
+    message.gps_offset_lon = 3;
+    message.rf_select = 1;
+
+    assert_eq!(message.serialise(&mut link()), &UAVIONIX_ADSB_OUT_CFG[..]);
+
+    match parse(&UAVIONIX_ADSB_OUT_CFG) {
+        MavMessage::TransponderConfig(message) => {
+            assert_eq!((message.get_icao(), message.get_callsign()), (0xabc123, "DRISQ1".to_string()));
+            assert_eq!((message.stall_speed, message.emitter_type, message.aircraft_size), (2500, 14, 1));
+            assert_eq!((message.gps_offset_lat, message.gps_offset_lon, message.rf_select), (2, 3, 1));
+        }
+        _ => panic!("not an ADS-B OUT configuration"),
+    }
+}
+
+#[test]
+fn golden_uavionix_adsb_out_dynamic() {
+    let mut message = msg10002::Message::new();
+
+    message.utc_time = 1200000000;
+    message.gps_lat = 510000000;
+    message.gps_lon = -10000000;
+    message.gps_alt = 150000;
+    message.baro_alt_msl = 1013;
+    message.accuracy_hor = 5000;
+    message.accuracy_vert = 300;
+    message.accuracy_vel = 100;
+    message.vel_vert = -50;
+    message.vel_ns = 1000;
+    message.vel_ew = -250;
+    message.state = 3;
+    message.squawk = 0x1234;
+    message.gps_fix = 3;
+    message.num_sats = 10;
+
+    assert_eq!(message.serialise(&mut link()), &UAVIONIX_ADSB_OUT_DYNAMIC[..]);
+
+    for frame in [&UAVIONIX_ADSB_OUT_DYNAMIC[..], &UAVIONIX_ADSB_OUT_DYNAMIC_TRUNCATED[..]].iter() {
+        match parse(frame) {
+            MavMessage::TransponderDynamic(message) => {
+                assert_eq!(message.utc_time, 1200000000);
+                assert_eq!((message.gps_lat, message.gps_lon, message.gps_alt), (510000000, -10000000, 150000));
+                assert_eq!((message.baro_alt_msl, message.accuracy_hor), (1013, 5000));
+                assert_eq!((message.accuracy_vert, message.accuracy_vel), (300, 100));
+                assert_eq!((message.vel_vert, message.vel_ns, message.vel_ew), (-50, 1000, -250));
+                assert_eq!((message.state, message.squawk), (3, 0x1234));
+                assert_eq!((message.gps_fix, message.num_sats, message.emergency_status), (3, 10, 0));
+            }
+            _ => panic!("not an ADS-B OUT dynamic"),
+        }
+    }
+
+    assert_eq!(mavlink::to_v2(&UAVIONIX_ADSB_OUT_DYNAMIC, 0).unwrap(), UAVIONIX_ADSB_OUT_DYNAMIC.to_vec());
+}
+
+#[test]
+fn golden_uavionix_adsb_transceiver_health_report() {
+    let mut message = msg10003::Message::new();
+
+    assert_eq!(message.serialise(&mut link()), &UAVIONIX_ADSB_TRANSCEIVER_HEALTH_REPORT[..]);
+
+    match parse(&UAVIONIX_ADSB_TRANSCEIVER_HEALTH_REPORT) {
+        MavMessage::TransponderHealth(message) => assert_eq!(message.rf_health, msg10003::RF_HEALTH_OK),
+        _ => panic!("not a transceiver health report"),
+    }
+}
+
+// ---------------------------------------------------------------------------
+
+#[test]
+fn round_trip_heartbeat() {
+    let mut random = Random::new(0);
+    let mut link = Link::new();
+
+    for _ in 0 .. ROUNDS {
+        let mut message = msg0::Message::new();
+
+        message.custom_mode = random.u32();
+        message.type_ = random.u8();
+        message.autopilot = random.u8();
+        message.base_mode = random.u8();
+        message.system_status = random.u8();
+        message.mavlink_version = random.u8();
+
+        let frame = message.serialise(&mut link).to_vec();
+
+        match parse(&frame) {
+            MavMessage::Heartbeat(copy) => {
+                assert_eq!(copy.custom_mode, message.custom_mode);
+                assert_eq!(copy.type_, message.type_);
+                assert_eq!(copy.autopilot, message.autopilot);
+                assert_eq!(copy.base_mode, message.base_mode);
+                assert_eq!(copy.system_status, message.system_status);
+                assert_eq!(copy.mavlink_version, message.mavlink_version);
+            }
+            _ => panic!("not a heartbeat"),
+        }
+
+        corrupt(&frame, &mut random);
+    }
+}
+
+#[test]
+fn round_trip_request_data_stream() {
+    let mut random = Random::new(66);
+    let mut link = Link::new();
+
+    for _ in 0 .. ROUNDS {
+        let mut message = msg66::Message::new();
+
+        message.req_message_rate = random.u16();
+        message.target_system = random.u8();
+        message.target_component = random.u8();
+        message.req_stream_id = random.u8();
+        message.start_stop = random.u8();
+
+        let frame = message.serialise(&mut link).to_vec();
+
+        match parse(&frame) {
+            MavMessage::DataStreamRequest(copy) => {
+                assert_eq!(copy.req_message_rate, message.req_message_rate);
+                assert_eq!(copy.target_system, message.target_system);
+                assert_eq!(copy.target_component, message.target_component);
+                assert_eq!(copy.req_stream_id, message.req_stream_id);
+                assert_eq!(copy.start_stop, message.start_stop);
+            }
+            _ => panic!("not a data stream request"),
+        }
+
+        corrupt(&frame, &mut random);
+    }
+}
+
+#[test]
+fn round_trip_set_position_target_local_ned() {
+    let mut random = Random::new(84);
+    let mut link = Link::new();
+
+    for _ in 0 .. ROUNDS {
+        let mut message = msg84::Message::new();
+
+        message.time_boot_ms = random.u32();
+        message.x = random.f32();
+        message.y = random.f32();
+        message.z = random.f32();
+        message.vx = random.f32();
+        message.vy = random.f32();
+        message.vz = random.f32();
+        message.afx = random.f32();
+        message.afy = random.f32();
+        message.afz = random.f32();
+        message.yaw = random.f32();
+        message.yaw_rate = random.f32();
+        message.type_mask = random.u16();
+        message.target_system = random.u8();
+        message.target_component = random.u8();
+        message.coordinate_frame = random.u8();
+
+        let frame = message.serialise(&mut link).to_vec();
+
+        match parse(&frame) {
+            MavMessage::SetTargetPosition(copy) => {
+                assert_eq!(copy.time_boot_ms, message.time_boot_ms);
+                assert_eq!(copy.x.to_bits(), message.x.to_bits());
+                assert_eq!(copy.y.to_bits(), message.y.to_bits());
+                assert_eq!(copy.z.to_bits(), message.z.to_bits());
+                assert_eq!(copy.vx.to_bits(), message.vx.to_bits());
+                assert_eq!(copy.vy.to_bits(), message.vy.to_bits());
+                assert_eq!(copy.vz.to_bits(), message.vz.to_bits());
+                assert_eq!(copy.afx.to_bits(), message.afx.to_bits());
+                assert_eq!(copy.afy.to_bits(), message.afy.to_bits());
+                assert_eq!(copy.afz.to_bits(), message.afz.to_bits());
+                assert_eq!(copy.yaw.to_bits(), message.yaw.to_bits());
+                assert_eq!(copy.yaw_rate.to_bits(), message.yaw_rate.to_bits());
+                assert_eq!(copy.type_mask, message.type_mask);
+                assert_eq!(copy.target_system, message.target_system);
+                assert_eq!(copy.target_component, message.target_component);
+                assert_eq!(copy.coordinate_frame, message.coordinate_frame);
+            }
+            _ => panic!("not a set target position"),
+        }
+
+        corrupt(&frame, &mut random);
+    }
+}
+
+#[test]
+fn round_trip_ownship() {
+    let mut random = Random::new(202);
+    let mut link = Link::new();
+
+    for _ in 0 .. ROUNDS {
+        let mut message = msg202::Message::new();
+
+        message.utctime = random.u32();
+        message.latitude = random.i32();
+        message.longitude = random.i32();
+        message.altpres = random.i32();
+        message.altgnss = random.i32();
+        message.acchoriz = random.u32();
+        message.accvert = random.u16();
+        message.accvel = random.u16();
+        message.velvert = random.i16();
+        message.nsvog = random.i16();
+        message.ewvog = random.i16();
+        message.state = random.u16();
+        message.squawk = random.u16();
+        message.fixtype = random.u8();
+        message.numsats = random.u8();
+        message.emstatus = random.u8();
+        message.control = random.u8();
+
+        let frame = message.serialise(&mut link).to_vec();
+
+        match parse(&frame) {
+            MavMessage::Ownship(copy) => {
+                assert_eq!(copy.utctime, message.utctime);
+                assert_eq!(copy.latitude, message.latitude);
+                assert_eq!(copy.longitude, message.longitude);
+                assert_eq!(copy.altpres, message.altpres);
+                assert_eq!(copy.altgnss, message.altgnss);
+                assert_eq!(copy.acchoriz, message.acchoriz);
+                assert_eq!(copy.accvert, message.accvert);
+                assert_eq!(copy.accvel, message.accvel);
+                assert_eq!(copy.velvert, message.velvert);
+                assert_eq!(copy.nsvog, message.nsvog);
+                assert_eq!(copy.ewvog, message.ewvog);
+                assert_eq!(copy.state, message.state);
+                assert_eq!(copy.squawk, message.squawk);
+                assert_eq!(copy.fixtype, message.fixtype);
+                assert_eq!(copy.numsats, message.numsats);
+                assert_eq!(copy.emstatus, message.emstatus);
+                assert_eq!(copy.control, message.control);
+            }
+            _ => panic!("not an ownship"),
+        }
+
+        corrupt(&frame, &mut random);
+    }
+}
+
+#[test]
+fn round_trip_status() {
+    let mut random = Random::new(203);
+    let mut link = Link::new();
+
+    for _ in 0 .. ROUNDS {
+        let mut message = msg203::Message::new();
+
+        message.status = random.u8();
+
+        let frame = message.serialise(&mut link).to_vec();
+
+        match parse(&frame) {
+            MavMessage::Status(copy) => assert_eq!(copy.status, message.status),
+            _ => panic!("not a status"),
+        }
+
+        corrupt(&frame, &mut random);
+    }
+}
+
+#[test]
+fn round_trip_adsb_vehicle() {
+    let mut random = Random::new(246);
+    let mut link = Link::new();
+
+    for _ in 0 .. ROUNDS {
+        let mut message = msg246::Message::new();
+
+        message.icao_address = random.u32();
+        message.lat = random.i32();
+        message.lon = random.i32();
+        message.altitude = random.i32();
+        message.heading = random.u16();
+        message.hor_velocity = random.u16();
+        message.ver_velocity = random.i16();
+        message.flags = random.u16();
+        message.squawk = random.u16();
+        message.altitude_type = random.u8();
+        for ii in 0 .. message.callsign.len() {
+            message.callsign[ii] = random.u8();
+        }
+        message.emitter_type = random.u8();
+        message.tslc = random.u8();
+
+        let frame = message.serialise(&mut link).to_vec();
+
+        match parse(&frame) {
+            MavMessage::TrafficReport(copy) => {
+                assert_eq!(copy.icao_address, message.icao_address);
+                assert_eq!(copy.lat, message.lat);
+                assert_eq!(copy.lon, message.lon);
+                assert_eq!(copy.altitude, message.altitude);
+                assert_eq!(copy.heading, message.heading);
+                assert_eq!(copy.hor_velocity, message.hor_velocity);
+                assert_eq!(copy.ver_velocity, message.ver_velocity);
+                assert_eq!(copy.flags, message.flags);
+                assert_eq!(copy.squawk, message.squawk);
+                assert_eq!(copy.altitude_type, message.altitude_type);
+                assert_eq!(copy.callsign, message.callsign);
+                assert_eq!(copy.emitter_type, message.emitter_type);
+                assert_eq!(copy.tslc, message.tslc);
+            }
+            _ => panic!("not an ADS-B vehicle"),
+        }
+
+        corrupt(&frame, &mut random);
+    }
+}
+
+#[test]
+fn round_trip_uavionix_adsb_out_cfg() {
+    let mut random = Random::new(10001);
+    let mut link = Link::new();
+
+    for _ in 0 .. ROUNDS {
+        let mut message = msg10001::Message::new();
+
+        message.icao = random.u32();
+        message.stall_speed = random.u16();
+        for ii in 0 .. message.callsign.len() {
+            message.callsign[ii] = random.u8();
+        }
+        message.emitter_type = random.u8();
+        message.aircraft_size = random.u8();
+        message.gps_offset_lat = random.u8();
+        message.gps_offset_lon = random.u8();
+        message.rf_select = random.u8();
+
+        let frame = message.serialise(&mut link).to_vec();
+
+        // as sent and with any trailing zeros dropped
+        for frame in [frame.clone(), mavlink::to_v2(&frame, 0).unwrap()].iter() {
+            match parse(frame) {
+                MavMessage::TransponderConfig(copy) => {
+                    assert_eq!(copy.icao, message.icao);
+                    assert_eq!(copy.stall_speed, message.stall_speed);
+                    assert_eq!(copy.callsign, message.callsign);
+                    assert_eq!(copy.emitter_type, message.emitter_type);
+                    assert_eq!(copy.aircraft_size, message.aircraft_size);
+                    assert_eq!(copy.gps_offset_lat, message.gps_offset_lat);
+                    assert_eq!(copy.gps_offset_lon, message.gps_offset_lon);
+                    assert_eq!(copy.rf_select, message.rf_select);
+                }
+                _ => panic!("not an ADS-B OUT configuration"),
+            }
+
+            corrupt(frame, &mut random);
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+
+#[test]
+fn short_and_unknown_frames_rejected() {
+    assert!(MavMessage::parse(&HEARTBEAT[.. 7]).is_err());
+    assert!(MavMessage::parse(&[]).is_err());
+
+    let mut unknown = STATUS;
+
+    unknown[5] = 250;
+
+    assert!(MavMessage::parse(&unknown).is_err());
+
+    assert!(MavMessage::parse(&UAVIONIX_ADSB_OUT_CFG[.. 20]).is_err());
+
+    let mut unknown = UAVIONIX_ADSB_TRANSCEIVER_HEALTH_REPORT;
+
+    unknown[9] = 1;
+
+    assert!(MavMessage::parse(&unknown).is_err());
+}
+
+// ---------------------------------------------------------------------------
+
+#[test]
+fn json_adsb_vehicle() {
+    let text = json::render(&ADSB_VEHICLE).unwrap();
+
+    assert!(text.starts_with("{\"msgid\":246,\"msgname\":\"ADSB_VEHICLE\","));
+    assert!(text.contains("\"ICAO_address\":3146073,\"lat\":51,\"lon\":-1,\"altitude_type\":1,\"altitude\":150,"));
+    assert!(text.contains("\"heading\":90,\"hor_velocity\":10,\"ver_velocity\":-0.5,"));
+    assert!(text.contains("\"callsign\":\"D-RisQ\","));
+    assert!(text.contains("\"units\":{\"lat\":\"deg\",\"lon\":\"deg\",\"altitude\":\"m\",\"heading\":\"deg\","));
+    assert!(text.ends_with("\"tslc\":\"s\"}}"));
+}
+
+#[test]
+fn json_ownship_and_status() {
+    let text = json::render(&OWNSHIP).unwrap();
+
+    assert!(text.starts_with("{\"msgid\":202,\"msgname\":\"OWNSHIP\","));
+    assert!(text.contains("\"fields\":{\"utctime\":1500000000,\"latitude\":51,\"longitude\":-1,\"altpres\":150,\"altgnss\":152,"));
+    assert!(text.contains("\"acchoriz\":0.01,\"accvert\":0.03,\"accvel\":0.001,"));
+    assert!(text.contains("\"acchoriz\":\"m\",\"accvert\":\"m\",\"accvel\":\"m/s\","));
+    assert!(text.contains("\"velvert\":-0.5,\"nsvog\":10,\"ewvog\":-2.5,"));
+    assert!(text.contains("\"squawk\":1200,"));
+
+    assert!(json::render(&STATUS).unwrap().contains("\"fields\":{\"status\":1},\"units\":{}}"));
+    assert!(json::render(&HEARTBEAT[.. 7]).is_err());
+}
+
+#[test]
+fn json_uavionix_adsb_out_cfg() {
+    let text = json::render(&UAVIONIX_ADSB_OUT_CFG).unwrap();
+
+    assert!(text.starts_with("{\"msgid\":10001,\"msgname\":\"UAVIONIX_ADSB_OUT_CFG\",\"seqno\":0,\"sysid\":1,\"compid\":1,"));
+    assert!(text.contains("\"fields\":{\"ICAO\":11256099,\"callsign\":\"DRISQ1\",\"emitterType\":14,"));
+    assert!(text.contains("\"stallSpeed\":25,\"rfSelect\":1},\"units\":{\"stallSpeed\":\"m/s\"}}"));
+}
+
+// EOF
@@ -0,0 +1,224 @@
+//! ADS-B Simulator - see README.md
+//
+// © NewForester, 2018.  Available under MIT licence terms.
+//
+//! Tests of the parsing of the MQTT client parameters and of the choice of QoS,
+//! retain flag and last-will message they configure, of the reconnection
+//! backoff, of what happens to messages published while disconnected and of
+//! topic templates.
+//!
+extern crate adsbsim;
+
+use std::io::ErrorKind;
+use std::time::Duration;
+
+use adsbsim::mqtt::{Backoff, Client, Fields, State};
+
+// ---------------------------------------------------------------------------
+
+fn client(cli: &str) -> Client {
+    let mut client = Client::new();
+
+    client.set_cli(cli).unwrap();
+
+    client
+}
+
+// ---------------------------------------------------------------------------
+
+#[test]
+fn qos_by_default_and_by_topic() {
+    let mut client = client("sim,,/300159:/151060/202");
+
+    assert_eq!(client.get_qos("/300159/202", 0), 0);
+    assert_eq!(client.get_qos("/151060/202", 1), 1);
+
+    client.set_qos_cli("/300159/246:2,/+/202:1").unwrap();
+
+    assert_eq!(client.get_qos("/300159/246", 0), 2);
+    assert_eq!(client.get_qos("/300159/202", 0), 1);
+    assert_eq!(client.get_qos("/151060/202", 0), 1);
+    assert_eq!(client.get_qos("/300159/0", 0), 0);
+
+    // the first match wins
+    client.set_qos_cli("#:0").unwrap();
+
+    assert_eq!(client.get_qos("/300159/246", 1), 2);
+    assert_eq!(client.get_qos("/300159/0", 1), 0);
+}
+
+#[test]
+fn bad_qos_rejected() {
+    let mut client = Client::new();
+
+    assert!(client.set_qos_cli("3").is_err());
+    assert!(client.set_qos_cli("/300159/246:x").is_err());
+    assert!(client.set_qos_cli(":1").is_err());
+    assert!(client.set_qos_cli("2").is_ok());
+}
+
+#[test]
+fn retained_topics() {
+    let mut client = client("sim,,/300159");
+
+    assert!(! client.get_retain("/300159/202"));
+
+    client.set_retain_cli("/300159/202,/+/246").unwrap();
+
+    assert!(client.get_retain("/300159/202"));
+    assert!(client.get_retain("/300159/246"));
+    assert!(! client.get_retain("/300159/0"));
+
+    assert!(client.set_retain_cli("/300159/0,").is_err());
+}
+
+#[test]
+fn last_will() {
+    let mut client = client("sim,,/300159");
+
+    assert!(client.get_will().is_none());
+
+    client.set_will_cli("").unwrap();
+
+    assert_eq!(client.get_will(), Some(("/300159/status".to_string(), "offline")));
+
+    client.set_will_cli("devices/300159:gone").unwrap();
+
+    assert_eq!(client.get_will(), Some(("devices/300159".to_string(), "gone")));
+}
+
+#[test]
+fn credentials_and_tls() {
+    let mut client = Client::new();
+
+    assert!(client.set_auth_cli("sim:pass:word").is_ok());
+    assert!(client.set_auth_cli(":password").is_err());
+
+    assert!(client.set_tls_cli("ca.crt").is_ok());
+    assert!(client.set_tls_cli("ca.crt,client.crt,client.key").is_ok());
+    assert!(client.set_tls_cli("ca.crt,client.crt").is_err());
+    assert!(client.set_tls_cli("").is_err());
+}
+
+#[test]
+fn backoff_doubles_up_to_maximum() {
+    let mut backoff = Backoff::new(Duration::new(1, 0), Duration::new(10, 0));
+
+    let delays: Vec<u64> = (0 .. 6).map(|_| backoff.next_delay().as_secs()).collect();
+
+    assert_eq!(delays, vec![1, 2, 4, 8, 10, 10]);
+
+    backoff.reset();
+
+    assert_eq!(backoff.next_delay().as_secs(), 1);
+}
+
+#[test]
+fn bad_retry_rejected() {
+    let mut client = Client::new();
+
+    assert!(client.set_retry_cli("2:60").is_ok());
+    assert!(client.set_retry_cli("0:60").is_err());
+    assert!(client.set_retry_cli("60:2").is_err());
+    assert!(client.set_retry_cli("2").is_err());
+    assert!(client.set_buffer_cli("x").is_err());
+}
+
+#[test]
+fn disconnected_messages_dropped() {
+    let mut client = client("sim,,/300159");
+
+    assert_eq!(client.get_state(), State::Disconnected);
+
+    let e = client.publish(b"\xfe", "/202").unwrap_err();
+
+    assert_eq!(e.kind(), ErrorKind::NotConnected);
+    assert_eq!(client.get_dropped(), 1);
+}
+
+#[test]
+fn disconnected_messages_buffered() {
+    let mut client = client("sim,,/300159");
+
+    client.set_buffer_cli("2").unwrap();
+
+    for _ in 0 .. 5 {
+        assert_eq!(client.publish(b"\xfe", "/202").unwrap(), 0);
+    }
+
+    // only the two most recent are kept
+    assert_eq!(client.get_dropped(), 3);
+}
+
+#[test]
+fn default_topics() {
+    let client = client("sim,,/300159:/151060/202;/300159/84");
+
+    assert_eq!(client.get_topic(202, 25, 89), "/300159/202");
+    assert_eq!(client.get_icao(), Some(0x300159));
+    assert_eq!(client.get_ufo_icao(), Some(0x151060));
+
+    assert_eq!(client.parse_topic("/300159/84"), Some(Fields {icao: Some(0x300159), msgid: Some(84), ..Fields::default()}));
+    assert_eq!(client.parse_topic("/300159/84/x"), None);
+}
+
+#[test]
+fn templated_topics() {
+    let mut client = client("sim,,:fleet/lab/151060/mavlink/OWNSHIP;fleet/lab/+/mavlink/SET_POSITION_TARGET_LOCAL_NED");
+
+    client.set_template_cli("fleet/lab/{icao}/mavlink/{msgname}").unwrap();
+    client.set_icao_cli("300159").unwrap();
+
+    assert_eq!(client.get_topic(246, 25, 89), "fleet/lab/300159/mavlink/ADSB_VEHICLE");
+    assert_eq!(client.get_ufo_icao(), Some(0x151060));
+
+    // a wildcard says nothing, an unknown message name or a different hierarchy does not match
+    assert_eq!(client.parse_topic("fleet/lab/+/mavlink/SET_POSITION_TARGET_LOCAL_NED"),
+               Some(Fields {msgid: Some(84), ..Fields::default()}));
+    assert_eq!(client.parse_topic("fleet/lab/151060/mavlink/NO_SUCH_MESSAGE"), None);
+    assert_eq!(client.parse_topic("fleet/home/151060/mavlink/OWNSHIP"), None);
+
+    client.set_will_cli("").unwrap();
+
+    assert_eq!(client.get_will(), Some(("fleet/lab/300159/mavlink/status".to_string(), "offline")));
+}
+
+#[test]
+fn separate_templates() {
+    let mut client = client("sim,,out:in/151060/202");
+
+    client.set_template_cli("{pubtopic}/{sysid}/{compid}/{msgid};in/{icao}/{msgid}").unwrap();
+
+    assert_eq!(client.get_topic(0, 1, 2), "out/1/2/0");
+    assert_eq!(client.get_ufo_icao(), Some(0x151060));
+}
+
+#[test]
+fn bad_templates_rejected() {
+    let mut client = Client::new();
+
+    assert!(client.set_template_cli("fleet/{site}/{icao}/{msgid}").is_err());
+    assert!(client.set_template_cli("fleet/{icao/{msgid}").is_err());
+    assert!(client.set_template_cli("fleet/icao-{icao}/{msgid}").is_err());
+    assert!(client.set_template_cli("fleet/icao-{icao}/{msgid};fleet/{icao}/{msgid}").is_ok());
+    assert!(client.set_icao_cli("1000000").is_err());
+    assert!(client.set_icao_cli("abcdef").is_ok());
+}
+
+#[test]
+fn json_topics() {
+    let mut client = client("sim,,/300159:");
+
+    assert_eq!(client.get_json_topic(246, 25, 89), None);
+
+    client.set_json_cli("").unwrap();
+
+    assert_eq!(client.get_json_topic(246, 25, 89), Some("/300159/246/json".to_string()));
+
+    client.set_json_cli("json/{icao}/{msgname}").unwrap();
+
+    assert_eq!(client.get_json_topic(246, 25, 89), Some("json/300159/ADSB_VEHICLE".to_string()));
+    assert!(client.set_json_cli("json/{site}/{msgid}").is_err());
+}
+
+// EOF
@@ -0,0 +1,85 @@
+//! ADS-B Simulator - see README.md
+//
+// © NewForester, 2018.  Available under MIT licence terms.
+//
+//! Tests of the tracking of the MAVLink components heard from.
+//!
+extern crate adsbsim;
+
+use adsbsim::peers::{Heard, Peers};
+
+use std::time::{Duration, Instant};
+
+// ---------------------------------------------------------------------------
+
+// after() returns the instant the given number of milliseconds after another
+fn after(start: Instant, millis: u64) -> Instant {
+    start + Duration::from_millis(millis)
+}
+
+// ---------------------------------------------------------------------------
+
+#[test]
+fn first_heartbeat_reported_once() {
+    let mut peers = Peers::new();
+    let start = Instant::now();
+
+    assert_eq!(peers.heard(1, 1, 2, start), Some(Heard::First));
+    assert_eq!(peers.heard(1, 1, 2, after(start, 1000)), None);
+    assert_eq!(peers.heard(1, 1, 2, after(start, 2000)), None);
+
+    assert!(peers.check(after(start, 2500)).is_empty());
+}
+
+#[test]
+fn silence_reported_once_after_timeout() {
+    let mut peers = Peers::new();
+    let start = Instant::now();
+
+    peers.heard(1, 1, 2, start);
+
+    assert_eq!(peers.get_timeout(), Duration::new(3, 0));
+
+    assert!(peers.check(after(start, 3000)).is_empty());
+    assert_eq!(peers.check(after(start, 3001)), vec![(1, 1, 2)]);
+    assert!(peers.check(after(start, 4000)).is_empty());
+    assert!(peers.check(after(start, 60000)).is_empty());
+}
+
+#[test]
+fn recovery_reported_and_silence_again() {
+    let mut peers = Peers::new();
+    let start = Instant::now();
+
+    peers.heard(1, 1, 2, start);
+    peers.check(after(start, 5000));
+
+    assert_eq!(peers.heard(1, 1, 2, after(start, 6000)), Some(Heard::Resumed));
+    assert_eq!(peers.heard(1, 1, 2, after(start, 7000)), None);
+
+    assert!(peers.check(after(start, 9000)).is_empty());
+    assert_eq!(peers.check(after(start, 10500)), vec![(1, 1, 2)]);
+}
+
+#[test]
+fn peers_told_apart_by_system_and_component() {
+    let mut peers = Peers::new();
+    let start = Instant::now();
+
+    assert_eq!(peers.heard(1, 1, 2, start), Some(Heard::First));
+    assert_eq!(peers.heard(1, 190, 6, start), Some(Heard::First));
+    assert_eq!(peers.heard(255, 1, 6, start), Some(Heard::First));
+    assert_eq!(peers.heard(255, 190, 6, start), Some(Heard::First));
+
+    // only the ground station keeps talking
+    assert_eq!(peers.heard(255, 190, 6, after(start, 2000)), None);
+    assert_eq!(peers.heard(255, 190, 6, after(start, 4000)), None);
+
+    assert_eq!(peers.check(after(start, 5000)), vec![(1, 1, 2), (1, 190, 6), (255, 1, 6)]);
+
+    assert_eq!(peers.heard(1, 190, 6, after(start, 5500)), Some(Heard::Resumed));
+    assert_eq!(peers.heard(1, 1, 2, after(start, 5500)), Some(Heard::Resumed));
+    assert!(peers.check(after(start, 6000)).is_empty());
+}
+
+// EOF
@@ -0,0 +1,207 @@
+//! ADS-B Simulator - see README.md
+//
+// © NewForester, 2018.  Available under MIT licence terms.
+//
+//! Tests of MAVLink 2 framing and message signing.
+//!
+//! The golden signed message was produced independently of this crate with
+//! Python's hashlib the way the MAVLink reference implementation signs
+//! messages.  The SHA-256 vectors are those of FIPS 180-2.
+//!
+extern crate adsbsim;
+
+use std::time::{Duration, Instant};
+
+use adsbsim::mavlink;
+use adsbsim::mavlink::{Link, Message};
+use adsbsim::mavlink::{msg0, msg84, msg202, msg246, msg10003};
+use adsbsim::mavlink::signing::{self, Signing};
+
+// ---------------------------------------------------------------------------
+
+const HEARTBEAT: [u8; 17] = [
+    0xfe, 0x09, 0x00, 0x01, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x1b, 0x08, 0x00, 0x04, 0x03, 0xff,
+    0x34,
+];
+
+// HEARTBEAT signed with the passphrase 'secret', link id 1 and timestamp 0x0102030405
+const SIGNED_HEARTBEAT: [u8; 34] = [
+    0xfd, 0x09, 0x01, 0x00, 0x00, 0x01, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x1b, 0x08,
+    0x00, 0x04, 0x03, 0x82, 0x0f, 0x01, 0x05, 0x04, 0x03, 0x02, 0x01, 0x00, 0x5f, 0x92, 0xf1, 0x9d,
+    0x7c, 0xd7,
+];
+
+// ---------------------------------------------------------------------------
+
+fn signing(cli: &str, clock: Instant) -> Signing {
+    let mut signing = Signing::new();
+
+    signing.set_cli(cli).unwrap();
+    signing.set_epoch(clock, 0x0102030405);
+
+    signing
+}
+
+fn command() -> Vec<u8> {
+    let mut link = Link::new();
+    let mut command = msg84::Message::new();
+
+    link.set_source(1, 1);
+
+    command.vx = -10.0;
+
+    command.serialise(&mut link).to_vec()
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+// ---------------------------------------------------------------------------
+
+#[test]
+fn sha256_vectors() {
+    assert_eq!(hex(&signing::sha256(b"")), "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855");
+    assert_eq!(hex(&signing::sha256(b"abc")), "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+    assert_eq!(hex(&signing::sha256(b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq")),
+               "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1");
+}
+
+#[test]
+fn golden_signed_heartbeat() {
+    let clock = Instant::now();
+
+    assert_eq!(signing("secret:1", clock).sign(&HEARTBEAT, clock).unwrap(), SIGNED_HEARTBEAT.to_vec());
+
+    // the key of a passphrase is its hash
+    let key = hex(&signing::sha256(b"secret"));
+
+    assert_eq!(signing(&format!("{}:1", key), clock).sign(&HEARTBEAT, clock).unwrap(), SIGNED_HEARTBEAT.to_vec());
+
+    assert_eq!(signing("secret", clock).verify(&SIGNED_HEARTBEAT, clock).unwrap(), HEARTBEAT.to_vec());
+}
+
+#[test]
+fn mavlink2_round_trip() {
+    let mut link = Link::new();
+
+    let frames = [
+        msg0::Message::new().serialise(&mut link).to_vec(),
+        msg84::Message::new().serialise(&mut link).to_vec(),
+        msg202::Message::new().serialise(&mut link).to_vec(),
+        msg246::Message::new().serialise(&mut link).to_vec(),
+        command(),
+    ];
+
+    for frame in frames.iter() {
+        let v2 = mavlink::to_v2(frame, 0).unwrap();
+
+        // trailing zeros are not sent
+        assert!(v2.len() <= frame.len() + 4);
+        assert_eq!(mavlink::source(&v2), mavlink::source(frame));
+        assert_eq!(&mavlink::to_v1(&v2).unwrap(), frame);
+
+        let mut corrupt = v2.clone();
+
+        corrupt[10] ^= 0x01;
+
+        assert!(mavlink::to_v1(&corrupt).is_err());
+    }
+}
+
+#[test]
+fn mavlink2_only_messages_signed() {
+    let clock = Instant::now();
+
+    let mut sender = signing("secret:7", clock);
+    let mut receiver = signing("secret", clock);
+
+    let health = msg10003::Message::new().serialise(&mut Link::new()).to_vec();
+
+    let signed = sender.sign(&health, clock).unwrap();
+
+    assert_eq!(signed.len(), health.len() + 13);
+    assert_eq!(mavlink::msgid(&signed), Some(10003));
+    assert!(receiver.verify(&signed, clock).is_ok());
+    assert_eq!(&mavlink::to_v2(&signed, 0).unwrap(), &health);
+}
+
+#[test]
+fn signed_messages_verified() {
+    let clock = Instant::now();
+
+    let mut sender = signing("secret:7", clock);
+    let mut receiver = signing("secret", clock);
+    let mut stranger = signing("guessed", clock);
+
+    let signed = sender.sign(&command(), clock).unwrap();
+
+    assert!(stranger.verify(&signed, clock).is_err());
+    assert_eq!(receiver.verify(&signed, clock).unwrap(), command());
+
+    // no replay
+    assert!(receiver.verify(&signed, clock).is_err());
+
+    // no tampering
+    let mut tampered = sender.sign(&command(), clock).unwrap();
+
+    tampered[12] ^= 0x01;
+
+    assert!(receiver.verify(&tampered, clock).is_err());
+
+    // the timestamp increases even if the clock does not
+    assert!(receiver.verify(&sender.sign(&command(), clock).unwrap(), clock).is_ok());
+
+    assert_eq!(receiver.get_rejected(), 2);
+}
+
+#[test]
+fn unsigned_messages_rejected() {
+    let clock = Instant::now();
+
+    let mut receiver = signing("secret", clock);
+
+    assert!(receiver.verify(&command(), clock).is_err());
+    assert!(receiver.verify(&mavlink::to_v2(&command(), 0).unwrap(), clock).is_err());
+
+    // but not heartbeats
+    assert_eq!(receiver.verify(&HEARTBEAT, clock).unwrap(), HEARTBEAT.to_vec());
+
+    assert_eq!(receiver.get_rejected(), 2);
+}
+
+#[test]
+fn old_messages_rejected() {
+    let clock = Instant::now();
+
+    let mut sender = signing("secret", clock);
+    let mut receiver = signing("secret", clock);
+
+    // a new stream may be a little behind the receiver's clock but not a lot
+    let signed = sender.sign(&command(), clock).unwrap();
+
+    assert!(receiver.verify(&signed, clock + Duration::new(120, 0)).is_err());
+    assert!(receiver.verify(&signed, clock + Duration::new(30, 0)).is_ok());
+}
+
+#[test]
+fn bad_signatures_injected() {
+    let clock = Instant::now();
+
+    let mut sender = signing("secret:1:3", clock);
+    let mut receiver = signing("secret", clock);
+
+    let verified: Vec<bool> = (0 .. 6).map(|_| receiver.verify(&sender.sign(&command(), clock).unwrap(), clock).is_ok()).collect();
+
+    assert_eq!(verified, vec![true, true, false, true, true, false]);
+}
+
+#[test]
+fn bad_parameters_rejected() {
+    assert!(Signing::new().set_cli("").is_err());
+    assert!(Signing::new().set_cli("secret:256").is_err());
+    assert!(Signing::new().set_cli("secret:1:often").is_err());
+    assert!(Signing::new().set_cli("secret:1:2:3").is_err());
+}
+
+// EOF
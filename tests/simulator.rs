@@ -0,0 +1,1264 @@
+//! ADS-B Simulator - see README.md
+//
+// © NewForester, 2018.  Available under MIT licence terms.
+//
+//! End-to-end tests of the simulator's tick:  the burst of messages sent each
+//! second, the change of course on receipt of 84 messages, the tracking of a
+//! UFO from 202 messages, riding along with a SITL autopilot, the signing
+//! of messages and changes to the rates at which messages are sent.
+//!
+//! The tests drive `Simulator::tick()` with a simulated clock, exchanging
+//! messages over an in-memory transport or via a stand-in MQTT broker that
+//! routes published messages to subscribers by topic, so they are fast and
+//! deterministic.
+//!
+extern crate adsbsim;
+
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+
+use adsbsim::coords::CwithV;
+use adsbsim::linkstats::LinkStats;
+use adsbsim::mavlink;
+use adsbsim::mavlink::{Link, Message, MavMessage};
+use adsbsim::mavlink::{msg0, msg20, msg21, msg23, msg24, msg33, msg44, msg47, msg66, msg73, msg76, msg84, msg202, msg10001, msg10002};
+use adsbsim::mavlink::signing::Signing;
+use adsbsim::mission::Mission;
+use adsbsim::recovery::{Recovery, Strategy};
+use adsbsim::simulator::{Simulator, AUTOPILOT, BURST, CONFIGURED, PARAMETERS};
+use adsbsim::snapshot::Snapshot;
+
+// ---------------------------------------------------------------------------
+
+/// The Broker structure is a stand-in for an MQTT broker (exact topic match, no wildcards)
+struct Broker {
+    published:      Vec<(String, Vec<u8>)>,
+    subscriptions:  HashMap<String, Vec<usize>>,
+    inboxes:        Vec<VecDeque<Vec<u8>>>,
+}
+
+impl Broker {
+    fn new() -> Broker {
+        Broker {
+            published:      Vec::new(),
+            subscriptions:  HashMap::new(),
+            inboxes:        Vec::new(),
+        }
+    }
+
+    // connect() returns the handle of a new client
+    fn connect(&mut self) -> usize {
+        self.inboxes.push(VecDeque::new());
+
+        self.inboxes.len() - 1
+    }
+
+    fn subscribe(&mut self, client: usize, topic: &str) {
+        self.subscriptions.entry(topic.to_string()).or_default().push(client);
+    }
+
+    // publish() publishes a burst the way mqtt::Client does (pubtopic/msgid)
+    fn publish(&mut self, pubtopic: &str, burst: &[(u32, Vec<u8>)]) {
+        for &(msgid, ref message) in burst {
+            let topic = format!("{}/{}", pubtopic, msgid);
+
+            if let Some(clients) = self.subscriptions.get(&topic) {
+                for &client in clients {
+                    self.inboxes[client].push_back(message.clone());
+                }
+            }
+
+            self.published.push((topic, message.clone()));
+        }
+    }
+
+    // receive() returns the messages delivered to a client since the last call
+    fn receive(&mut self, client: usize) -> Vec<Vec<u8>> {
+        self.inboxes[client].drain(..).collect()
+    }
+}
+
+// ---------------------------------------------------------------------------
+
+fn craft(cli: &str) -> CwithV {
+    let mut cwithv = CwithV::new();
+
+    cwithv.set_cli(cli).unwrap();
+
+    cwithv
+}
+
+fn simulator(uav: &str, ufo: Option<&str>) -> Simulator {
+    let mut linkstats = LinkStats::new();
+
+    linkstats.set_cli("0").unwrap();
+
+    Simulator::new(craft(uav), ufo.map(craft), Link::new(), linkstats)
+}
+
+fn msgids(burst: &[(u32, Vec<u8>)]) -> Vec<u32> {
+    burst.iter().map(|&(msgid, _)| msgid).collect()
+}
+
+fn ownship(frame: &[u8]) -> msg202::Message {
+    match MavMessage::parse(frame) {
+        Ok(MavMessage::Ownship(message)) => message,
+        _ => panic!("not a 202 message"),
+    }
+}
+
+// params() returns the id, value and index of each parameter value in a burst
+fn params(burst: &[(u32, Vec<u8>)]) -> Vec<(String, f32, u16)> {
+    burst.iter().filter_map(|(_, frame)| match MavMessage::parse(frame) {
+        Ok(MavMessage::ParamValue(value)) => {
+            assert_eq!(value.param_count as usize, PARAMETERS.len());
+            Some((value.get_param_id(), value.param_value, value.param_index))
+        },
+        _ => None,
+    }).collect()
+}
+
+fn param_set(link: &mut Link, param_id: &str, value: f32) -> Vec<u8> {
+    let mut message = msg23::Message::new();
+
+    message.param_value = value;
+    message.set_param_id(param_id);
+
+    message.serialise(link).to_vec()
+}
+
+// mission() returns what a burst says of the mission:  MISSION_REQUEST_INT, MISSION_ACK and MISSION_ITEM_REACHED
+fn mission(burst: &[(u32, Vec<u8>)]) -> (Vec<u16>, Vec<u8>, Vec<u16>) {
+    let (mut requests, mut acks, mut reached) = (Vec::new(), Vec::new(), Vec::new());
+
+    for (_, frame) in burst.iter() {
+        match MavMessage::parse(frame) {
+            Ok(MavMessage::MissionRequest(request)) => {
+                assert_eq!((request.target_system, request.target_component), (0xff, 0xbe));
+                requests.push(request.seq);
+            },
+            Ok(MavMessage::MissionAck(ack)) => acks.push(ack.type_),
+            Ok(MavMessage::MissionItemReached(item)) => reached.push(item.seq),
+            _ => (),
+        }
+    }
+
+    (requests, acks, reached)
+}
+
+// diverted() returns a simulator whose UAV, flying north at 10 m/s, has been sent east for 3 seconds and how it is to recover
+fn diverted(cli: &str, clock: &mut Instant) -> Simulator {
+    let mut sim = simulator("(0m 0m 100),(10 0 0)", None);
+    let mut recovery = Recovery::new();
+
+    recovery.set_cli(cli).unwrap();
+    sim.set_recovery(recovery);
+
+    let mut link = Link::new();
+    let mut command = msg84::Message::new();
+
+    link.set_source(1, 1);
+
+    command.vy = 10.0;
+
+    for _ in 0 .. 3 {
+        sim.tick(*clock, vec![command.serialise(&mut link).to_vec()]);
+        *clock += Duration::from_secs(1);
+    }
+
+    sim
+}
+
+// offtrack() returns how far the UAV is from its original line (north through the secret location)
+fn offtrack(sim: &Simulator) -> f32 {
+    let (from, to) = (craft("(0m 0m 100)"), craft("(1000m 0m 100)"));
+
+    sim.get_uav().distance(&from.towards(&to, sim.get_uav().along(&from, &to)))
+}
+
+fn close(a: f32, b: f32, tolerance: f32) -> bool {
+    (a - b).abs() <= tolerance
+}
+
+// ---------------------------------------------------------------------------
+
+#[test]
+fn burst_without_ufo_has_no_traffic_report() {
+    let mut sim = simulator("(51 -1 100),(0 0 0)", None);
+    let clock = Instant::now();
+
+    let burst = sim.tick(clock, vec![]);
+
+    assert_eq!(msgids(&burst.mavlink), vec![0, 66, 203, 202]);
+    assert_eq!(burst.gdl90.len(), 3);
+}
+
+#[test]
+fn burst_with_ufo_every_tick() {
+    let mut sim = simulator("(51 -1 100),(0 0 0)", Some("(51 -1.001 100),(0 1 0)"));
+    let mut clock = Instant::now();
+    let mut seqno = 0u8;
+
+    for _ in 0 .. 5 {
+        let burst = sim.tick(clock, vec![]);
+
+        assert_eq!(msgids(&burst.mavlink), BURST.to_vec());
+        assert_eq!(burst.gdl90.len(), 4);
+
+        for &(msgid, ref frame) in burst.mavlink.iter() {
+            assert_eq!(frame[2], seqno, "sequence number of message {}", msgid);
+            assert_eq!(mavlink::msgid(frame), Some(msgid));
+
+            assert_eq!(mavlink::check_crc(frame), Some(true));
+
+            seqno = seqno.wrapping_add(1);
+        }
+
+        clock += Duration::new(1, 0);
+    }
+}
+
+#[test]
+fn uav_flies_a_straight_line() {
+    let mut sim = simulator("(0m 0m 100),(10 0 1)", None);
+    let mut clock = Instant::now();
+
+    for tick in 1 .. 4 {
+        let burst = sim.tick(clock, vec![]);
+        let mut message = ownship(&burst.mavlink[3].1);
+
+        assert!(close(sim.get_uav().get_altitude(), 100.0 + tick as f32, 0.01));
+        assert!(close(message.get_ns_velocity(), 10.0, 0.01));
+        assert!(close(message.get_rateofclimb(), 1.0, 0.01));
+
+        clock += Duration::new(1, 0);
+    }
+}
+
+#[test]
+fn uav_changes_course_on_84_and_resumes() {
+    let mut sim = simulator("(0m 0m 100),(10 0 0)", None);
+    let mut clock = Instant::now();
+
+    let mut link = Link::new();
+    let mut command = msg84::Message::new();
+
+    link.set_source(1, 1);
+
+    command.vx = 0.0;
+    command.vy = -5.0;
+    command.vz = 2.0;
+
+    sim.tick(clock, vec![]);
+    clock += Duration::new(1, 0);
+
+    let before = sim.get_uav().clone();
+
+    let burst = sim.tick(clock, vec![command.serialise(&mut link).to_vec()]);
+    clock += Duration::new(1, 0);
+
+    let mut message = ownship(&burst.mavlink[3].1);
+
+    assert!(close(message.get_ns_velocity(), 0.0, 0.01));
+    assert!(close(message.get_ew_velocity(), -5.0, 0.01));
+    assert!(close(message.get_rateofclimb(), 2.0, 0.01));
+    assert!(close(sim.get_uav().get_altitude(), before.get_altitude() + 2.0, 0.01));
+    assert!(close(sim.get_uav().get_latitude(), before.get_latitude(), 1.0e-6));
+
+    let burst = sim.tick(clock, vec![]);
+
+    let mut message = ownship(&burst.mavlink[3].1);
+
+    assert!(close(message.get_ns_velocity(), 10.0, 0.01));
+    assert!(close(message.get_ew_velocity(), 0.0, 0.01));
+    assert!(close(message.get_rateofclimb(), 0.0, 0.01));
+}
+
+#[test]
+fn misaddressed_84_is_ignored() {
+    let mut sim = simulator("(0m 0m 100),(10 0 0)", None);
+    let mut clock = Instant::now();
+
+    let mut link = Link::new();
+    let mut command = msg84::Message::new();
+
+    link.set_source(1, 1);
+
+    command.vx = -10.0;
+
+    // for some other system, for some other component of this one, for this one and for anyone
+    let targets = [((0x1a, 0x59), false), ((0x19, 0x5a), false), ((0x19, 0x59), true), ((0x00, 0x00), true)];
+
+    for &((sysid, compid), applied) in targets.iter() {
+        let mut target = simulator("(0m 0m 100),(10 0 0)", None);
+
+        command.target_system = sysid;
+        command.target_component = compid;
+
+        let burst = target.tick(clock, vec![command.serialise(&mut link).to_vec()]);
+
+        let expected = if applied { -10.0 } else { 10.0 };
+
+        assert!(close(ownship(&burst.mavlink[3].1).get_ns_velocity(), expected, 0.01));
+        assert_eq!(target.get_misaddressed(), if applied { 0 } else { 1 });
+    }
+
+    // those ignored are counted
+    command.target_system = 0x1a;
+
+    for _ in 0 .. 3 {
+        sim.tick(clock, vec![command.serialise(&mut link).to_vec()]);
+        clock += Duration::new(1, 0);
+    }
+
+    assert_eq!(sim.get_misaddressed(), 3);
+    assert!(close(sim.get_uav().get_ns_velocity(), 10.0, 0.01));
+}
+
+#[test]
+fn bad_checksum_is_ignored() {
+    let mut sim = simulator("(0m 0m 100),(10 0 0)", None);
+
+    let mut link = Link::new();
+    let mut command = msg84::Message::new();
+
+    command.vx = -10.0;
+
+    let mut frame = command.serialise(&mut link).to_vec();
+    let last = frame.len() - 1;
+
+    frame[last] ^= 0xff;
+
+    // MAVLink 2 too:  the checksum is checked before the frame is converted
+    let mut v2 = mavlink::to_v2(command.serialise(&mut link), 0).unwrap();
+    let last = v2.len() - 1;
+
+    v2[last] ^= 0xff;
+
+    let burst = sim.tick(Instant::now(), vec![frame, v2]);
+
+    assert!(close(ownship(&burst.mavlink[3].1).get_ns_velocity(), 10.0, 0.01));
+    assert_eq!(sim.get_linkstats().get_crcfails(0x19, 0x59), 2);
+}
+
+#[test]
+fn ufo_tracked_from_202() {
+    let mut sim = simulator("(51 -1 100),(0 0 0)", None);
+    let mut clock = Instant::now();
+
+    let mut other = craft("(51.001 -1.001 150),(3 4 -1)");
+    let mut link = Link::new();
+    let mut report = msg202::Message::new();
+
+    link.set_source(2, 1);
+
+    assert!(sim.get_ufo().is_none());
+
+    for _ in 0 .. 3 {
+        let frame = report.set_cwithv(&other).serialise(&mut link).to_vec();
+
+        let burst = sim.tick(clock, vec![frame]);
+
+        assert_eq!(msgids(&burst.mavlink), BURST.to_vec());
+
+        let traffic = match MavMessage::parse(&burst.mavlink[4].1) {
+            Ok(MavMessage::TrafficReport(message)) => message,
+            _ => panic!("not a 246 message"),
+        };
+
+        assert!(close(traffic.lat as f32 / 1.0e7, other.get_latitude(), 1.0e-5));
+        assert!(close(traffic.lon as f32 / 1.0e7, other.get_longitude(), 1.0e-5));
+        assert!(close(sim.get_ufo().unwrap().get_altitude(), other.get_altitude(), 0.01));
+
+        other.update_position();
+        clock += Duration::new(1, 0);
+    }
+}
+
+#[test]
+fn snapshot_counts_messages_received_and_sent() {
+    let mut sim = simulator("(51 -1 100),(0 0 0)", None);
+    let mut clock = Instant::now();
+
+    let other = craft("(51.001 -1.001 150),(3 4 -1)");
+    let mut link = Link::new();
+
+    link.set_source(2, 1);
+
+    // a 202 sent and no 246 until there is a UFO
+    sim.tick(clock, Vec::<Vec<u8>>::new());
+
+    assert_eq!(sim.get_messages(), 1);
+
+    // a 202 received, a heartbeat that does not count and a 202 and a 246 sent
+    let frames = vec![
+        msg202::Message::new().set_cwithv(&other).serialise(&mut link).to_vec(),
+        msg0::Message::new().serialise(&mut link).to_vec(),
+    ];
+
+    clock += Duration::from_secs(1);
+
+    sim.tick(clock, frames);
+
+    assert_eq!(sim.get_messages(), 4);
+
+    // messages, not aircraft
+    let json = Snapshot::render(&sim.aircraft(), sim.get_messages());
+
+    assert!(json.contains("\"messages\" : 4,\n"));
+
+    // the UFO but not the UAV, which has no ICAO address
+    assert_eq!(json.matches("\"hex\"").count(), 1);
+}
+
+#[test]
+fn snapshot_shows_craft_as_reported() {
+    let mut sim = simulator("(0m 0m 100),(10 0 0)", Some("(100m 0m 100),(0 5 0)"));
+    let mut clock = Instant::now();
+
+    sim.set_icao(0xabcdef, 0x123456);
+
+    // with noise the UFO is where the 246 message says, not where it is
+    assert!(sim.set_param(7, 20.0));
+
+    let burst = sim.tick(clock, vec![]);
+
+    let report = burst.mavlink.iter().find(|&&(msgid, _)| msgid == 246).map(|(_, frame)| match MavMessage::parse(frame) {
+        Ok(MavMessage::TrafficReport(report)) => report,
+        _ => panic!("not a 246 message"),
+    }).unwrap();
+
+    let aircraft = sim.aircraft();
+
+    assert_eq!(aircraft.iter().map(|craft| craft.icao).collect::<Vec<u32>>(), vec![0x123456, 0xabcdef]);
+    assert_eq!(aircraft[1].cwithv.get_latitude(), report.lat as f32 / 1.0e7);
+    assert_eq!(aircraft[1].cwithv.get_longitude(), report.lon as f32 / 1.0e7);
+    assert!(aircraft[1].cwithv.distance(sim.get_ufo().unwrap()) > 0.0);
+
+    // out of range, the UFO is not reported and so not shown
+    assert!(sim.set_param(6, 50.0));
+
+    clock += Duration::from_secs(1);
+    sim.tick(clock, vec![]);
+
+    assert_eq!(sim.aircraft().iter().map(|craft| craft.icao).collect::<Vec<u32>>(), vec![0x123456]);
+}
+
+#[test]
+fn uav_holds_course_then_resumes_parallel() {
+    let mut clock = Instant::now();
+    let mut sim = diverted("resume:3", &mut clock);
+
+    // the last 84 was a second ago:  still heading east for another two seconds
+    for _ in 0 .. 2 {
+        sim.tick(clock, vec![]);
+        clock += Duration::from_secs(1);
+
+        assert!(close(sim.get_uav().get_ew_velocity(), 10.0, 0.01));
+    }
+
+    sim.tick(clock, vec![]);
+
+    assert!(close(sim.get_uav().get_ns_velocity(), 10.0, 0.01));
+    assert!(close(sim.get_uav().get_ew_velocity(), 0.0, 0.01));
+    assert!(close(offtrack(&sim), 50.0, 1.0));
+}
+
+#[test]
+fn uav_steers_back_onto_its_line() {
+    let mut clock = Instant::now();
+    let mut sim = diverted("track", &mut clock);
+
+    let away = offtrack(&sim);
+
+    assert!(close(away, 30.0, 1.0));
+
+    // back at 45 degrees
+    sim.tick(clock, vec![]);
+    clock += Duration::from_secs(1);
+
+    assert!(sim.get_uav().get_ew_velocity() < -5.0 && sim.get_uav().get_ns_velocity() > 5.0);
+    assert!(offtrack(&sim) < away);
+
+    for _ in 0 .. 10 {
+        sim.tick(clock, vec![]);
+        clock += Duration::from_secs(1);
+    }
+
+    assert!(offtrack(&sim) <= 5.0);
+    assert!(close(sim.get_uav().get_ns_velocity(), 10.0, 0.01));
+    assert!(close(sim.get_uav().get_ew_velocity(), 0.0, 0.01));
+}
+
+#[test]
+fn uav_heads_for_next_planned_point() {
+    let mut clock = Instant::now();
+    let mut sim = diverted("next:0", &mut clock);
+
+    // where the UAV would be now had it carried on north (the three ticks took it 30 m east, not north)
+    let planned = craft("(30m 0m 100)");
+
+    assert!(close(sim.get_uav().distance(&planned), 30.0 * 2.0f32.sqrt(), 1.0));
+
+    let mut reached = false;
+
+    for _ in 0 .. 10 {
+        sim.tick(clock, vec![]);
+        clock += Duration::from_secs(1);
+
+        reached |= sim.get_uav().distance(&planned) <= 5.0;
+    }
+
+    assert!(reached);
+    assert!(offtrack(&sim) <= 5.0);
+    assert!(close(sim.get_uav().get_ns_velocity(), 10.0, 0.01));
+}
+
+#[test]
+fn bad_recovery_parameters_rejected() {
+    assert_eq!(Recovery::new().get_strategy(), Strategy::Resume);
+    assert_eq!(Recovery::new().get_hold(), Duration::from_secs(1));
+    assert_eq!(Recovery::new().set_cli("track:2.5").unwrap().get_hold(), Duration::from_millis(2500));
+    assert_eq!(Recovery::new().set_cli(":0").unwrap().get_strategy(), Strategy::Resume);
+
+    assert!(Recovery::new().set_cli("home").is_err());
+    assert!(Recovery::new().set_cli("next:soon").is_err());
+    assert!(Recovery::new().set_cli("next:-1").is_err());
+    assert!(Recovery::new().set_cli("next:1:2").is_err());
+}
+
+#[test]
+fn two_simulators_track_each_other_via_broker() {
+    let mut broker = Broker::new();
+
+    let mut alpha = simulator("(51 -1 100),(10 0 0)", None);
+    let mut bravo = simulator("(51.01 -1 100),(-10 0 0)", None);
+
+    alpha.set_icao(0xb0b0b0, 0xa0a0a0);
+    bravo.set_icao(0xa0a0a0, 0xb0b0b0);
+
+    let a = broker.connect();
+    let b = broker.connect();
+
+    broker.subscribe(a, "/b0b0b0/202");
+    broker.subscribe(b, "/a0a0a0/202");
+
+    let mut clock = Instant::now();
+
+    for tick in 0 .. 4 {
+        let inbox = broker.receive(a);
+        let burst = alpha.tick(clock, inbox);
+        broker.publish("/a0a0a0", &burst.mavlink);
+
+        let inbox = broker.receive(b);
+        let burst = bravo.tick(clock, inbox);
+        broker.publish("/b0b0b0", &burst.mavlink);
+
+        if tick > 0 {
+            assert!(alpha.get_ufo().is_some());
+            assert!(bravo.get_ufo().is_some());
+        }
+
+        clock += Duration::new(1, 0);
+    }
+
+    // the UFO lags one tick behind the other UAV (it moves on before the next 202 arrives)
+    assert!(close(alpha.get_ufo().unwrap().get_latitude(), bravo.get_uav().get_latitude(), 1.0e-4));
+    assert!(close(bravo.get_ufo().unwrap().get_latitude(), alpha.get_uav().get_latitude(), 1.0e-4));
+
+    let traffic = broker.published.iter().filter(|(topic, _)| topic.ends_with("/246")).count();
+
+    assert!(traffic >= 5);
+}
+
+#[test]
+fn uav_rides_along_with_sitl() {
+    let mut sim = simulator("(0m 0m 100),(10 0 0)", None);
+    let mut clock = Instant::now();
+
+    sim.set_sitl(true);
+
+    let mut autopilot = craft("(51 -1 120),(3 4 1)");
+    let mut link = Link::new();
+    let mut gps = msg24::Message::new();
+    let mut position = msg33::Message::new();
+
+    link.set_source(1, 1);
+
+    // raw GPS will do until there is a position estimate
+    gps.fix_type = 3;
+    gps.satellites_visible = 9;
+    gps.lat = 510010000;
+    gps.lon = -10010000;
+    gps.alt = 110000;
+    gps.vel = 500;
+    gps.cog = 9000;
+
+    let burst = sim.tick(clock, vec![gps.serialise(&mut link).to_vec()]);
+    let mut message = ownship(&burst.mavlink[3].1);
+
+    assert!(close(message.get_latitude(), 51.001, 1.0e-5));
+    assert!(close(message.get_altitude(), 110.0, 0.01));
+    assert!(close(message.get_ew_velocity(), 5.0, 0.01));
+    assert_eq!((message.fixtype, message.numsats), (3, 9));
+
+    for _ in 0 .. 3 {
+        clock += Duration::new(1, 0);
+
+        let frames = vec![position.set_cwithv(&autopilot).serialise(&mut link).to_vec(), gps.serialise(&mut link).to_vec()];
+
+        let burst = sim.tick(clock, frames);
+        let mut message = ownship(&burst.mavlink[3].1);
+
+        // where the autopilot says, not where the velocity would take it
+        assert!(close(message.get_latitude(), autopilot.get_latitude(), 1.0e-5));
+        assert!(close(message.get_longitude(), autopilot.get_longitude(), 1.0e-5));
+        assert!(close(message.get_altitude(), autopilot.get_altitude(), 0.01));
+        assert!(close(message.get_ns_velocity(), 3.0, 0.01));
+        assert!(close(message.get_rateofclimb(), 1.0, 0.01));
+
+        autopilot.update_position();
+    }
+}
+
+#[test]
+fn transponder_configured_by_autopilot() {
+    let mut sim = simulator("(51 -1 100),(0 0 0)", None);
+    let mut clock = Instant::now();
+
+    let mut link = Link::new();
+    let mut config = msg10001::Message::new();
+    let mut dynamic = msg10002::Message::new();
+
+    link.set_source(1, 1);
+
+    // no health reports until configured
+    let burst = sim.tick(clock, vec![]);
+
+    assert_eq!(msgids(&burst.mavlink), vec![0, 66, 203, 202]);
+    assert_eq!(sim.get_transponder(), (0, "", 14));
+
+    config.icao = 0xabc123;
+    config.set_callsign("DRISQ1");
+    config.emitter_type = 2;
+    dynamic.squawk = 0x1234;
+
+    clock += Duration::new(1, 0);
+
+    let burst = sim.tick(clock, vec![config.serialise(&mut link).to_vec(), dynamic.serialise(&mut link).to_vec()]);
+
+    assert_eq!(&msgids(&burst.mavlink)[4 ..], &CONFIGURED[..]);
+    assert_eq!(sim.get_transponder(), (0xabc123, "DRISQ1", 2));
+    assert_eq!(ownship(&burst.mavlink[3].1).squawk, 0x1234);
+
+    match MavMessage::parse(&burst.mavlink[4].1) {
+        Ok(MavMessage::TransponderHealth(health)) => assert_eq!(health.rf_health, 1),
+        _ => panic!("not a 10003 message"),
+    }
+
+    // the GDL 90 ownship report and snapshots follow suit
+    let report = &burst.gdl90[1];
+
+    assert!(report.windows(3).any(|bytes| bytes == [0xab, 0xc1, 0x23]));
+    assert!(report.windows(9).any(|bytes| bytes == b"\x02DRISQ1  "));
+    assert_eq!(sim.aircraft()[0].icao, 0xabc123);
+    assert_eq!(sim.aircraft()[0].callsign, "DRISQ1");
+}
+
+#[test]
+fn traffic_reports_at_5hz_on_request() {
+    let mut sim = simulator("(0m 0m 100),(10 0 0)", Some("(100m 0m 100),(0 5 0)"));
+    let mut clock = Instant::now();
+
+    let mut link = Link::new();
+    let mut request = msg66::Message::new();
+
+    link.set_source(1, 1);
+
+    // MAV_DATA_STREAM_POSITION at 5 Hz
+    request.target_system = 0x19;
+    request.req_stream_id = 6;
+    request.req_message_rate = 5;
+    request.start_stop = 1;
+
+    let burst = sim.tick(clock, vec![request.serialise(&mut link).to_vec()]);
+
+    assert_eq!(msgids(&burst.mavlink), BURST.to_vec());
+    assert_eq!(sim.get_period(), Duration::from_millis(200));
+
+    let start = sim.get_uav().get_latitude();
+    let mut count: HashMap<u32, usize> = HashMap::new();
+    let mut gdl90 = 0;
+
+    for _ in 0 .. 5 {
+        clock += Duration::from_millis(200);
+
+        let burst = sim.tick(clock, vec![]);
+
+        for msgid in msgids(&burst.mavlink) {
+            *count.entry(msgid).or_insert(0) += 1;
+        }
+        gdl90 += burst.gdl90.len();
+    }
+
+    assert_eq!((count[&202], count[&246]), (5, 5));
+    assert_eq!((count[&0], count[&66], count[&203]), (1, 1, 1));
+    assert_eq!(gdl90, 4);
+
+    // a second's worth of movement in five ticks
+    let moved = sim.get_uav().get_latitude() - start;
+    let mut expected = craft("(0m 0m 100),(10 0 0)");
+
+    expected.update_position().update_position();
+
+    assert!(close(moved, expected.get_latitude() - start, 1.0e-6));
+
+    // stopping the stream stops the messages
+    request.start_stop = 0;
+
+    clock += Duration::from_millis(200);
+
+    sim.tick(clock, vec![request.serialise(&mut link).to_vec()]);
+
+    assert_eq!(sim.get_period(), Duration::from_secs(1));
+
+    for _ in 0 .. 3 {
+        clock += Duration::from_secs(1);
+
+        assert_eq!(msgids(&sim.tick(clock, vec![]).mavlink), vec![0, 66, 203]);
+    }
+}
+
+#[test]
+fn simulators_sharing_a_topic_do_not_stop_each_other() {
+    let mut one = simulator("(0m 0m 100),(10 0 0)", Some("(100m 0m 100),(0 5 0)"));
+    let mut two = simulator("(0m 500m 100),(0 -10 0)", Some("(100m 0m 100),(0 5 0)"));
+    let mut clock = Instant::now();
+
+    // each hears the other's burst (and both have the same ids)
+    let mut heard: Vec<Vec<u8>> = Vec::new();
+
+    for _ in 0 .. 3 {
+        let burst = one.tick(clock, heard);
+
+        assert_eq!(msgids(&burst.mavlink), BURST.to_vec());
+
+        heard = burst.mavlink.into_iter().map(|(_, frame)| frame).collect();
+
+        let burst = two.tick(clock, heard);
+
+        assert_eq!(msgids(&burst.mavlink), BURST.to_vec());
+
+        heard = burst.mavlink.into_iter().map(|(_, frame)| frame).collect();
+        clock += Duration::new(1, 0);
+    }
+
+    // the simulator's own 66 message does not say 'stop all streams'
+    let request = match MavMessage::parse(&one.tick(clock, vec![]).mavlink[1].1) {
+        Ok(MavMessage::DataStreamRequest(request)) => request,
+        _ => panic!("not a 66 message"),
+    };
+
+    assert_eq!((request.target_system, request.target_component), AUTOPILOT);
+    assert_eq!((request.req_stream_id, request.req_message_rate, request.start_stop), (6, 1, 1));
+
+    // nor is a 66 message that does not say which system it is for obeyed
+    let mut link = Link::new();
+
+    link.set_source(1, 1);
+
+    clock += Duration::new(1, 0);
+
+    let burst = one.tick(clock, vec![msg66::Message::new().serialise(&mut link).to_vec()]);
+
+    assert_eq!(msgids(&burst.mavlink), BURST.to_vec());
+    assert_eq!(one.get_rates().get_interval(202), Some(Duration::from_secs(1)));
+}
+
+#[test]
+fn message_interval_commands_acknowledged() {
+    let mut sim = simulator("(51 -1 100),(0 0 0)", None);
+    let mut clock = Instant::now();
+
+    let mut link = Link::new();
+
+    link.set_source(1, 1);
+
+    let mut command = |target: u8, cmd: u16, param1: f32, param2: f32| {
+        let mut message = msg76::Message::new();
+
+        message.target_system = target;
+        message.command = cmd;
+        message.param1 = param1;
+        message.param2 = param2;
+
+        message.serialise(&mut link).to_vec()
+    };
+
+    let acks = |burst: &[(u32, Vec<u8>)]| -> Vec<(u16, u8)> {
+        burst.iter().filter_map(|(_, frame)| match MavMessage::parse(frame) {
+            Ok(MavMessage::CommandAck(ack)) => Some((ack.command, ack.result)),
+            _ => None,
+        }).collect()
+    };
+
+    let frames = vec![
+        command(0x19, msg76::MAV_CMD_SET_MESSAGE_INTERVAL, 202.0, 500000.0),
+        command(0, msg76::MAV_CMD_SET_MESSAGE_INTERVAL, 203.0, -1.0),
+        command(0x19, msg76::MAV_CMD_SET_MESSAGE_INTERVAL, 24.0, 500000.0),
+        command(0x19, 400, 1.0, 0.0),
+        command(42, msg76::MAV_CMD_SET_MESSAGE_INTERVAL, 202.0, 0.0),
+    ];
+
+    let burst = sim.tick(clock, frames);
+
+    assert_eq!(acks(&burst.mavlink), vec![(511, 0), (511, 0), (511, 2), (400, 3)]);
+    assert_eq!(sim.get_rates().get_interval(202), Some(Duration::from_millis(500)));
+    assert_eq!(sim.get_rates().get_interval(203), None);
+
+    clock += Duration::from_millis(500);
+
+    let burst = sim.tick(clock, vec![]);
+
+    assert_eq!(msgids(&burst.mavlink), vec![202]);
+    assert!(acks(&burst.mavlink).is_empty());
+
+    // back to the default
+    clock += Duration::from_millis(500);
+
+    let burst = sim.tick(clock, vec![command(0x19, msg76::MAV_CMD_SET_MESSAGE_INTERVAL, 202.0, 0.0)]);
+
+    // the last 202 went only half a second ago
+    assert_eq!(msgids(&burst.mavlink), vec![0, 66, 77]);
+    assert_eq!(sim.get_period(), Duration::from_secs(1));
+
+    // parameters that are not a message id or an interval are refused
+    let frames = vec![
+        command(0x19, msg76::MAV_CMD_SET_MESSAGE_INTERVAL, f32::NAN, 500000.0),
+        command(0x19, msg76::MAV_CMD_SET_MESSAGE_INTERVAL, -202.0, 500000.0),
+        command(0x19, msg76::MAV_CMD_SET_MESSAGE_INTERVAL, 202.5, 500000.0),
+        command(0x19, msg76::MAV_CMD_SET_MESSAGE_INTERVAL, 202.0, f32::NAN),
+        command(0x19, msg76::MAV_CMD_SET_MESSAGE_INTERVAL, 202.0, -2.0),
+    ];
+
+    clock += Duration::from_secs(1);
+
+    let burst = sim.tick(clock, frames);
+
+    assert_eq!(acks(&burst.mavlink), vec![(511, 2); 5]);
+    assert_eq!(sim.get_rates().get_interval(202), Some(Duration::from_secs(1)));
+}
+
+#[test]
+fn rates_kept_when_period_does_not_divide_interval() {
+    let mut sim = simulator("(0m 0m 100),(10 0 0)", Some("(100m 0m 100),(0 5 0)"));
+    let mut clock = Instant::now();
+
+    let mut link = Link::new();
+    let mut command = msg76::Message::new();
+
+    link.set_source(1, 1);
+
+    // 202 at 3 Hz so the simulator ticks every 333 ms but the heartbeat is still wanted once a second
+    command.command = msg76::MAV_CMD_SET_MESSAGE_INTERVAL;
+    command.param1 = 202.0;
+    command.param2 = 333333.0;
+
+    sim.tick(clock, vec![command.serialise(&mut link).to_vec()]);
+
+    let mut count: HashMap<u32, usize> = HashMap::new();
+    let mut gdl90 = 0;
+
+    for _ in 0 .. 30 {
+        let period = sim.get_period();
+
+        clock += period;
+
+        let burst = sim.tick(clock, vec![]);
+
+        for msgid in msgids(&burst.mavlink) {
+            *count.entry(msgid).or_insert(0) += 1;
+        }
+        gdl90 += burst.gdl90.len();
+    }
+
+    // ten seconds' worth
+    assert_eq!(sim.get_period(), Duration::from_micros(333333));
+    assert_eq!((count[&0], count[&66], count[&246]), (10, 10, 10));
+    assert_eq!(count[&202], 30);
+    assert_eq!(gdl90, 10 * 4);
+}
+
+#[test]
+fn parameters_read_and_set_by_ground_station() {
+    let mut sim = simulator("(51 -1 100),(0 0 0)", None);
+    let mut clock = Instant::now();
+
+    let mut link = Link::new();
+
+    link.set_source(0xff, 0xbe);
+
+    // the whole list (in order)
+    let burst = sim.tick(clock, vec![msg21::Message::new().serialise(&mut link).to_vec()]);
+
+    let values = params(&burst.mavlink);
+    let names: Vec<&str> = values.iter().map(|(name, _, _)| &name[..]).collect();
+    let expected: Vec<&str> = PARAMETERS.iter().map(|&(name, _)| name).collect();
+
+    assert_eq!(names, expected);
+    assert_eq!(values[0], ("ADSB_ICAO".to_string(), 0.0, 0));
+    assert_eq!(values[12], ("RATE_246".to_string(), 1.0, 12));
+    assert_eq!(values[14], ("RATE_42".to_string(), 1.0, 14));
+
+    // changes apply from the next tick and are echoed
+    let frames = vec![
+        param_set(&mut link, "ADSB_ICAO", 0x123456 as f32),
+        param_set(&mut link, "ADSB_SQUAWK", 7000.0),
+        param_set(&mut link, "RATE_202", 5.0),
+        param_set(&mut link, "RATE_42", 0.0),
+        param_set(&mut link, "ADSB_EMIT_TYPE", 3.5),
+        param_set(&mut link, "ADSB_MODE_C", 1.0),
+    ];
+
+    clock += Duration::from_secs(1);
+
+    let burst = sim.tick(clock, frames);
+
+    assert_eq!(params(&burst.mavlink), vec![
+        ("ADSB_ICAO".to_string(), 0x123456 as f32, 0),
+        ("ADSB_SQUAWK".to_string(), 7000.0, 5),
+        ("RATE_202".to_string(), 5.0, 11),
+        ("RATE_42".to_string(), 0.0, 14),
+        ("ADSB_EMIT_TYPE".to_string(), 14.0, 4),
+    ]);
+
+    assert_eq!(sim.get_rates().get_interval(42), None);
+
+    assert_eq!(sim.get_transponder().0, 0x123456);
+    assert_eq!(sim.get_period(), Duration::from_millis(200));
+
+    let squawk = burst.mavlink.iter().find(|&&(msgid, _)| msgid == 202).map(|(_, frame)| ownship(frame).squawk);
+
+    assert_eq!(squawk, Some(7000));
+
+    // one by index and one by name but none meant for another system
+    let mut read = msg20::Message::new();
+
+    read.param_index = 6;
+
+    let by_index = read.serialise(&mut link).to_vec();
+
+    read.param_index = -1;
+    read.set_param_id("RATE_0");
+
+    let by_name = read.serialise(&mut link).to_vec();
+
+    read.target_system = 42;
+
+    let elsewhere = read.serialise(&mut link).to_vec();
+
+    clock += Duration::from_millis(200);
+
+    let burst = sim.tick(clock, vec![by_index, by_name, elsewhere]);
+
+    assert_eq!(params(&burst.mavlink), vec![("ADSB_RANGE".to_string(), 0.0, 6), ("RATE_0".to_string(), 1.0, 8)]);
+}
+
+#[test]
+fn callsign_read_and_set_by_ground_station() {
+    let mut sim = simulator("(51 -1 100),(0 0 0)", None);
+    let mut clock = Instant::now();
+
+    let mut link = Link::new();
+
+    link.set_source(0xff, 0xbe);
+
+    // three characters a value, the first in the top byte
+    let chars = |cs: &[u8]| cs.iter().fold(0, |value, &cc| value << 8 | cc as u32) as f32;
+
+    let frames = vec![
+        param_set(&mut link, "ADSB_CALLSIGN1", chars(b"G-A")),
+        param_set(&mut link, "ADSB_CALLSIGN2", chars(b"BCD")),
+        param_set(&mut link, "ADSB_CALLSIGN3", chars(b"EF\x07")),
+    ];
+
+    let burst = sim.tick(clock, frames);
+
+    assert_eq!(params(&burst.mavlink), vec![
+        ("ADSB_CALLSIGN1".to_string(), chars(b"G-A"), 1),
+        ("ADSB_CALLSIGN2".to_string(), chars(b"BCD"), 2),
+        ("ADSB_CALLSIGN3".to_string(), 0.0, 3),
+    ]);
+
+    assert_eq!(sim.get_transponder().1, "G-ABCD");
+
+    // a shorter callsign ends at the first null
+    clock += Duration::from_secs(1);
+
+    let burst = sim.tick(clock, vec![param_set(&mut link, "ADSB_CALLSIGN2", chars(b"X\0\0"))]);
+
+    assert_eq!(params(&burst.mavlink), vec![("ADSB_CALLSIGN2".to_string(), chars(b"X\0\0"), 2)]);
+    assert_eq!(sim.get_transponder().1, "G-AX");
+
+    // and reads back as set
+    let mut read = msg20::Message::new();
+
+    read.param_index = -1;
+    read.set_param_id("ADSB_CALLSIGN1");
+
+    clock += Duration::from_secs(1);
+
+    let burst = sim.tick(clock, vec![read.serialise(&mut link).to_vec()]);
+
+    assert_eq!(params(&burst.mavlink), vec![("ADSB_CALLSIGN1".to_string(), chars(b"G-A"), 1)]);
+}
+
+#[test]
+fn traffic_limited_by_range_and_noisy() {
+    let mut sim = simulator("(0m 0m 100),(0 0 0)", Some("(1000m 0m 100),(0 0 0)"));
+    let mut clock = Instant::now();
+
+    let mut link = Link::new();
+
+    link.set_source(0xff, 0xbe);
+
+    let burst = sim.tick(clock, vec![param_set(&mut link, "ADSB_RANGE", 500.0)]);
+
+    assert_eq!(msgids(&burst.mavlink), vec![0, 66, 203, 202, 22]);
+    assert_eq!(burst.gdl90.len(), 3);
+
+    clock += Duration::from_secs(1);
+
+    let burst = sim.tick(clock, vec![param_set(&mut link, "ADSB_RANGE", 2000.0), param_set(&mut link, "ADSB_NOISE", 50.0)]);
+
+    assert_eq!(msgids(&burst.mavlink), vec![0, 66, 203, 202, 246, 22, 22]);
+    assert_eq!(burst.gdl90.len(), 4);
+
+    // the noise moves the reported position (but not far) and not the UFO itself
+    let ufo = sim.get_ufo().unwrap().clone();
+    let mut moved = false;
+
+    for _ in 0 .. 10 {
+        clock += Duration::from_secs(1);
+
+        let burst = sim.tick(clock, vec![]);
+        let report = burst.mavlink.iter().find(|&&(msgid, _)| msgid == 246).map(|(_, frame)| match MavMessage::parse(frame) {
+            Ok(MavMessage::TrafficReport(report)) => report,
+            _ => panic!("not a 246 message"),
+        }).unwrap();
+
+        let mut reported = CwithV::new();
+
+        reported.set_position(report.lat as f32 / 1.0e7, report.lon as f32 / 1.0e7, report.altitude as f32 / 1000.0);
+
+        let distance = reported.distance(&ufo);
+
+        assert!(distance <= 50.0 * 3.0f32.sqrt() + 1.0, "reported {} m away", distance);
+
+        moved |= distance > 1.0;
+    }
+
+    assert!(moved);
+    assert_eq!(sim.get_ufo().unwrap().distance(&ufo), 0.0);
+
+    // negative ranges are not
+    clock += Duration::from_secs(1);
+
+    let burst = sim.tick(clock, vec![param_set(&mut link, "ADSB_RANGE", -1.0)]);
+
+    assert_eq!(params(&burst.mavlink), vec![("ADSB_RANGE".to_string(), 2000.0, 6)]);
+}
+
+#[test]
+fn mission_uploaded_flown_and_rejoined() {
+    let mut sim = simulator("(0m 0m 100),(10 0 0)", None);
+    let mut clock = Instant::now();
+
+    let mut link = Link::new();
+
+    link.set_source(0xff, 0xbe);
+
+    let waypoints = [craft("(0m 100m 100)"), craft("(100m 100m 120)")];
+
+    let mut count = msg44::Message::new();
+
+    count.count = waypoints.len() as u16;
+
+    let count = count.serialise(&mut link).to_vec();
+
+    let mut items: Vec<Vec<u8>> = waypoints.iter().enumerate().map(|(seq, waypoint)| {
+        msg73::Message::new().set_waypoint(seq as u16, waypoint).serialise(&mut link).to_vec()
+    }).collect();
+
+    // an unsupported command spoils an upload
+    let mut bad = msg73::Message::new();
+
+    bad.seq = 0;
+    bad.command = 400;
+
+    let burst = sim.tick(clock, vec![count.clone(), bad.serialise(&mut link).to_vec()]);
+
+    assert_eq!(mission(&burst.mavlink), (vec![], vec![msg47::MAV_MISSION_UNSUPPORTED], vec![]));
+    assert_eq!(sim.get_mission().get_count(), 0);
+
+    // each item asked for in turn (and again if it goes missing)
+    clock += Duration::from_secs(1);
+
+    assert_eq!(mission(&sim.tick(clock, vec![count]).mavlink), (vec![0], vec![], vec![]));
+
+    clock += Duration::from_secs(1);
+
+    assert_eq!(mission(&sim.tick(clock, vec![items.remove(0)]).mavlink), (vec![1], vec![], vec![]));
+
+    clock += Duration::from_secs(1);
+
+    assert_eq!(mission(&sim.tick(clock, vec![]).mavlink), (vec![1], vec![], vec![]));
+
+    clock += Duration::from_secs(1);
+
+    let burst = sim.tick(clock, vec![items.remove(0)]);
+
+    assert_eq!(mission(&burst.mavlink), (vec![], vec![msg47::MAV_MISSION_ACCEPTED], vec![]));
+    assert_eq!(sim.get_mission().get_count(), 2);
+    assert_eq!(msgids(&burst.mavlink)[.. 6], [0, 66, 203, 202, 42, 47]);
+
+    // off to the first waypoint
+    let mut reached = Vec::new();
+
+    for _ in 0 .. 15 {
+        clock += Duration::from_secs(1);
+
+        reached.extend(mission(&sim.tick(clock, vec![]).mavlink).2);
+    }
+
+    assert_eq!(reached, vec![0]);
+    assert_eq!(sim.get_mission().get_current(), 1);
+
+    // avoid something for a few seconds (heading west)
+    let mut avoid = msg84::Message::new();
+
+    avoid.vy = -10.0;
+
+    for _ in 0 .. 3 {
+        clock += Duration::from_secs(1);
+
+        sim.tick(clock, vec![avoid.serialise(&mut link).to_vec()]);
+    }
+
+    assert!(sim.get_uav().distance(&craft("(0m 100m 100)")) > 20.0);
+
+    // and back to the route (not the original course north) to the last waypoint, where the UAV stays
+    for _ in 0 .. 20 {
+        clock += Duration::from_secs(1);
+
+        reached.extend(mission(&sim.tick(clock, vec![]).mavlink).2);
+    }
+
+    assert_eq!(reached, vec![0, 1]);
+    assert!(sim.get_uav().distance(&waypoints[1]) <= 5.0);
+    assert_eq!(sim.get_uav().get_groundspeed(), 0.0);
+}
+
+#[test]
+fn mission_steered_for_time_since_last_tick() {
+    let mut sim = simulator("(0m 0m 100),(10 0 0)", None);
+    let mut clock = Instant::now();
+
+    let mut link = Link::new();
+
+    link.set_source(0xff, 0xbe);
+
+    let waypoint = craft("(28m 0m 100)");
+
+    let mut count = msg44::Message::new();
+    let mut item = msg73::Message::new();
+
+    count.count = 1;
+    item.set_waypoint(0, &waypoint).param2 = 1.0;
+
+    // ticking once a second although 202 messages are wanted five times a second:  north 10 m each tick
+    sim.tick(clock, vec![param_set(&mut link, "RATE_202", 5.0), count.serialise(&mut link).to_vec()]);
+
+    clock += Duration::from_secs(1);
+
+    assert_eq!(mission(&sim.tick(clock, vec![item.serialise(&mut link).to_vec()]).mavlink).1, vec![msg47::MAV_MISSION_ACCEPTED]);
+    assert_eq!(sim.get_period(), Duration::from_millis(200));
+
+    // 8 m short the UAV slows to arrive in the second to come, not in the fifth of a second, and so does not overshoot
+    clock += Duration::from_secs(1);
+
+    sim.tick(clock, vec![]);
+
+    assert!(sim.get_uav().distance(&waypoint) <= 1.0);
+}
+
+#[test]
+fn mission_upload_says_why_items_are_ignored() {
+    let mut mission = Mission::new(0.0);
+    let mut clock = Instant::now();
+
+    let waypoint = craft("(0m 100m 100)");
+
+    // no upload in progress
+    assert!(mission.add_item(msg73::Message::new().set_waypoint(0, &waypoint), clock).is_err());
+
+    // an item out of turn and then the one wanted
+    assert_eq!(mission.start_upload((0xff, 0xbe), 2, clock), None);
+    assert!(mission.add_item(msg73::Message::new().set_waypoint(1, &waypoint), clock).is_err());
+    assert_eq!(mission.add_item(msg73::Message::new().set_waypoint(0, &waypoint), clock), Ok(None));
+    assert_eq!(mission.get_request(), Some(((0xff, 0xbe), 1)));
+
+    // an upload that goes quiet is dropped (once)
+    clock += Duration::from_secs(5);
+
+    assert!(mission.abandon_quiet(clock));
+    assert!(! mission.abandon_quiet(clock));
+    assert_eq!(mission.get_request(), None);
+}
+
+#[test]
+fn signed_simulators_reject_unsigned_84() {
+    let mut clock = Instant::now();
+
+    let mut sim = simulator("(0m 0m 100),(10 0 0)", None);
+    let mut signing = Signing::new();
+
+    signing.set_cli("secret:1").unwrap();
+    signing.set_epoch(clock, 1);
+
+    sim.set_signing(signing);
+
+    // the transports sign the messages sent (see the transport tests)
+    let burst = sim.tick(clock, vec![]);
+
+    assert_eq!(msgids(&burst.mavlink), vec![0, 66, 203, 202]);
+
+    let mut link = Link::new();
+    let mut command = msg84::Message::new();
+
+    command.vx = -10.0;
+
+    let unsigned = command.serialise(&mut link).to_vec();
+
+    clock += Duration::new(1, 0);
+
+    let burst = sim.tick(clock, vec![unsigned.clone()]);
+
+    assert!(close(ownship(&mavlink::to_v1(&burst.mavlink[3].1).unwrap()).get_ns_velocity(), 10.0, 0.01));
+    assert_eq!(sim.get_signing().unwrap().get_rejected(), 1);
+    assert_eq!(sim.get_linkstats().get_sigfails(0x19, 0x59), 1);
+    assert_eq!(sim.get_linkstats().get_crcfails(0x19, 0x59), 0);
+
+    let mut sender = Signing::new();
+
+    sender.set_cli("secret:2").unwrap();
+
+    clock += Duration::new(1, 0);
+
+    let burst = sim.tick(clock, vec![sender.sign(&unsigned, clock).unwrap()]);
+
+    assert!(close(ownship(&mavlink::to_v1(&burst.mavlink[3].1).unwrap()).get_ns_velocity(), -10.0, 0.01));
+    assert_eq!(sim.get_signing().unwrap().get_rejected(), 1);
+}
+
+// EOF
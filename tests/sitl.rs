@@ -0,0 +1,82 @@
+//! ADS-B Simulator - see README.md
+//
+// © NewForester, 2018.  Available under MIT licence terms.
+//
+//! Tests of the reception of the messages of a SITL autopilot over UDP.
+//!
+extern crate adsbsim;
+
+use std::net::UdpSocket;
+use std::sync::mpsc;
+use std::time::Duration;
+
+use adsbsim::mavlink;
+use adsbsim::mavlink::{Link, Message};
+use adsbsim::mavlink::{msg0, msg24, msg33, msg202};
+use adsbsim::sitl::{self, Sitl};
+
+// ---------------------------------------------------------------------------
+
+// datagram() returns a datagram of the messages an autopilot might send (and some noise)
+fn datagram() -> (Vec<u8>, Vec<Vec<u8>>) {
+    let mut link = Link::new();
+
+    let heartbeat = msg0::Message::new().serialise(&mut link).to_vec();
+    let position = mavlink::to_v2(msg33::Message::new().serialise(&mut link), 0).unwrap();
+    let ownship = msg202::Message::new().serialise(&mut link).to_vec();
+    let gps = msg24::Message::new().serialise(&mut link).to_vec();
+
+    let mut datagram = vec![0x00, 0x55];
+
+    for message in [&heartbeat, &position, &ownship, &gps].iter() {
+        datagram.extend_from_slice(message);
+    }
+
+    (datagram, vec![heartbeat, position, gps])
+}
+
+// ---------------------------------------------------------------------------
+
+#[test]
+fn messages_of_interest_picked_out() {
+    let (datagram, wanted) = datagram();
+
+    let messages: Vec<Vec<u8>> = sitl::messages(&datagram).iter().map(|message| message.to_vec()).collect();
+
+    assert_eq!(messages, wanted);
+
+    // a message cut short is not a message
+    assert_eq!(sitl::messages(&datagram[.. datagram.len() - 1]).len(), 2);
+}
+
+#[test]
+fn messages_received_over_udp() {
+    let mut input = Sitl::new();
+
+    input.set_cli("0").unwrap();
+    input.open().unwrap();
+
+    let (sender, receiver) = mpsc::channel();
+
+    input.listen(sender).unwrap();
+
+    // port 0 means any so ask the socket which
+    let address = input.get_local_address().unwrap();
+
+    let (datagram, wanted) = datagram();
+
+    UdpSocket::bind("127.0.0.1:0").unwrap().send_to(&datagram, &address).unwrap();
+
+    for message in wanted {
+        assert_eq!(receiver.recv_timeout(Duration::new(5, 0)).unwrap(), message);
+    }
+}
+
+#[test]
+fn bad_parameters_rejected() {
+    assert!(Sitl::new().set_cli("").is_err());
+    assert!(Sitl::new().set_cli("14551:localhost:x").is_err());
+    assert_eq!(Sitl::new().set_cli("14555").unwrap().get_address(), "127.0.0.1:14555");
+}
+
+// EOF
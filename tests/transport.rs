@@ -0,0 +1,221 @@
+//! ADS-B Simulator - see README.md
+//
+// © NewForester, 2018.  Available under MIT licence terms.
+//
+//! Tests of the transports:  the fan out of messages to several transports at
+//! once, the telemetry log file and UDP.
+//!
+extern crate adsbsim;
+
+use std::env;
+use std::fs;
+use std::io::{Error, ErrorKind};
+use std::net::UdpSocket;
+use std::process;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use adsbsim::inet::Inet;
+use adsbsim::mavlink;
+use adsbsim::mavlink::{Link, Message};
+use adsbsim::mavlink::{msg0, msg202};
+use adsbsim::mavlink::signing::Signing;
+use adsbsim::transport::{FanOut, Stdout, Tlog, Transport};
+
+// ---------------------------------------------------------------------------
+
+/// What a Recorder has been sent (message id and frame)
+type Sent = Arc<Mutex<Vec<(u32, Vec<u8>)>>>;
+
+/// The Recorder structure is a transport that records what it is sent (or fails) and, optionally, wants only one message
+struct Recorder {
+    sent:       Sent,
+    fail:       bool,
+    only:       Option<u32>,
+}
+
+impl Transport for Recorder {
+    fn name(&self) -> String {
+        "recorder".to_string()
+    }
+
+    fn send(&mut self, msgid: u32, message: &[u8]) -> Result<usize, Error> {
+        if self.fail {
+            return Err(Error::new(ErrorKind::NotConnected, "recorder failing"));
+        }
+
+        self.sent.lock().unwrap().push((msgid, message.to_vec()));
+
+        Ok(message.len())
+    }
+
+    fn wants(&self, msgid: u32) -> bool {
+        self.only.is_none_or(|only| only == msgid)
+    }
+}
+
+// recorder() returns a transport that records what it is sent and what it records
+fn recorder(only: Option<u32>) -> (Box<Recorder>, Sent) {
+    let sent = Arc::new(Mutex::new(Vec::new()));
+
+    (Box::new(Recorder {sent: sent.clone(), fail: false, only}), sent)
+}
+
+// ---------------------------------------------------------------------------
+
+#[test]
+fn fan_out_to_every_transport() {
+    let one = Arc::new(Mutex::new(Vec::new()));
+    let two = Arc::new(Mutex::new(Vec::new()));
+
+    let mut fanout = FanOut::new();
+
+    fanout.add(Box::new(Recorder {sent: one.clone(), fail: false, only: None}));
+    fanout.add(Box::new(Recorder {sent: Arc::new(Mutex::new(Vec::new())), fail: true, only: None}));
+    fanout.add(Box::new(Recorder {sent: two.clone(), fail: false, only: None}));
+
+    assert_eq!(fanout.len(), 3);
+
+    // one failing transport does not stop the others
+    assert_eq!(fanout.send(0, b"\xfe\x09").unwrap(), 2);
+    assert_eq!(fanout.send(202, b"\xfe\x2a").unwrap(), 2);
+
+    let expected = vec![(0, b"\xfe\x09".to_vec()), (202, b"\xfe\x2a".to_vec())];
+
+    assert_eq!(*one.lock().unwrap(), expected);
+    assert_eq!(*two.lock().unwrap(), expected);
+}
+
+#[test]
+fn each_transport_a_link_of_its_own() {
+    let (all, allsent) = recorder(None);
+    let (some, somesent) = recorder(Some(202));
+
+    let mut fanout = FanOut::new();
+
+    fanout.set_source(7, 8).add(all).add(some);
+
+    // the simulator's own link numbers every message
+    let mut link = Link::new();
+
+    for _ in 0 .. 3 {
+        fanout.send(0, msg0::Message::new().serialise(&mut link)).unwrap();
+        fanout.send(202, msg202::Message::new().serialise(&mut link)).unwrap();
+    }
+
+    let seqnos = |sent: &Sent| -> Vec<u8> {
+        sent.lock().unwrap().iter().map(|(_, frame)| mavlink::seqno(frame).unwrap()).collect()
+    };
+
+    // no gaps in either, whatever the other sends
+    assert_eq!(seqnos(&allsent), vec![0, 1, 2, 3, 4, 5]);
+    assert_eq!(seqnos(&somesent), vec![0, 1, 2]);
+
+    for (_, frame) in allsent.lock().unwrap().iter().chain(somesent.lock().unwrap().iter()) {
+        assert_eq!(mavlink::source(frame), Some((7, 8)));
+        assert_eq!(mavlink::check_crc(frame), Some(true));
+    }
+}
+
+#[test]
+fn fan_out_signs_for_each_transport() {
+    let clock = Instant::now();
+
+    let (one, onesent) = recorder(None);
+    let (two, twosent) = recorder(Some(0));
+
+    let mut signing = Signing::new();
+    let mut receiver = Signing::new();
+
+    signing.set_cli("secret:1").unwrap();
+    receiver.set_cli("secret").unwrap();
+
+    let mut fanout = FanOut::new();
+
+    fanout.set_signing(signing).add(one).add(two);
+
+    let mut link = Link::new();
+
+    fanout.send(202, msg202::Message::new().serialise(&mut link)).unwrap();
+    fanout.send(0, msg0::Message::new().serialise(&mut link)).unwrap();
+
+    assert_eq!(onesent.lock().unwrap().len(), 2);
+    assert_eq!(twosent.lock().unwrap().len(), 1);
+
+    // every message sent is signed (and the signature covers the transport's own sequence number)
+    for &(msgid, ref frame) in onesent.lock().unwrap().iter().chain(twosent.lock().unwrap().iter()) {
+        assert!(frame[0] == 0xfd && frame[2] & 0x01 != 0);
+        assert_eq!(mavlink::msgid(&receiver.verify(frame, clock).unwrap()), Some(msgid));
+    }
+}
+
+#[test]
+fn tlog_records_are_timestamped() {
+    let path = env::temp_dir().join(format!("adsbsim-{}.tlog", process::id()));
+
+    let mut tlog = Tlog::new();
+
+    tlog.set_cli(path.to_str().unwrap()).open().unwrap();
+
+    tlog.send(0, b"\xfe\x09\x00").unwrap();
+    tlog.send(66, b"\xfe\x06\x01").unwrap();
+
+    let bytes = fs::read(&path).unwrap();
+
+    fs::remove_file(&path).unwrap();
+
+    assert_eq!(bytes.len(), 2 * (8 + 3));
+    assert_eq!(&bytes[8 .. 11], b"\xfe\x09\x00");
+    assert_eq!(&bytes[19 .. 22], b"\xfe\x06\x01");
+
+    let usec = bytes[.. 8].iter().fold(0u64, |usec, &byte| usec << 8 | byte as u64);
+
+    // some time after 2018
+    assert!(usec > 1_514_764_800_000_000);
+}
+
+#[test]
+fn udp_transport() {
+    let receiver = UdpSocket::bind("127.0.0.1:0").unwrap();
+
+    receiver.set_read_timeout(Some(Duration::new(5, 0))).unwrap();
+
+    let mut inet = Inet::new();
+
+    inet.set_cli(&format!("0:{}", receiver.local_addr().unwrap().port())).unwrap().open().unwrap();
+
+    // sent from any address, not just the loopback address
+    assert_eq!(inet.get_source(), "0.0.0.0:0");
+
+    let mut fanout = FanOut::new();
+
+    fanout.add(Box::new(inet));
+
+    fanout.send(246, b"\xfe\x26").unwrap();
+
+    let mut buffer = [0u8; 16];
+    let (length, _) = receiver.recv_from(&mut buffer).unwrap();
+
+    assert_eq!(&buffer[.. length], b"\xfe\x26");
+}
+
+#[test]
+fn udp_host_looked_up_when_opened() {
+    let mut inet = Inet::new();
+
+    // syntax only, no lookup, on the command line
+    assert!(Inet::new().set_cli("0:14550:bad host").is_err());
+    assert!(Inet::new().set_cli("0:port:localhost").is_err());
+    assert!(inet.set_cli("0:14550:no-such-host.invalid").is_ok());
+
+    assert!(inet.open().is_err());
+}
+
+#[test]
+fn stdout_message_ids() {
+    assert!(Stdout::new().set_cli("").is_ok());
+    assert!(Stdout::new().set_cli("202,246").is_ok());
+    assert!(Stdout::new().set_cli("202,x").is_err());
+}
+
+// EOF